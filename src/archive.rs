@@ -0,0 +1,44 @@
+//! Tags that keep deleted branch tips reachable long after the session's
+//! undo is gone.
+
+use crate::branch::Branch;
+use crate::Result;
+use chrono::Local;
+use git2::Repository;
+
+/// Tags the tip of `branch` as `<prefix><name>`, returning the tag name. If a
+/// tag of that name already points elsewhere, the short id is appended.
+pub fn tag(repo: &Repository, prefix: &str, branch: &Branch, annotate: bool) -> Result<String> {
+    let target = repo.find_object(branch.id, None)?;
+
+    let mut name = format!("{}{}", prefix, branch.name);
+    if let Ok(existing) = repo.find_reference(&format!("refs/tags/{}", name)) {
+        if existing.peel_to_commit()?.id() == branch.id {
+            return Ok(name);
+        }
+        name = format!("{}-{}", name, branch.short_id());
+    }
+
+    if annotate {
+        let message = format!(
+            "Archived branch '{}' on {}\n",
+            branch.name,
+            Local::now().format("%Y-%m-%d %H:%M:%S %z")
+        );
+        repo.tag(&name, &target, &repo.signature()?, &message, false)?;
+    } else {
+        repo.tag_lightweight(&name, &target, false)?;
+    }
+
+    Ok(name)
+}
+
+pub fn remove(repo: &Repository, name: &str) -> Result<()> {
+    repo.tag_delete(name).map_err(From::from)
+}
+
+/// Names of all tags under `prefix`.
+pub fn list(repo: &Repository, prefix: &str) -> Result<Vec<String>> {
+    let names = repo.tag_names(Some(&format!("{}*", prefix)))?;
+    Ok(names.iter().flatten().map(String::from).collect())
+}
@@ -0,0 +1,93 @@
+//! `.git/delete-branches/audit.log`: a line of JSON for every branch deleted
+//! or restored, never pruned, so there is a record of what a branch pointed
+//! at long after the trash has let go of it. Things worth knowing about a
+//! whole session, like hooks being skipped, get a line of their own.
+//!
+//! Every line has the run it came from, its sequence number in that run,
+//! see [`crate::run`], and the OS user who ran it. Lines are only ever
+//! appended, so everyone sharing a clone shares the one log.
+
+use crate::json::Value;
+use crate::{run, state, Result};
+use git2::{Oid, Repository};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+const FILE: &str = "audit.log";
+
+pub struct Entry {
+    pub time: i64,
+    pub event: String,
+    pub branch: String,
+    pub tip: Oid,
+    /// The OS user who ran the session, missing from older lines.
+    pub user: Option<String>,
+}
+
+/// Appends `event` (`deleted` or `restored`) for the branch `name` at `tip`,
+/// as this run's action number `sequence`.
+pub fn append(repo: &Repository, sequence: u64, event: &str, name: &str, tip: Oid) -> Result<()> {
+    write(
+        repo,
+        sequence,
+        event,
+        vec![
+            ("branch".to_string(), name.into()),
+            ("tip".to_string(), tip.to_string().into()),
+        ],
+    )
+}
+
+/// Appends `event` for the session rather than any one branch.
+pub fn note(repo: &Repository, sequence: u64, event: &str) -> Result<()> {
+    write(repo, sequence, event, Vec::new())
+}
+
+fn write(
+    repo: &Repository,
+    sequence: u64,
+    event: &str,
+    fields: Vec<(String, Value)>,
+) -> Result<()> {
+    let mut line = vec![
+        ("time".to_string(), chrono::Utc::now().timestamp().into()),
+        ("run".to_string(), run::id().into()),
+        ("seq".to_string(), (sequence as i64).into()),
+        ("event".to_string(), event.into()),
+        ("user".to_string(), state::user().into()),
+    ];
+    line.extend(fields);
+    let line = Value::Object(line);
+
+    let dir = state::dir(repo);
+    fs::create_dir_all(&dir)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(FILE))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Every branch's entry, oldest first. Lines that don't parse are skipped.
+pub fn entries(repo: &Repository) -> Result<Vec<Entry>> {
+    let contents = match state::read(&state::dir(repo).join(FILE))? {
+        Some(contents) => contents,
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| {
+            let json = Value::parse(line).ok()?;
+            Some(Entry {
+                time: json.get("time")?.as_f64()? as i64,
+                event: json.get("event")?.as_str()?.to_string(),
+                branch: json.get("branch")?.as_str()?.to_string(),
+                tip: Oid::from_str(json.get("tip")?.as_str()?).ok()?,
+                user: json.get("user").and_then(Value::as_str).map(String::from),
+            })
+        })
+        .collect())
+}
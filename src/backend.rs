@@ -0,0 +1,651 @@
+use crate::branch::{self, Branch, Upstream};
+use crate::head;
+use crate::log::{self, Level};
+use crate::merge::{self, MergeCache, Reachability};
+use crate::{Error, Result};
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, ErrorCode, Oid, Repository, StatusOptions};
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The branch that is never offered for deletion and that merge status is
+/// computed against.
+pub const BASE_BRANCH: &str = "master";
+
+/// How many times a ref update is tried again when another git process
+/// holds the lock on the ref or on `packed-refs`, and how long it waits
+/// before the first retry, doubling after that.
+const LOCK_RETRIES: u32 = 5;
+const LOCK_WAIT: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Where branches come from and where actions on them are carried out.
+pub trait Backend {
+    fn branches(&self) -> Result<Vec<Branch>> {
+        self.scan(&|_| true, &|_, _| {})
+    }
+
+    /// Lists the branches like [`Backend::branches`], but only those whose
+    /// names `wanted` accepts, calling `progress` with how many of how many
+    /// are done as it goes, possibly from several threads. Nothing but the
+    /// name is looked at for a branch that isn't wanted.
+    fn scan(
+        &self,
+        wanted: &dyn Fn(&str) -> bool,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Result<Vec<Branch>>;
+
+    fn delete(&mut self, branch: &Branch) -> Result<()>;
+
+    /// Recreates a branch previously removed with [`Backend::delete`],
+    /// replacing one made with its name since if `force` is set.
+    fn restore(&mut self, branch: &Branch, force: bool) -> Result<()>;
+
+    /// Where a branch with `branch`'s name points now, if there is one.
+    fn tip(&self, branch: &Branch) -> Option<Oid>;
+
+    fn rename(&mut self, branch: &Branch, new_name: &str) -> Result<()>;
+
+    /// Whether tracked files have uncommitted changes.
+    fn has_local_changes(&self) -> Result<bool>;
+
+    /// Makes `branch` the current branch, stashing local changes first if
+    /// asked to.
+    fn checkout(&mut self, branch: &Branch, stash: bool) -> Result<()>;
+
+    /// Makes the base branch the current branch, like [`Backend::checkout`].
+    fn checkout_base(&mut self, stash: bool) -> Result<()>;
+
+    /// The underlying repository, if there is one. Features that need more
+    /// than the operations above are unavailable without it.
+    fn repo(&self) -> Option<&Repository>;
+}
+
+pub struct GitBackend {
+    repo: Repository,
+}
+
+impl GitBackend {
+    pub fn open_from_env() -> Result<Self> {
+        match Repository::open_from_env() {
+            Ok(repo) => Ok(GitBackend { repo }),
+            // libgit2 refuses repositories with extensions it doesn't know,
+            // which is more helpful with what to do about it.
+            Err(e) if e.message().contains("extensions.refstorage") => Err(Error::InvalidValue(
+                "extensions.refStorage".to_string(),
+                "reftable, which libgit2 can't read yet; `git refs migrate --ref-format=files` converts it"
+                    .to_string(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(GitBackend {
+            repo: Repository::open(path)?,
+        })
+    }
+}
+
+/// Below this many branches per thread, collecting metadata in parallel
+/// costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 256;
+
+/// What listing a branch ref gives us before any objects are read.
+struct BranchRef {
+    name: Vec<u8>,
+    id: Oid,
+    is_head: bool,
+}
+
+fn branch_metadata(
+    repo: &Repository,
+    config: &git2::Config,
+    merge_cache: &Mutex<MergeCache>,
+    reachability: Option<&Reachability>,
+    branch: &BranchRef,
+) -> Result<Branch> {
+    let start = Instant::now();
+    let commit = log::timed(
+        Level::Trace,
+        || format!("find_commit {:.7}", branch.id),
+        || repo.find_commit(branch.id),
+    )?;
+
+    let time = branch::commit_time(commit.time());
+
+    let upstream = upstream(repo, config, &branch.name);
+
+    let merge = match reachability {
+        Some(reachability) => Some(merge::merge_info(
+            repo,
+            merge_cache,
+            reachability,
+            commit.id(),
+        )?),
+        None => None,
+    };
+
+    // Names that aren't UTF-8 can't have config of their own.
+    let name = std::str::from_utf8(&branch.name).ok();
+    let description = name
+        .and_then(|name| {
+            config
+                .get_string(&format!("branch.{}.description", name))
+                .ok()
+        })
+        .filter(|description| !description.trim().is_empty());
+    let keep = name
+        .and_then(|name| config.get_bool(&format!("branch.{}.keep", name)).ok())
+        .unwrap_or(false);
+
+    let branch = Branch {
+        time,
+        id: commit.id(),
+        name: String::from_utf8_lossy(&branch.name).into_owned(),
+        name_bytes: branch.name.clone(),
+        is_head: branch.is_head,
+        merged: merge.map(|merge| merge.merged),
+        squash_merged: merge.is_some_and(|merge| merge.squash_merged),
+        fork_point: merge.map(|merge| merge.fork_point),
+        ahead: merge.map(|merge| merge.ahead),
+        behind: merge.map(|merge| merge.behind),
+        push: push_target(repo, config, &branch.name, upstream.as_ref()),
+        orphaned: upstream.is_none() && !on_a_remote(repo, &branch.name),
+        upstream_ahead_behind: upstream
+            .as_ref()
+            .and_then(|upstream| upstream_ahead_behind(repo, commit.id(), upstream)),
+        upstream,
+        pull_request: None,
+        checks: None,
+        protected: false,
+        protection: None,
+        stashes: Vec::new(),
+        wip_commit: false,
+        size: None,
+        owners: Vec::new(),
+        case_clashes: Vec::new(),
+        description,
+        keep,
+        worktree: None,
+    };
+    if log::enabled(Level::Verbose) {
+        log::line(
+            Level::Verbose,
+            format!(
+                "{}: {} ({})",
+                branch.name,
+                classification(&branch),
+                log::took(start.elapsed())
+            ),
+        );
+    }
+    Ok(branch)
+}
+
+/// How `branch` came out of the scan, for `--verbose`.
+fn classification(branch: &Branch) -> String {
+    let mut parts = vec![match branch.merged {
+        Some(true) => "merged".to_string(),
+        Some(false) if branch.squash_merged => "squash-merged".to_string(),
+        Some(false) => "not merged".to_string(),
+        None => format!("no {} to compare with", BASE_BRANCH),
+    }];
+    if let Some(fork_point) = branch.fork_point {
+        parts.push(format!("fork point {:.7}", fork_point));
+    }
+    if let (Some(ahead), Some(behind)) = (branch.ahead, branch.behind) {
+        parts.push(format!("{} ahead {} behind", ahead, behind));
+    }
+    for (kind, upstream) in [("upstream", &branch.upstream), ("push", &branch.push)] {
+        if let Some(upstream) = upstream {
+            parts.push(format!(
+                "{} {}/{}{}",
+                kind,
+                upstream.remote,
+                upstream.branch_name(),
+                if upstream.gone { " (gone)" } else { "" }
+            ));
+        }
+    }
+    if branch.orphaned {
+        parts.push("never pushed".to_string());
+    }
+    if branch.keep {
+        parts.push(format!("branch.{}.keep", branch.name));
+    }
+    parts.join(", ")
+}
+
+impl GitBackend {
+    fn find_branch(&self, branch: &Branch) -> Result<git2::Branch<'_>> {
+        if let Ok(name) = std::str::from_utf8(&branch.name_bytes) {
+            return Ok(self.repo.find_branch(name, BranchType::Local)?);
+        }
+
+        for candidate in self.repo.branches(Some(BranchType::Local))? {
+            let (candidate, _) = candidate?;
+            if candidate.name_bytes()? == branch.name_bytes.as_slice() {
+                return Ok(candidate);
+            }
+        }
+
+        Err(git2::Error::from_str(&format!("branch '{}' not found", branch.name)).into())
+    }
+
+    /// A branch that can't exist alongside one named `name`, since one of
+    /// their refs would have to be a directory: `feature` for `feature/x`,
+    /// or the other way round.
+    fn conflicting_branch(&self, name: &str) -> Result<Option<String>> {
+        for other in self.repo.branches(Some(BranchType::Local))? {
+            let (other, _) = other?;
+            let other = match other.name()? {
+                Some(other) => other.to_string(),
+                None => continue,
+            };
+            let (shorter, longer) = if other.len() < name.len() {
+                (other.as_str(), name)
+            } else {
+                (name, other.as_str())
+            };
+            if longer
+                .strip_prefix(shorter)
+                .is_some_and(|rest| rest.starts_with('/'))
+            {
+                return Ok(Some(other));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Runs `update`, trying again a few times if a lock someone else holds
+/// got in its way.
+fn retry_locked<T>(mut update: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut wait = LOCK_WAIT;
+    for _ in 0..LOCK_RETRIES {
+        match update() {
+            Err(Error::Git(e)) if e.code() == ErrorCode::Locked => {
+                std::thread::sleep(wait);
+                wait *= 2;
+            }
+            result => return result,
+        }
+    }
+    update()
+}
+
+impl Backend for GitBackend {
+    fn scan(
+        &self,
+        wanted: &dyn Fn(&str) -> bool,
+        progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Result<Vec<Branch>> {
+        let repo = &self.repo;
+        let base = repo
+            .find_branch(BASE_BRANCH, BranchType::Local)
+            .ok()
+            .and_then(|branch| branch.get().target());
+
+        // Listing the refs themselves is cheap, as long as it stays at their
+        // names and targets: libgit2 reads `packed-refs` once for all of
+        // them, but resolves HEAD anew for every `is_head()`. Everything that
+        // needs to read objects or walk history happens afterwards, spread
+        // over threads, and only for wanted branches.
+        let head = repo
+            .find_reference("HEAD")?
+            .symbolic_target_bytes()
+            .map(<[u8]>::to_vec)
+            // git counts the branch a rebase or bisect is on as checked out.
+            .or_else(|| {
+                head::interrupted(repo).map(|(name, _)| format!("refs/heads/{}", name).into_bytes())
+            });
+        let mut refs = Vec::new();
+        for branch in repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            let name = branch.name_bytes()?;
+            if name == BASE_BRANCH.as_bytes() || !wanted(&String::from_utf8_lossy(name)) {
+                continue;
+            }
+            let id = match branch.get().target() {
+                Some(id) => id,
+                None => branch.get().peel_to_commit()?.id(),
+            };
+            refs.push(BranchRef {
+                name: name.to_vec(),
+                id,
+                is_head: head.as_deref() == Some(branch.get().name_bytes()),
+            });
+        }
+
+        log::line(
+            Level::Verbose,
+            format!(
+                "{} branch(es) to look at, against {}",
+                refs.len(),
+                base.map_or("no base branch".to_string(), |base| format!(
+                    "{} at {:.7}",
+                    BASE_BRANCH, base
+                ))
+            ),
+        );
+        let merge_cache = Mutex::new(MergeCache::load(repo)?);
+        let reachability = base.map(Reachability::new);
+        let total = refs.len();
+        let done = AtomicUsize::new(0);
+        let metadata = |repo: &Repository, config: &git2::Config, branch: &BranchRef| {
+            let branch =
+                branch_metadata(repo, config, &merge_cache, reachability.as_ref(), branch)?;
+            progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+            Ok(branch)
+        };
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(refs.len() / PARALLEL_THRESHOLD + 1);
+
+        let mut branches = if threads <= 1 {
+            let config = repo.config()?.snapshot()?;
+            refs.iter()
+                .map(|branch| metadata(repo, &config, branch))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            // Repository handles can't be shared between threads, so each
+            // worker opens its own.
+            let path = repo.path();
+            let metadata = &metadata;
+            let chunk_size = refs.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = refs
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            let repo = Repository::open(path)?;
+                            let config = repo.config()?.snapshot()?;
+                            chunk
+                                .iter()
+                                .map(|branch| metadata(&repo, &config, branch))
+                                .collect::<Result<Vec<_>>>()
+                        })
+                    })
+                    .collect();
+
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().expect("branch metadata worker panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?
+            .into_iter()
+            .flatten()
+            .collect()
+        };
+
+        merge_cache.into_inner().unwrap().save()?;
+
+        branches.sort_unstable_by_key(|branch| branch.time);
+
+        Ok(branches)
+    }
+
+    fn delete(&mut self, branch: &Branch) -> Result<()> {
+        log::timed(
+            Level::Verbose,
+            || format!("delete refs/heads/{} at {:.7}", branch.name, branch.id),
+            || retry_locked(|| Ok(self.find_branch(branch)?.delete()?)),
+        )
+    }
+
+    fn restore(&mut self, branch: &Branch, force: bool) -> Result<()> {
+        log::timed(
+            Level::Verbose,
+            || format!("restore refs/heads/{} at {:.7}", branch.name, branch.id),
+            || self.restore_ref(branch, force),
+        )
+    }
+
+    fn tip(&self, branch: &Branch) -> Option<Oid> {
+        self.find_branch(branch).ok()?.get().target()
+    }
+
+    fn rename(&mut self, branch: &Branch, new_name: &str) -> Result<()> {
+        log::timed(
+            Level::Verbose,
+            || format!("rename refs/heads/{} to {}", branch.name, new_name),
+            || {
+                Ok(self
+                    .find_branch(branch)?
+                    .rename(new_name, false)
+                    .map(|_| ())?)
+            },
+        )
+    }
+
+    fn has_local_changes(&self) -> Result<bool> {
+        if self.repo.is_bare() {
+            return Ok(false);
+        }
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).exclude_submodules(true);
+        Ok(!self.repo.statuses(Some(&mut options))?.is_empty())
+    }
+
+    fn checkout(&mut self, branch: &Branch, stash: bool) -> Result<()> {
+        let name = std::str::from_utf8(&branch.name_bytes)
+            .map_err(|_| git2::Error::from_str("can't check out a branch with a non-UTF-8 name"))?;
+        self.checkout_name(name, stash)
+    }
+
+    fn checkout_base(&mut self, stash: bool) -> Result<()> {
+        self.checkout_name(BASE_BRANCH, stash)
+    }
+
+    fn repo(&self) -> Option<&Repository> {
+        Some(&self.repo)
+    }
+}
+
+impl GitBackend {
+    /// Points `refs/heads/<branch>` back at the branch's tip.
+    fn restore_ref(&self, branch: &Branch, force: bool) -> Result<()> {
+        match std::str::from_utf8(&branch.name_bytes) {
+            Ok(name) => {
+                if let Some(other) = self.conflicting_branch(name)? {
+                    return Err(git2::Error::from_str(&format!(
+                        "'{}' is in the way, git can't have both it and '{}'",
+                        other, name
+                    ))
+                    .into());
+                }
+                let commit = self.repo.find_commit(branch.id)?;
+                retry_locked(|| Ok(self.repo.branch(name, &commit, force).map(|_| ())?))
+            }
+            // git2 only takes ref names as `&str`, so hand the raw bytes to git.
+            Err(_) => {
+                let mut refname = b"refs/heads/".to_vec();
+                refname.extend_from_slice(&branch.name_bytes);
+                let status = std::process::Command::new("git")
+                    .arg("update-ref")
+                    .arg(os_string(refname))
+                    .arg(branch.id.to_string())
+                    // No old value to check against when replacing whatever is there.
+                    .args((!force).then(|| git2::Oid::zero().to_string()))
+                    .current_dir(self.repo.path())
+                    .status()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(git2::Error::from_str(&format!(
+                        "git update-ref failed to restore '{}'",
+                        branch.name
+                    ))
+                    .into())
+                }
+            }
+        }
+    }
+
+    fn checkout_name(&mut self, name: &str, stash: bool) -> Result<()> {
+        if stash {
+            let signature = self.repo.signature()?;
+            let message = format!("delete-branches: before checking out {}", name);
+            self.repo.stash_save(&signature, &message, None)?;
+        }
+
+        let refname = format!("refs/heads/{}", name);
+        let checked_out = self
+            .repo
+            .revparse_single(&refname)
+            .and_then(|target| {
+                self.repo
+                    .checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+            })
+            .and_then(|()| self.repo.set_head(&refname));
+        match checked_out {
+            Ok(()) => Ok(()),
+            // Nothing was checked out, so the changes go back where they were.
+            Err(e) if stash => match self.repo.stash_pop(0, None) {
+                Ok(()) => Err(e.into()),
+                Err(_) => Err(Error::LeftStashed(e)),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn upstream(repo: &Repository, config: &git2::Config, name: &[u8]) -> Option<Upstream> {
+    let name = std::str::from_utf8(name).ok()?;
+    let refname = format!("refs/heads/{}", name);
+
+    let remote = log::timed(
+        Level::Trace,
+        || format!("branch_upstream_remote {}", refname),
+        || repo.branch_upstream_remote(&refname),
+    )
+    .ok()?;
+    let remote = remote.as_str()?;
+    if remote == "." {
+        return None;
+    }
+
+    let merge = config.get_string(&format!("branch.{}.merge", name)).ok()?;
+    let tracking = log::timed(
+        Level::Trace,
+        || format!("branch_upstream_name {}", refname),
+        || repo.branch_upstream_name(&refname),
+    )
+    .ok()?;
+    let tracking = tracking.as_str()?.to_string();
+
+    Some(Upstream {
+        remote: remote.to_string(),
+        merge,
+        gone: find_reference(repo, &tracking).is_err(),
+        tracking,
+    })
+}
+
+/// Whether any remote has a remote-tracking ref for `name`, pushed without
+/// setting an upstream or the other way around.
+fn on_a_remote(repo: &Repository, name: &[u8]) -> bool {
+    let name = match std::str::from_utf8(name) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+    let remotes = match repo.remotes() {
+        Ok(remotes) => remotes,
+        Err(_) => return false,
+    };
+    remotes
+        .iter()
+        .flatten()
+        .any(|remote| find_reference(repo, &format!("refs/remotes/{}/{}", remote, name)).is_ok())
+}
+
+fn find_reference<'r>(
+    repo: &'r Repository,
+    name: &str,
+) -> Result<git2::Reference<'r>, git2::Error> {
+    log::timed(
+        Level::Trace,
+        || format!("find_reference {}", name),
+        || repo.find_reference(name),
+    )
+}
+
+fn upstream_ahead_behind(
+    repo: &Repository,
+    tip: Oid,
+    upstream: &Upstream,
+) -> Option<(usize, usize)> {
+    let tracking = find_reference(repo, &upstream.tracking).ok()?.target()?;
+    log::timed(
+        Level::Trace,
+        || format!("graph_ahead_behind {:.7} {}", tip, upstream.tracking),
+        || repo.graph_ahead_behind(tip, tracking),
+    )
+    .ok()
+}
+
+/// Where `git push` sends the branch in a triangular workflow, i.e. when
+/// `branch.<name>.pushRemote` or `remote.pushDefault` names a different
+/// remote than the upstream's.
+fn push_target(
+    repo: &Repository,
+    config: &git2::Config,
+    name: &[u8],
+    upstream: Option<&Upstream>,
+) -> Option<Upstream> {
+    let name = std::str::from_utf8(name).ok()?;
+
+    let remote = match config
+        .get_string(&format!("branch.{}.pushRemote", name))
+        .or_else(|_| config.get_string("remote.pushDefault"))
+    {
+        Ok(remote) => remote,
+        Err(_) if upstream.is_none() && repo.is_bare() => return mirrored(repo, config, name),
+        Err(_) => return None,
+    };
+    if remote == "." || upstream.is_some_and(|upstream| upstream.remote == remote) {
+        return None;
+    }
+
+    let tracking = format!("refs/remotes/{}/{}", remote, name);
+    Some(Upstream {
+        merge: format!("refs/heads/{}", name),
+        gone: find_reference(repo, &tracking).is_err(),
+        tracking,
+        remote,
+    })
+}
+
+/// The same branch on the remote a bare repository mirrors, if it is a
+/// mirror. Its branches are the remote's own rather than remote-tracking
+/// refs, so it never counts as gone.
+fn mirrored(repo: &Repository, config: &git2::Config, name: &str) -> Option<Upstream> {
+    let remotes = repo.remotes().ok()?;
+    let remote = remotes.iter().flatten().find(|remote| {
+        config
+            .get_bool(&format!("remote.{}.mirror", remote))
+            .unwrap_or(false)
+    })?;
+    Some(Upstream {
+        remote: remote.to_string(),
+        merge: format!("refs/heads/{}", name),
+        tracking: format!("refs/remotes/{}/{}", remote, name),
+        gone: false,
+    })
+}
+
+#[cfg(unix)]
+fn os_string(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
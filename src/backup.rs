@@ -0,0 +1,154 @@
+//! `--backup-remote <name>` and `--backup-bundle <path>`: a copy of every
+//! branch somewhere off the repository before it is deleted, for batch
+//! cleanups that need more of a safety net than the trash.
+//!
+//! Both keep branches as `refs/archive/<branch>`. The remote gets each one
+//! pushed there; the bundle is rewritten with each one added to whatever it
+//! already held, so it ends up with every branch ever backed up into it.
+//! Either can be fetched back from:
+//!
+//! ```sh
+//! git fetch backup.bundle 'refs/archive/*:refs/heads/*'
+//! ```
+//!
+//! A branch that can't be backed up is kept.
+
+use crate::branch::Branch;
+use crate::remote;
+use crate::session::Session;
+use crate::state;
+use crate::theme::Role;
+use crate::{Error, Result};
+use git2::Repository;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PREFIX: &str = "refs/archive/";
+
+impl Session<'_> {
+    /// Backs `branch` up as `--backup-remote` and `--backup-bundle` ask.
+    /// `false` if that failed and the branch should be kept.
+    pub fn back_up(&mut self, branch: &Branch) -> Result<bool> {
+        if self.cli.backup_remote.is_none() && self.cli.backup_bundle.is_none() {
+            return Ok(true);
+        }
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => return Ok(true),
+        };
+        if branch.has_lossy_name() {
+            return self.not_backed_up(branch, "its name isn't valid UTF-8".to_string());
+        }
+        let archived = format!("{}{}", PREFIX, branch.name);
+
+        if let Some(name) = &self.cli.backup_remote {
+            let refspec = format!("+refs/heads/{}:{}", branch.name, archived);
+            if let Err(e) = remote::push(repo, &self.config, name, &refspec) {
+                return self.not_backed_up(branch, e.to_string());
+            }
+            write!(
+                self.stdout,
+                "Backed up '{}' to {} as {}\r\n",
+                branch.name, name, archived
+            )?;
+        }
+
+        if let Some(path) = &self.cli.backup_bundle {
+            if let Err(e) = add_to_bundle(repo, path, &archived, branch) {
+                return self.not_backed_up(branch, e.to_string());
+            }
+            write!(
+                self.stdout,
+                "Backed up '{}' to {}\r\n",
+                branch.name,
+                path.display()
+            )?;
+        }
+        Ok(true)
+    }
+
+    fn not_backed_up(&mut self, branch: &Branch, why: String) -> Result<bool> {
+        let failed = format!("Could not back up '{}', keeping it: {}", branch.name, why);
+        write!(
+            self.stdout,
+            "{}\r\n",
+            self.theme.paint(Role::Warning, failed)
+        )?;
+        Ok(false)
+    }
+}
+
+/// Rewrites the bundle at `path` with `branch` in it as `archived`.
+///
+/// `git bundle` can't add to a bundle, and only bundles refs the repository
+/// has, so what the bundle already holds is fetched back under its own names
+/// first. That happens in a scratch repository borrowing this one's objects,
+/// so the repository's own `refs/archive/*` are left as they were.
+fn add_to_bundle(repo: &Repository, path: &Path, archived: &str, branch: &Branch) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let scratch_dir = state::cache_dir(repo).join("bundle");
+    // Left over from a run that didn't get to clean up.
+    std::fs::remove_dir_all(&scratch_dir).ok();
+
+    let result = (|| {
+        let scratch = Repository::init_bare(&scratch_dir)?;
+        let info = scratch_dir.join("objects").join("info");
+        std::fs::create_dir_all(&info)?;
+        let objects = std::path::absolute(state::common_dir(repo).join("objects"))?;
+        std::fs::write(info.join("alternates"), format!("{}\n", objects.display()))?;
+
+        let mut bundled = HashSet::new();
+        if path.exists() {
+            let mut list = Command::new("git");
+            list.args(["bundle", "list-heads"]).arg(&path);
+            let heads = git(&scratch, list)?;
+            bundled.extend(
+                heads
+                    .lines()
+                    .filter_map(|line| line.split_once(' '))
+                    .map(|(_, name)| name.to_string())
+                    .filter(|name| name.starts_with(PREFIX)),
+            );
+
+            let mut fetch = Command::new("git");
+            fetch
+                .args(["fetch", "--quiet"])
+                .arg(&path)
+                .arg(format!("+{0}*:{0}*", PREFIX));
+            git(&scratch, fetch)?;
+        }
+        scratch.reference(archived, branch.id, true, "delete-branches: back up")?;
+        bundled.insert(archived.to_string());
+
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let mut names: Vec<_> = bundled.iter().collect();
+        names.sort();
+        let mut create = Command::new("git");
+        create
+            .args(["bundle", "create", "--quiet"])
+            .arg(&partial)
+            .args(names);
+        git(&scratch, create)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&scratch_dir).ok();
+    result
+}
+
+/// Runs `git` in the repository, returning what it printed.
+fn git(repo: &Repository, mut git: Command) -> Result<String> {
+    let output = git.current_dir(repo.path()).output()?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    Err(Error::InvalidValue(
+        "--backup-bundle".to_string(),
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
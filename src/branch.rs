@@ -0,0 +1,150 @@
+use crate::forge::{Ci, PullRequest};
+use crate::protection::Protection;
+use crate::size::Size;
+use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use git2::Oid;
+use std::path::PathBuf;
+
+/// A local branch offered for deletion.
+///
+/// This is plain data so the session can run against a real repository or a
+/// fixture alike; whatever backend produced it is responsible for acting on it.
+#[derive(Clone)]
+pub struct Branch {
+    /// When the tip was committed, in the committer's time zone.
+    pub time: DateTime<FixedOffset>,
+    pub id: Oid,
+    /// Display form of the name, with invalid UTF-8 replaced.
+    pub name: String,
+    /// The name exactly as stored in the ref, used when acting on the branch.
+    pub name_bytes: Vec<u8>,
+    pub is_head: bool,
+    /// Whether the tip is reachable from the base branch, when known.
+    pub merged: Option<bool>,
+    /// Whether the branch's changes landed on the base branch as a single
+    /// squashed commit.
+    pub squash_merged: bool,
+    /// Where the branch left the base branch, when known; the tip itself for
+    /// merged branches.
+    pub fork_point: Option<Oid>,
+    /// Commits on the branch that the base branch doesn't have, when known.
+    pub ahead: Option<usize>,
+    /// Commits on the base branch that this one doesn't have, when known.
+    pub behind: Option<usize>,
+    /// The remote branch this one tracks, if it was configured with one.
+    pub upstream: Option<Upstream>,
+    /// Where the branch is pushed to when that isn't its upstream's remote.
+    pub push: Option<Upstream>,
+    /// Never pushed anywhere: no upstream, and no remote has a branch of
+    /// the same name.
+    pub orphaned: bool,
+    /// Commits ahead of and behind the upstream's remote-tracking ref.
+    pub upstream_ahead_behind: Option<(usize, usize)>,
+    /// The branch's latest pull request, with `--check-prs`.
+    pub pull_request: Option<PullRequest>,
+    /// How CI went on the tip, with `--check-ci` or as last cached.
+    pub checks: Option<Ci>,
+    /// Whether the forge protects the branch from deletion.
+    pub protected: bool,
+    /// The configured protection that keeps the branch out of the session.
+    pub protection: Option<Protection>,
+    /// Stash entries made on the branch, with `--interactive-rebase-safe`.
+    pub stashes: Vec<String>,
+    /// Whether the tip is a `WIP`, `fixup!` or `squash!` commit, with
+    /// `--interactive-rebase-safe`.
+    pub wip_commit: bool,
+    /// Other branches whose names differ from this one only in case.
+    pub case_clashes: Vec<String>,
+    /// `branch.<name>.description`, as `git branch --edit-description` sets
+    /// it.
+    pub description: Option<String>,
+    /// `branch.<name>.keep`: never offer the branch for deletion.
+    pub keep: bool,
+    /// Linked worktree the branch is checked out in, with `--all-worktrees`.
+    pub worktree: Option<PathBuf>,
+    /// What the branch adds to the repository, with `--sizes`.
+    pub size: Option<Size>,
+    /// CODEOWNERS owners of what the branch touches, most files first,
+    /// with `--owners` or `--owner`.
+    pub owners: Vec<String>,
+}
+
+impl Branch {
+    /// The remote branch that `D` deletes: the push destination in
+    /// triangular workflows, the upstream otherwise.
+    pub fn remote_target(&self) -> Option<&Upstream> {
+        self.push.as_ref().or(self.upstream.as_ref())
+    }
+
+    /// The namespace the branch lives in: everything up to and including the
+    /// last `/`, or an empty string for top-level branches.
+    pub fn prefix(&self) -> &str {
+        match self.name.rfind('/') {
+            Some(i) => &self.name[..=i],
+            None => "",
+        }
+    }
+
+    /// How many namespaces deep the branch is, 0 for top-level branches.
+    /// Batches are deleted deepest first, so every directory under
+    /// `refs/heads/` is emptied before the refs next to it go.
+    pub fn depth(&self) -> usize {
+        self.name.matches('/').count()
+    }
+
+    /// Whether `name` is only an approximation of the real ref name.
+    pub fn has_lossy_name(&self) -> bool {
+        std::str::from_utf8(&self.name_bytes).is_err()
+    }
+
+    pub fn short_id(&self) -> String {
+        self.id.to_string()[..7].to_string()
+    }
+}
+
+/// `branch.<name>.remote` and `branch.<name>.merge` of a branch.
+#[derive(Clone)]
+pub struct Upstream {
+    pub remote: String,
+    /// The branch's ref on the remote, e.g. `refs/heads/topic`.
+    pub merge: String,
+    /// The local remote-tracking ref, e.g. `refs/remotes/origin/topic`.
+    pub tracking: String,
+    /// The remote-tracking ref no longer exists, usually because the branch
+    /// was deleted upstream and then pruned.
+    pub gone: bool,
+}
+
+impl Upstream {
+    pub fn branch_name(&self) -> &str {
+        self.merge
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&self.merge)
+    }
+}
+
+/// When a commit was made, in the time zone it was made in.
+pub fn commit_time(time: git2::Time) -> DateTime<FixedOffset> {
+    let offset =
+        FixedOffset::east_opt(time.offset_minutes() * 60).unwrap_or_else(|| FixedOffset::east(0));
+    offset
+        .timestamp_opt(time.seconds(), 0)
+        .single()
+        .unwrap_or_else(|| offset.timestamp(0, 0))
+}
+
+/// How many whole days ago `time` was.
+pub fn days_since(time: DateTime<FixedOffset>) -> i64 {
+    Local::now().signed_duration_since(time).num_days()
+}
+
+/// Renders how long ago `time` was in a single rough unit, e.g. "14 months".
+pub fn age(time: DateTime<FixedOffset>) -> String {
+    let days = days_since(time).max(0);
+    let (count, unit) = match days {
+        0..=59 => (days, "day"),
+        60..=729 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
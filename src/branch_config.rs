@@ -0,0 +1,92 @@
+//! A branch's own section of git config, `branch.<name>.*`. Deleting a
+//! branch drops it, and with it the upstream (`remote` and `merge`), the
+//! push remote and whatever else was set for the branch, so it is kept with
+//! every deletion and written back when the branch is undone or restored
+//! from the trash.
+
+use crate::json::Value;
+use crate::Result;
+use git2::{ConfigLevel, Repository};
+
+/// The variables of a `branch.<name>` section and their values, in the
+/// order git lists them. A variable set more than once appears once per
+/// value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BranchConfig(Vec<(String, String)>);
+
+impl BranchConfig {
+    /// What the repository's own config sets for branch `name`.
+    pub fn read(repo: &Repository, name: &str) -> Result<BranchConfig> {
+        let config = repo.config()?.open_level(ConfigLevel::Local)?;
+        let mut variables = Vec::new();
+
+        let entries = config.entries(Some("^branch\\."))?;
+        for entry in &entries {
+            let entry = entry?;
+            let (key, value) = match (entry.name(), entry.value()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+            // The subsection is everything between the first and the last
+            // dot, so `branch.a.b.remote` belongs to `a.b`, not `a`.
+            let variable = key
+                .strip_prefix("branch.")
+                .and_then(|rest| rest.rsplit_once('.'))
+                .filter(|(subsection, _)| *subsection == name)
+                .map(|(_, variable)| variable);
+            if let Some(variable) = variable {
+                variables.push((variable.to_string(), value.to_string()));
+            }
+        }
+        Ok(BranchConfig(variables))
+    }
+
+    /// Sets the variables for branch `name` again.
+    pub fn write(&self, repo: &Repository, name: &str) -> Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let mut config = repo.config()?.open_level(ConfigLevel::Local)?;
+        let mut written: Vec<&str> = Vec::new();
+        for (variable, value) in &self.0 {
+            let key = format!("branch.{}.{}", name, variable);
+            if written.contains(&variable.as_str()) {
+                // Matches no value, so this one is added to the others.
+                config.set_multivar(&key, "$^", value)?;
+            } else {
+                config.set_str(&key, value)?;
+                written.push(variable);
+            }
+        }
+        Ok(())
+    }
+
+    /// `[["remote", "origin"], ["merge", "refs/heads/topic"]]`.
+    pub fn to_json(&self) -> Value {
+        let pairs: Vec<Value> = self
+            .0
+            .iter()
+            .map(|(variable, value)| {
+                let pair: Vec<Value> = vec![variable.as_str().into(), value.as_str().into()];
+                pair.into()
+            })
+            .collect();
+        pairs.into()
+    }
+
+    /// Reads back [`BranchConfig::to_json`], skipping anything malformed.
+    pub fn from_json(json: &Value) -> BranchConfig {
+        let variables = json
+            .as_array()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|pair| match pair.as_array()? {
+                [variable, value] => {
+                    Some((variable.as_str()?.to_string(), value.as_str()?.to_string()))
+                }
+                _ => None,
+            })
+            .collect();
+        BranchConfig(variables)
+    }
+}
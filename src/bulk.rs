@@ -0,0 +1,75 @@
+//! Deleting many branches in one go, after `a`, `d` on a group or the final
+//! review of marked branches. A progress bar stays under the per-branch
+//! results while it runs, and Esc stops it: the branches deleted so far stay
+//! deleted, and can still be undone, and the rest are kept.
+
+use crate::input::Input;
+use crate::output::Output;
+use crate::theme::{Role, Theme};
+use crate::Result;
+use crossterm::terminal::{Clear, ClearType};
+use std::io::Write;
+
+const BAR_WIDTH: usize = 30;
+
+pub struct Bulk {
+    done: usize,
+    total: usize,
+    drawn: bool,
+}
+
+impl Bulk {
+    /// A run of `total` deletions, `done` of which already happened.
+    pub fn new(done: usize, total: usize) -> Bulk {
+        Bulk {
+            done,
+            total,
+            drawn: false,
+        }
+    }
+
+    /// Clears the bar for the next branch's results. `false`, saying how
+    /// many branches are kept, if Esc was pressed to stop.
+    pub fn next(&mut self, stdout: &mut Output, input: &mut Input, theme: &Theme) -> Result<bool> {
+        self.clear(stdout)?;
+        if !input.drain()? {
+            return Ok(true);
+        }
+        let stopped = format!(
+            "Stopped, keeping the {} branch(es) left",
+            self.total - self.done
+        );
+        write!(stdout, "{}\r\n", theme.paint(Role::Warning, stopped))?;
+        Ok(false)
+    }
+
+    /// Counts a branch as dealt with and redraws the bar.
+    pub fn advance(&mut self, stdout: &mut Output, theme: &Theme) -> Result<()> {
+        self.done += 1;
+        if !stdout.is_tty() || self.done >= self.total {
+            return Ok(());
+        }
+        let filled = self.done * BAR_WIDTH / self.total;
+        write!(
+            stdout,
+            "[{}{}] {}/{}, Esc to stop",
+            theme.paint(Role::Badge, "█".repeat(filled)),
+            " ".repeat(BAR_WIDTH - filled),
+            self.done,
+            self.total
+        )?;
+        stdout.flush()?;
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// Clears the bar, if it is drawn.
+    pub fn clear(&mut self, stdout: &mut Output) -> Result<()> {
+        if self.drawn {
+            write!(stdout, "\r")?;
+            crossterm::execute!(stdout, Clear(ClearType::CurrentLine))?;
+            self.drawn = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1331 @@
+use crate::{Error, Result};
+use std::path::PathBuf;
+
+pub const DEFAULT_ARCHIVE_PREFIX: &str = "archive/";
+
+/// Command line options.
+#[derive(Clone, Default)]
+pub struct Cli {
+    /// A subcommand to run instead of the interactive session.
+    pub command: Option<Command>,
+    /// Annotate branches with the linked worktree they are checked out in.
+    pub all_worktrees: bool,
+    /// Prune stale remote-tracking refs of every remote after the session
+    /// without asking.
+    pub prune: bool,
+    /// Tag each branch tip under this prefix before deleting it.
+    pub archive_tags: Option<String>,
+    /// Make archive tags annotated, recording when the branch was deleted.
+    pub archive_annotate: bool,
+    /// Push each branch to this remote as `refs/archive/<branch>` before
+    /// deleting it.
+    pub backup_remote: Option<String>,
+    /// Add each branch to this bundle as `refs/archive/<branch>` before
+    /// deleting it.
+    pub backup_bundle: Option<PathBuf>,
+    /// Leave a note on each deleted branch's tip in `refs/notes/delete-branches`.
+    pub tombstones: Option<TombstoneMode>,
+    /// Offer to remove previously created archive tags instead of triaging
+    /// branches.
+    pub clean_archive: bool,
+    /// Only offer branches that forked from the base branch at or after
+    /// this revision.
+    pub since: Option<String>,
+    /// Only offer branches that forked from the base branch at or before
+    /// this revision.
+    pub until: Option<String>,
+    /// Only offer branches containing one of these commits.
+    pub contains: Vec<String>,
+    /// Only offer branches containing none of these commits.
+    pub no_contains: Vec<String>,
+    /// Only offer branches whose last commit's author matches one of these.
+    pub author: Vec<String>,
+    /// Match `author` against every author of the branch's own commits.
+    pub all_authors: bool,
+    /// Only offer branches CODEOWNERS gives to one of these owners.
+    pub owner: Vec<String>,
+    /// Only offer branches at least this many commits behind the base
+    /// branch with nothing ahead of it.
+    pub behind_only: Option<usize>,
+    /// Only offer branches never pushed anywhere.
+    pub orphaned_only: bool,
+    /// Only offer branches older than their `delete-branches.retention`.
+    pub expired: bool,
+    /// Leave the newest this many branches of each namespace out.
+    pub keep_last_n_per_prefix: Option<usize>,
+    /// Leave out branches committed to in this many days, or by someone who
+    /// committed anywhere in them.
+    pub exclude_recent_authors_days: Option<i64>,
+    /// Only offer branches whose names match one of these globs.
+    pub only: Vec<String>,
+    /// Print nothing but the names of deleted branches.
+    pub quiet: bool,
+    /// How much to log, one for each `-v`; see [`crate::log`].
+    pub verbose: u8,
+    /// Where to log, instead of stderr.
+    pub log_file: Option<PathBuf>,
+    /// Whether branch names and patterns match regardless of case, instead
+    /// of as config has it.
+    pub ignore_case: Option<bool>,
+    /// Only offer the branches named in this file, one per line, `-` for
+    /// stdin.
+    pub from_file: Option<PathBuf>,
+    /// Offer the current branch too, switching to the base branch before
+    /// deleting it.
+    pub include_head: bool,
+    /// Show an overview of the branches first, and pick which to go through
+    /// first.
+    pub dashboard: bool,
+    /// Ask about whole namespaces (`feature/`, `user/alice/`) before their
+    /// branches.
+    pub group_by_prefix: bool,
+    /// Show the state of each branch's pull request on the `origin` forge.
+    pub check_prs: bool,
+    /// `check_prs`, also showing whether CI passed on each branch's tip,
+    /// which takes more requests.
+    pub check_ci: bool,
+    /// Estimate how much each branch adds to the repository.
+    pub sizes: bool,
+    /// Show who CODEOWNERS says owns each branch.
+    pub owners: bool,
+    /// Lift `delete-branches.protect` patterns from branches matching these
+    /// globs, after asking.
+    pub unprotect: Vec<String>,
+    /// Flag branches with stashes or WIP/fixup tips and make deleting them
+    /// take typing their name.
+    pub rebase_safe: bool,
+    /// Run none of the repository's hooks, for when a broken one blocks a
+    /// cleanup.
+    pub no_hooks: bool,
+    /// Only mark branches during the session, and delete them after a
+    /// final review. The default, unless `immediate`.
+    pub mark: bool,
+    /// Delete branches as they are decided on, without the final review.
+    pub immediate: bool,
+    /// Put the seatbelt on even where config takes it off.
+    pub seatbelt: bool,
+    /// Key profile for the branch prompt, `default` or `vim`.
+    pub keys: Option<String>,
+    /// Write `report` as JSON.
+    pub json: bool,
+    /// Hash branch names and identities in `report` and `graph` output.
+    pub anonymize: bool,
+    /// What `graph` draws in.
+    pub graph_format: GraphFormat,
+    /// With `gc`, expire reflog entries of unreachable commits and prune
+    /// them right away.
+    pub expire_reflogs: bool,
+    /// Whether to color the output.
+    pub color: ColorChoice,
+    /// How last commit dates are shown.
+    pub date: DateFormat,
+    /// Decisions to replay without asking.
+    pub script: Option<PathBuf>,
+    /// Where to write out the decisions made in the session.
+    pub record: Option<PathBuf>,
+    /// Unix domain socket to stream session events to.
+    pub events_socket: Option<PathBuf>,
+    /// Endpoint to POST the report of the run to.
+    pub upload_report: Option<String>,
+    /// Directories to find repositories in and triage one after another.
+    pub recurse: Vec<PathBuf>,
+    /// Run against a synthetic branch set instead of the current repository.
+    pub fixture: Option<PathBuf>,
+    /// The branch `restore` brings back.
+    pub branch: Option<String>,
+    /// The recorded plans `plan diff` compares, old then new.
+    pub plans: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Refresh the forge cache for every branch, within the API rate limit.
+    Sync,
+    /// Print branch hygiene statistics.
+    Report,
+    /// Print how branches fork from and merge into the base branch.
+    Graph,
+    /// List the trash, or bring a deleted branch back.
+    Restore,
+    /// Compare two recorded plans.
+    PlanDiff,
+    /// Run `git gc` to reclaim what deleted branches took up.
+    Gc,
+    /// Suggest a starter config from the repository's branches.
+    Init,
+    /// Print the settings in effect, where each comes from, and the
+    /// branches a session would go through.
+    ExplainConfig,
+    /// Print a completion script for a shell.
+    Completions(Shell),
+    /// `--help`, or `-h`.
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// `--tombstones` or `--tombstones=ask`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TombstoneMode {
+    Record,
+    /// Also ask for the reason each branch is deleted.
+    AskReason,
+}
+
+/// `graph --dot` or `graph --mermaid`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+/// `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// `--date`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DateFormat {
+    /// How long ago, e.g. "3 months ago".
+    Relative,
+    /// In the local time zone.
+    #[default]
+    Local,
+    /// ISO 8601-like, in the committer's time zone, as `git log --date=iso`.
+    Iso,
+    /// Seconds since the epoch and the committer's offset, as git stores it.
+    Raw,
+}
+
+/// How a flag takes its value.
+#[derive(Clone, Copy)]
+enum Takes {
+    Nothing,
+    /// `--flag value` or `--flag=value`.
+    Value(&'static str),
+    /// Only as `--flag=value`, since a following argument could be anything.
+    OptionalValue(&'static str),
+}
+
+/// What completions can offer for a flag's value.
+#[derive(Clone, Copy)]
+enum Hint {
+    Anything,
+    File,
+    OneOf(&'static [&'static str]),
+}
+
+struct Flag {
+    name: &'static str,
+    takes: Takes,
+    hint: Hint,
+    help: &'static str,
+    set: fn(&mut Cli, Option<String>) -> Result<()>,
+}
+
+struct Subcommand {
+    name: &'static str,
+    help: &'static str,
+    /// Left out of `--help`, but still completed.
+    hidden: bool,
+}
+
+/// Every flag, in the order `--help` lists them. Parsing, help and shell
+/// completions are all driven by this table.
+const FLAGS: &[Flag] = &[
+    Flag {
+        name: "--all-worktrees",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Show which linked worktree each branch is checked out in",
+        set: |cli, _| {
+            cli.all_worktrees = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--prune",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Prune stale remote-tracking refs afterwards without asking",
+        set: |cli, _| {
+            cli.prune = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--since",
+        takes: Takes::Value("REV"),
+        hint: Hint::Anything,
+        help: "Only offer branches that forked from the base branch at or after REV",
+        set: |cli, value| {
+            cli.since = value;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--until",
+        takes: Takes::Value("REV"),
+        hint: Hint::Anything,
+        help: "Only offer branches that forked from the base branch at or before REV",
+        set: |cli, value| {
+            cli.until = value;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--contains",
+        takes: Takes::Value("REV"),
+        hint: Hint::Anything,
+        help: "Only offer branches that contain REV, like git branch --contains",
+        set: |cli, value| {
+            cli.contains.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--no-contains",
+        takes: Takes::Value("REV"),
+        hint: Hint::Anything,
+        help: "Only offer branches that don't contain REV, say to clear out those from before a rewrite",
+        set: |cli, value| {
+            cli.no_contains.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--author",
+        takes: Takes::Value("PATTERN"),
+        hint: Hint::Anything,
+        help: "Only offer branches last committed to by a matching 'Name <email>' (substring or glob)",
+        set: |cli, value| {
+            cli.author.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--all-authors",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Match --author against everyone with a commit of the branch's own, not just the last",
+        set: |cli, _| {
+            cli.all_authors = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--owner",
+        takes: Takes::Value("OWNER"),
+        hint: Hint::Anything,
+        help: "Only offer branches touching code CODEOWNERS gives to OWNER, say @payments-team",
+        set: |cli, value| {
+            cli.owner.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--behind-only",
+        takes: Takes::Value("N"),
+        hint: Hint::Anything,
+        help: "Only offer branches N or more commits behind the base branch and none ahead",
+        set: |cli, value| {
+            let value = value.unwrap_or_default();
+            match value.parse() {
+                Ok(behind) => cli.behind_only = Some(behind),
+                Err(_) => return Err(Error::InvalidValue("--behind-only".to_string(), value)),
+            }
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--orphaned-only",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Only offer branches never pushed: no upstream and no remote branch of that name",
+        set: |cli, _| {
+            cli.orphaned_only = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--expired",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Only offer branches older than their delete-branches.retention allows",
+        set: |cli, _| {
+            cli.expired = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--keep-last-n-per-prefix",
+        takes: Takes::Value("N"),
+        hint: Hint::Anything,
+        help: "Keep the newest N branches of each prefix, like release/ or deploy/, and offer the rest",
+        set: |cli, value| {
+            let value = value.unwrap_or_default();
+            match value.parse() {
+                Ok(count) => cli.keep_last_n_per_prefix = Some(count),
+                Err(_) => {
+                    return Err(Error::InvalidValue(
+                        "--keep-last-n-per-prefix".to_string(),
+                        value,
+                    ))
+                }
+            }
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--exclude-recent-authors-days",
+        takes: Takes::Value("N"),
+        hint: Hint::Anything,
+        help: "Leave out branches committed to in the last N days, or last by someone active in them",
+        set: |cli, value| {
+            let value = value.unwrap_or_default();
+            match value.parse() {
+                Ok(days) if days >= 0 => cli.exclude_recent_authors_days = Some(days),
+                _ => {
+                    return Err(Error::InvalidValue(
+                        "--exclude-recent-authors-days".to_string(),
+                        value,
+                    ))
+                }
+            }
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--only",
+        takes: Takes::Value("GLOB"),
+        hint: Hint::Anything,
+        help: "Only offer branches whose names match GLOB; nothing else about the rest is read",
+        set: |cli, value| {
+            cli.only.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--quiet",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Print only the names of deleted branches, one per line, for scripts",
+        set: |cli, _| {
+            cli.quiet = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--verbose",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Log how branches are classified and what is sent to remotes (-v), or every lookup too (-vv)",
+        set: |cli, _| {
+            cli.verbose += 1;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--log-file",
+        takes: Takes::Value("FILE"),
+        hint: Hint::File,
+        help: "Append the --verbose log to FILE instead of stderr",
+        set: |cli, value| {
+            cli.log_file = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--ignore-case",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Match branch names and patterns regardless of case",
+        set: |cli, _| {
+            cli.ignore_case = Some(true);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--no-ignore-case",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Match branch names and patterns case-sensitively, whatever core.ignoreCase says",
+        set: |cli, _| {
+            cli.ignore_case = Some(false);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--from-file",
+        takes: Takes::Value("PATH"),
+        hint: Hint::File,
+        help: "Only offer the branches named in PATH, one per line (git branch output works)",
+        set: |cli, value| {
+            cli.from_file = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--stdin",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Only offer the branches named on stdin, like --from-file",
+        set: |cli, _| {
+            cli.from_file = Some(PathBuf::from("-"));
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--include-head",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Offer the current branch too; deleting it switches to the base branch first",
+        set: |cli, _| {
+            cli.include_head = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--dashboard",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Start with an overview of the branches and pick which ones to go through first",
+        set: |cli, _| {
+            cli.dashboard = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--group-by-prefix",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Ask about whole namespaces before their branches",
+        set: |cli, _| {
+            cli.group_by_prefix = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--check-prs",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Look up pull requests on the origin forge",
+        set: |cli, _| {
+            cli.check_prs = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--check-ci",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Look up pull requests and whether CI passed on each branch tip, also for sync",
+        set: |cli, _| {
+            cli.check_ci = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--sizes",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Estimate the commits, objects and bytes each branch adds over the base branch",
+        set: |cli, _| {
+            cli.sizes = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--owners",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Show the CODEOWNERS owners of the paths each branch touches",
+        set: |cli, _| {
+            cli.owners = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--unprotect",
+        takes: Takes::Value("GLOB"),
+        hint: Hint::Anything,
+        help: "Lift configured protections from matching branches, after asking",
+        set: |cli, value| {
+            cli.unprotect.extend(value);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--interactive-rebase-safe",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Guard branches with stashes or WIP/fixup tips",
+        set: |cli, _| {
+            cli.rebase_safe = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--no-hooks",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Run no hooks, for when a broken one blocks a cleanup (noted in the audit log)",
+        set: |cli, _| {
+            cli.no_hooks = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--mark",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Only mark branches, and delete them after a final review (the default)",
+        set: |cli, _| {
+            cli.mark = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--immediate",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Delete branches as they are decided on, without the final review",
+        set: |cli, _| {
+            cli.immediate = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--seatbelt",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Review deletions first, merged branches only, a few per session (on unless configured off)",
+        set: |cli, _| {
+            cli.seatbelt = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--keys",
+        takes: Takes::Value("PROFILE"),
+        hint: Hint::OneOf(&["default", "vim"]),
+        help: "Key profile for the branch prompt",
+        set: |cli, value| {
+            cli.keys = value;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--archive-tags",
+        takes: Takes::OptionalValue("PREFIX"),
+        hint: Hint::Anything,
+        help: "Tag each branch tip before deleting it (default prefix archive/)",
+        set: |cli, value| {
+            cli.archive_tags = Some(value.unwrap_or_else(|| DEFAULT_ARCHIVE_PREFIX.into()));
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--archive-annotate",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Make archive tags annotated",
+        set: |cli, _| {
+            cli.archive_annotate = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--backup-remote",
+        takes: Takes::Value("REMOTE"),
+        hint: Hint::Anything,
+        help: "Push each branch to REMOTE as refs/archive/<branch> before deleting it",
+        set: |cli, value| {
+            cli.backup_remote = value;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--backup-bundle",
+        takes: Takes::Value("PATH"),
+        hint: Hint::File,
+        help: "Add each branch to the git bundle at PATH before deleting it",
+        set: |cli, value| {
+            cli.backup_bundle = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--clean-archive",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Offer to remove archive tags instead of triaging branches",
+        set: |cli, _| {
+            cli.clean_archive = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--tombstones",
+        takes: Takes::OptionalValue("ask"),
+        hint: Hint::OneOf(&["ask"]),
+        help: "Note deletions on the tip commits; =ask also asks for a reason",
+        set: |cli, value| {
+            cli.tombstones = Some(match value.as_deref() {
+                None => TombstoneMode::Record,
+                Some("ask") => TombstoneMode::AskReason,
+                Some(other) => {
+                    return Err(Error::InvalidValue(
+                        "--tombstones".to_string(),
+                        other.to_string(),
+                    ))
+                }
+            });
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--json",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Write the report as JSON",
+        set: |cli, _| {
+            cli.json = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--anonymize",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Hash branch names and identities in the report or graph",
+        set: |cli, _| {
+            cli.anonymize = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--dot",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Draw the graph for Graphviz (the default)",
+        set: |cli, _| {
+            cli.graph_format = GraphFormat::Dot;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--mermaid",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Draw the graph as a Mermaid flowchart",
+        set: |cli, _| {
+            cli.graph_format = GraphFormat::Mermaid;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--expire-reflogs",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "With gc, expire reflog entries of deleted commits and prune them now",
+        set: |cli, _| {
+            cli.expire_reflogs = true;
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--color",
+        takes: Takes::Value("WHEN"),
+        hint: Hint::OneOf(&["auto", "always", "never"]),
+        help: "Color the output: auto (the default), always or never",
+        set: |cli, value| {
+            cli.color = match value.as_deref() {
+                Some("auto") => ColorChoice::Auto,
+                Some("always") => ColorChoice::Always,
+                Some("never") => ColorChoice::Never,
+                _ => {
+                    return Err(Error::InvalidValue(
+                        "--color".to_string(),
+                        value.unwrap_or_default(),
+                    ))
+                }
+            };
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--date",
+        takes: Takes::Value("FORMAT"),
+        hint: Hint::OneOf(&["relative", "local", "iso", "raw"]),
+        help: "Show last commit dates as relative, local (the default), iso or raw",
+        set: |cli, value| {
+            cli.date = match value.as_deref() {
+                Some("relative") => DateFormat::Relative,
+                Some("local") => DateFormat::Local,
+                Some("iso") => DateFormat::Iso,
+                Some("raw") => DateFormat::Raw,
+                _ => {
+                    return Err(Error::InvalidValue(
+                        "--date".to_string(),
+                        value.unwrap_or_default(),
+                    ))
+                }
+            };
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--script",
+        takes: Takes::Value("FILE"),
+        hint: Hint::File,
+        help: "Replay the decisions in FILE (pattern and action per line) without asking",
+        set: |cli, value| {
+            cli.script = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--record",
+        takes: Takes::Value("FILE"),
+        hint: Hint::File,
+        help: "Write the session's decisions to FILE, for --script",
+        set: |cli, value| {
+            cli.record = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--events-socket",
+        takes: Takes::Value("PATH"),
+        hint: Hint::File,
+        help: "Stream session events as JSON lines to a Unix domain socket",
+        set: |cli, value| {
+            cli.events_socket = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--upload-report",
+        takes: Takes::Value("URL"),
+        hint: Hint::Anything,
+        help: "POST a JSON report of the run to URL when it is over",
+        set: |cli, value| {
+            let url = value.unwrap_or_default();
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(Error::InvalidValue("--upload-report".to_string(), url));
+            }
+            cli.upload_report = Some(url);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--recurse",
+        takes: Takes::Value("DIR"),
+        hint: Hint::File,
+        help: "Triage every repository found under DIR in turn",
+        set: |cli, value| {
+            cli.recurse.extend(value.map(PathBuf::from));
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--fixture",
+        takes: Takes::Value("PATH"),
+        hint: Hint::File,
+        help: "Run against a synthetic branch set from a JSON file",
+        set: |cli, value| {
+            cli.fixture = value.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    Flag {
+        name: "--help",
+        takes: Takes::Nothing,
+        hint: Hint::Anything,
+        help: "Show this help",
+        set: |cli, _| {
+            cli.command = Some(Command::Help);
+            Ok(())
+        },
+    },
+];
+
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "sync",
+        help: "Refresh cached forge data for every branch, within the API rate limit",
+        hidden: false,
+    },
+    Subcommand {
+        name: "report",
+        help: "Print branch hygiene statistics",
+        hidden: false,
+    },
+    Subcommand {
+        name: "graph",
+        help: "Print branches, their fork points and merges as a DOT or Mermaid graph",
+        hidden: false,
+    },
+    Subcommand {
+        name: "restore",
+        help: "List deleted branches, or restore BRANCH from the trash or the reflogs",
+        hidden: false,
+    },
+    Subcommand {
+        name: "gc",
+        help: "Run git gc to reclaim the space deleted branches took up, and say how much",
+        hidden: false,
+    },
+    Subcommand {
+        name: "plan",
+        help: "plan diff OLD NEW: show how two --record files differ in what they delete",
+        hidden: false,
+    },
+    Subcommand {
+        name: "init",
+        help: "Suggest protections and retention policies from the repository's branches, and save them",
+        hidden: false,
+    },
+    Subcommand {
+        name: "explain-config",
+        help: "Print the settings in effect, where each is set, and the branches a session would act on",
+        hidden: false,
+    },
+    Subcommand {
+        name: "completions",
+        help: "Print a bash, zsh or fish completion script",
+        hidden: true,
+    },
+];
+
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+impl Cli {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli::default();
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // `--flag=value` and `--flag value` are both accepted.
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+
+            if name == "-h" {
+                cli.command = Some(Command::Help);
+                continue;
+            }
+            if let Some(vs) = name
+                .strip_prefix('-')
+                .filter(|vs| !vs.is_empty() && vs.bytes().all(|v| v == b'v'))
+            {
+                cli.verbose += vs.len() as u8;
+                continue;
+            }
+
+            if let Some(flag) = FLAGS.iter().find(|flag| flag.name == name) {
+                let value = match flag.takes {
+                    Takes::Nothing if inline.is_some() => {
+                        return Err(Error::InvalidValue(name, inline.unwrap_or_default()))
+                    }
+                    Takes::Nothing | Takes::OptionalValue(_) => inline,
+                    Takes::Value(_) => Some(
+                        inline
+                            .or_else(|| args.next())
+                            .ok_or_else(|| Error::MissingValue(name.clone()))?,
+                    ),
+                };
+                (flag.set)(&mut cli, value)?;
+                continue;
+            }
+
+            if cli.command == Some(Command::Restore) && cli.branch.is_none() {
+                cli.branch = Some(name);
+                continue;
+            }
+            if cli.command == Some(Command::PlanDiff) && cli.plans.len() < 2 {
+                cli.plans.push(PathBuf::from(name));
+                continue;
+            }
+
+            cli.command = match name.as_str() {
+                _ if cli.command.is_some() => return Err(Error::UnknownArgument(name)),
+                "sync" => Some(Command::Sync),
+                "report" => Some(Command::Report),
+                "graph" => Some(Command::Graph),
+                "restore" => Some(Command::Restore),
+                "gc" => Some(Command::Gc),
+                "init" => Some(Command::Init),
+                "explain-config" => Some(Command::ExplainConfig),
+                "plan" => match args.next().as_deref() {
+                    Some("diff") => Some(Command::PlanDiff),
+                    Some(other) => return Err(Error::InvalidValue(name, other.to_string())),
+                    None => return Err(Error::MissingValue(name)),
+                },
+                "completions" => {
+                    let shell = args
+                        .next()
+                        .ok_or_else(|| Error::MissingValue(name.clone()))?;
+                    Some(Command::Completions(match shell.as_str() {
+                        "bash" => Shell::Bash,
+                        "zsh" => Shell::Zsh,
+                        "fish" => Shell::Fish,
+                        _ => return Err(Error::InvalidValue(name, shell)),
+                    }))
+                }
+                _ => return Err(Error::UnknownArgument(name)),
+            };
+        }
+
+        Ok(cli)
+    }
+}
+
+fn program() -> &'static str {
+    env!("CARGO_PKG_NAME")
+}
+
+/// `--help`, generated from the flag table.
+pub fn help() -> String {
+    let mut help = format!(
+        "Interactively delete local git branches.\n\nUsage: {} [COMMAND] [OPTIONS]\n\nCommands:\n",
+        program()
+    );
+
+    for subcommand in SUBCOMMANDS.iter().filter(|subcommand| !subcommand.hidden) {
+        help.push_str(&format!("  {:<12} {}\n", subcommand.name, subcommand.help));
+    }
+
+    help.push_str("\nOptions:\n");
+    let usages: Vec<String> = FLAGS.iter().map(usage).collect();
+    let width = usages.iter().map(String::len).max().unwrap_or(0);
+    for (flag, usage) in FLAGS.iter().zip(&usages) {
+        help.push_str(&format!(
+            "  {:<width$}  {}\n",
+            usage,
+            flag.help,
+            width = width
+        ));
+    }
+
+    help
+}
+
+fn usage(flag: &Flag) -> String {
+    match flag.takes {
+        Takes::Nothing => flag.name.to_string(),
+        Takes::Value(placeholder) => format!("{} <{}>", flag.name, placeholder),
+        Takes::OptionalValue(placeholder) => format!("{}[={}]", flag.name, placeholder),
+    }
+}
+
+/// A completion script for `shell`, generated from the flag table.
+pub fn completions(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash_completions(),
+        Shell::Zsh => zsh_completions(),
+        Shell::Fish => fish_completions(),
+    }
+}
+
+fn bash_completions() -> String {
+    let function = format!("_{}", program().replace('-', "_"));
+    let mut script = format!(
+        "{}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    case \"$prev\" in\n",
+        function
+    );
+
+    for flag in FLAGS
+        .iter()
+        .filter(|flag| matches!(flag.takes, Takes::Value(_)))
+    {
+        let reply = match flag.hint {
+            Hint::Anything => "return".to_string(),
+            Hint::File => "COMPREPLY=($(compgen -f -- \"$cur\")); return".to_string(),
+            Hint::OneOf(values) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+                values.join(" ")
+            ),
+        };
+        script.push_str(&format!("        {}) {};;\n", flag.name, reply));
+    }
+    script.push_str(&format!(
+        "        completions) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return;;\n    esac\n",
+        SHELLS.join(" ")
+    ));
+
+    let words: Vec<&str> = SUBCOMMANDS
+        .iter()
+        .map(|subcommand| subcommand.name)
+        .chain(FLAGS.iter().map(|flag| flag.name))
+        .collect();
+    script.push_str(&format!(
+        "    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n}}\ncomplete -F {} {}\n",
+        words.join(" "),
+        function,
+        program()
+    ));
+
+    script
+}
+
+fn zsh_completions() -> String {
+    let mut script = format!("#compdef {}\n\n_arguments \\\n", program());
+
+    for flag in FLAGS {
+        let help = flag.help.replace('\'', "'\\''").replace(['[', ']'], "");
+        let spec = match (flag.takes, flag.hint) {
+            (Takes::Nothing, _) => format!("'{}[{}]'", flag.name, help),
+            (Takes::Value(placeholder), hint) => {
+                format!(
+                    "'{}=[{}]:{}:{}'",
+                    flag.name,
+                    help,
+                    placeholder,
+                    zsh_action(hint)
+                )
+            }
+            (Takes::OptionalValue(placeholder), hint) => {
+                format!(
+                    "'{}=-[{}]::{}:{}'",
+                    flag.name,
+                    help,
+                    placeholder,
+                    zsh_action(hint)
+                )
+            }
+        };
+        script.push_str(&format!("  {} \\\n", spec));
+    }
+
+    let subcommands: Vec<&str> = SUBCOMMANDS
+        .iter()
+        .map(|subcommand| subcommand.name)
+        .collect();
+    script.push_str(&format!(
+        "  '1::command:({})' \\\n  '2::shell:({})'\n",
+        subcommands.join(" "),
+        SHELLS.join(" ")
+    ));
+
+    script
+}
+
+fn zsh_action(hint: Hint) -> String {
+    match hint {
+        Hint::Anything => " ".to_string(),
+        Hint::File => "_files".to_string(),
+        Hint::OneOf(values) => format!("({})", values.join(" ")),
+    }
+}
+
+fn fish_completions() -> String {
+    let mut script = String::new();
+
+    for subcommand in SUBCOMMANDS {
+        script.push_str(&format!(
+            "complete -c {} -n __fish_use_subcommand -f -a {} -d '{}'\n",
+            program(),
+            subcommand.name,
+            subcommand.help.replace('\'', "\\'")
+        ));
+    }
+    script.push_str(&format!(
+        "complete -c {} -n '__fish_seen_subcommand_from completions' -f -a '{}'\n",
+        program(),
+        SHELLS.join(" ")
+    ));
+
+    for flag in FLAGS {
+        let mut line = format!(
+            "complete -c {} -l {} -d '{}'",
+            program(),
+            flag.name.trim_start_matches("--"),
+            flag.help.replace('\'', "\\'")
+        );
+        match (flag.takes, flag.hint) {
+            (Takes::Nothing, _) | (Takes::OptionalValue(_), Hint::Anything) => {}
+            (_, Hint::File) => line.push_str(" -r -F"),
+            (_, Hint::OneOf(values)) => line.push_str(&format!(" -r -f -a '{}'", values.join(" "))),
+            (Takes::Value(_), Hint::Anything) => line.push_str(" -r"),
+        }
+        script.push_str(&line);
+        script.push('\n');
+    }
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Command, Shell, TombstoneMode, DEFAULT_ARCHIVE_PREFIX};
+    use crate::Error;
+    use std::path::PathBuf;
+
+    fn parse(args: &str) -> Cli {
+        Cli::parse_from(args.split_whitespace().map(String::from))
+            .unwrap_or_else(|e| panic!("{:?} didn't parse: {}", args, e))
+    }
+
+    fn error(args: &str) -> Error {
+        match Cli::parse_from(args.split_whitespace().map(String::from)) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} parsed", args),
+        }
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let cli = parse("--prune --mark --since 2.weeks --unprotect a --unprotect b");
+        assert!(cli.prune);
+        assert!(cli.mark);
+        assert!(!cli.json);
+        assert_eq!(cli.since.as_deref(), Some("2.weeks"));
+        assert_eq!(cli.unprotect, ["a", "b"]);
+        assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn inline_values() {
+        let cli = parse("--since=2.weeks --keys=vim --unprotect=a --unprotect b");
+        assert_eq!(cli.since.as_deref(), Some("2.weeks"));
+        assert_eq!(cli.keys.as_deref(), Some("vim"));
+        assert_eq!(cli.unprotect, ["a", "b"]);
+
+        // Only the first `=` splits.
+        assert_eq!(parse("--until=a=b").until.as_deref(), Some("a=b"));
+    }
+
+    #[test]
+    fn optional_values() {
+        assert_eq!(
+            parse("--tombstones").tombstones,
+            Some(TombstoneMode::Record)
+        );
+        assert_eq!(
+            parse("--tombstones=ask").tombstones,
+            Some(TombstoneMode::AskReason)
+        );
+        assert_eq!(
+            parse("--archive-tags").archive_tags.as_deref(),
+            Some(DEFAULT_ARCHIVE_PREFIX)
+        );
+        assert_eq!(
+            parse("--archive-tags=old/").archive_tags.as_deref(),
+            Some("old/")
+        );
+        // Without `=`, the next argument isn't taken as the value.
+        assert!(matches!(
+            error("--tombstones ask"),
+            Error::UnknownArgument(arg) if arg == "ask"
+        ));
+    }
+
+    #[test]
+    fn unknown_arguments() {
+        assert!(matches!(
+            error("--prune --frobnicate"),
+            Error::UnknownArgument(arg) if arg == "--frobnicate"
+        ));
+        assert!(matches!(error("frobnicate"), Error::UnknownArgument(arg) if arg == "frobnicate"));
+        assert!(matches!(error("-x"), Error::UnknownArgument(arg) if arg == "-x"));
+        // A second subcommand is as unknown as any other word.
+        assert!(matches!(error("report sync"), Error::UnknownArgument(arg) if arg == "sync"));
+    }
+
+    #[test]
+    fn invalid_and_missing_values() {
+        assert!(matches!(error("--since"), Error::MissingValue(flag) if flag == "--since"));
+        assert!(matches!(
+            error("--prune=yes"),
+            Error::InvalidValue(flag, value) if flag == "--prune" && value == "yes"
+        ));
+        assert!(matches!(
+            error("--tombstones=later"),
+            Error::InvalidValue(flag, value) if flag == "--tombstones" && value == "later"
+        ));
+        assert!(matches!(error("completions"), Error::MissingValue(arg) if arg == "completions"));
+        assert!(matches!(
+            error("completions tcsh"),
+            Error::InvalidValue(arg, shell) if arg == "completions" && shell == "tcsh"
+        ));
+    }
+
+    #[test]
+    fn subcommands() {
+        assert_eq!(parse("sync").command, Some(Command::Sync));
+        assert_eq!(
+            parse("completions fish").command,
+            Some(Command::Completions(Shell::Fish))
+        );
+        assert_eq!(parse("-h").command, Some(Command::Help));
+        assert_eq!(parse("--help").command, Some(Command::Help));
+
+        // Flags may come before or after the subcommand.
+        let cli = parse("--json report --anonymize");
+        assert_eq!(cli.command, Some(Command::Report));
+        assert!(cli.json);
+        assert!(cli.anonymize);
+    }
+
+    #[test]
+    fn subcommand_operands() {
+        let cli = parse("restore feature/x");
+        assert_eq!(cli.command, Some(Command::Restore));
+        assert_eq!(cli.branch.as_deref(), Some("feature/x"));
+        assert_eq!(parse("restore").branch, None);
+        assert!(matches!(error("restore a b"), Error::UnknownArgument(arg) if arg == "b"));
+
+        let cli = parse("plan diff old.json new.json");
+        assert_eq!(cli.command, Some(Command::PlanDiff));
+        assert_eq!(
+            cli.plans,
+            [PathBuf::from("old.json"), PathBuf::from("new.json")]
+        );
+        assert!(matches!(
+            error("plan diff a b c"),
+            Error::UnknownArgument(arg) if arg == "c"
+        ));
+        assert!(matches!(error("plan"), Error::MissingValue(arg) if arg == "plan"));
+        assert!(matches!(
+            error("plan show"),
+            Error::InvalidValue(arg, value) if arg == "plan" && value == "show"
+        ));
+    }
+
+    #[test]
+    fn verbosity() {
+        assert_eq!(parse("").verbose, 0);
+        assert_eq!(parse("-v").verbose, 1);
+        assert_eq!(parse("-vv").verbose, 2);
+        assert_eq!(parse("-v -v --verbose").verbose, 3);
+        assert_eq!(parse("-vv --prune -v").verbose, 3);
+        assert!(matches!(error("-"), Error::UnknownArgument(arg) if arg == "-"));
+        assert!(matches!(error("-vx"), Error::UnknownArgument(arg) if arg == "-vx"));
+        assert!(matches!(
+            error("--verbose=2"),
+            Error::InvalidValue(flag, value) if flag == "--verbose" && value == "2"
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+//! Refs tooling keeps alongside a branch, like a tag CI pushes for every
+//! build of it or what `git filter-branch` leaves in `refs/original/`. They
+//! are outlived by the branch they go with, so deleting it offers to delete
+//! them too, in the same question:
+//!
+//! ```ini
+//! [delete-branches]
+//!     companion = refs/tags/{branch}-build*
+//!     companion = refs/original/refs/heads/{branch}
+//! ```
+//!
+//! `{branch}` stands for the branch name, and the rest may have wildcards.
+//! Undoing the deletion brings the companions back where they were.
+
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::glob;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::Result;
+use git2::{Oid, Repository};
+use std::io::Write;
+
+pub const KEY: &str = "delete-branches.companion";
+
+/// A companion ref and what it pointed at, to put it back on undo.
+pub struct Companion {
+    pub name: String,
+    pub target: Oid,
+}
+
+/// The refs matching a companion pattern for `branch`, the branch itself
+/// aside. Symbolic refs are left alone.
+pub fn find(repo: &Repository, config: &Config, branch: &str) -> Result<Vec<Companion>> {
+    let patterns: Vec<String> = config
+        .values(KEY)?
+        .into_iter()
+        .map(|(pattern, _)| pattern.replace("{branch}", branch))
+        .collect();
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let own = format!("refs/heads/{}", branch);
+    let mut companions = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        let (name, target) = match (reference.name(), reference.target()) {
+            (Some(name), Some(target)) if name != own => (name, target),
+            _ => continue,
+        };
+        if patterns.iter().any(|pattern| glob::matches(pattern, name)) {
+            companions.push(Companion {
+                name: name.to_string(),
+                target,
+            });
+        }
+    }
+    Ok(companions)
+}
+
+pub fn delete(repo: &Repository, companions: &[Companion]) -> Result<()> {
+    for companion in companions {
+        repo.find_reference(&companion.name)?.delete()?;
+    }
+    Ok(())
+}
+
+/// Puts the companions back, unless something else took their names since.
+pub fn restore(repo: &Repository, companions: &[Companion]) -> Result<()> {
+    for companion in companions {
+        repo.reference(
+            &companion.name,
+            companion.target,
+            false,
+            "delete-branches: undo",
+        )?;
+    }
+    Ok(())
+}
+
+impl Session<'_> {
+    /// Asks whether to delete the branch's companions along with it, and
+    /// returns the ones to delete.
+    pub fn companions_confirmed(&mut self, branch: &Branch) -> Result<Vec<Companion>> {
+        let companions = match self.backend.repo() {
+            Some(repo) if !branch.has_lossy_name() => find(repo, &self.config, &branch.name)?,
+            _ => return Ok(Vec::new()),
+        };
+        if companions.is_empty() {
+            return Ok(companions);
+        }
+
+        for companion in &companions {
+            write!(
+                self.stdout,
+                "  {} ({:.7})\r\n",
+                self.theme.paint(Role::Badge, &companion.name),
+                companion.target
+            )?;
+        }
+        let question = format!(
+            "'{}' has {} companion ref(s), delete them too?",
+            branch.name,
+            companions.len()
+        );
+        Ok(if self.confirm(&question)? {
+            companions
+        } else {
+            Vec::new()
+        })
+    }
+}
@@ -0,0 +1,101 @@
+//! Settings read from git config, so they can live in `~/.gitconfig` or a
+//! repository's `.git/config` next to everything else git knows about it.
+//!
+//! ```ini
+//! [delete-branches-remote "origin"]
+//!     transport = api
+//! ```
+
+use crate::Result;
+use git2::{ConfigLevel, Repository};
+
+pub struct Config {
+    config: git2::Config,
+}
+
+/// How branches are deleted on a remote.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    /// Push an empty ref, like `git push --delete`.
+    Push,
+    /// Ask the forge's REST API to delete the ref, for when pushing over
+    /// ssh/https isn't possible but a token is available.
+    Api,
+}
+
+impl Config {
+    pub fn load(repo: Option<&Repository>) -> Result<Self> {
+        let mut config = match repo {
+            Some(repo) => repo.config()?,
+            None => git2::Config::open_default()?,
+        };
+
+        Ok(Config {
+            config: config.snapshot()?,
+        })
+    }
+
+    pub fn git(&self) -> &git2::Config {
+        &self.config
+    }
+
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.config.get_string(key).ok()
+    }
+
+    /// Where the value of `key` in effect is set, `None` if it isn't.
+    pub fn level(&self, key: &str) -> Option<ConfigLevel> {
+        self.values(key).ok()?.pop().map(|(_, level)| level)
+    }
+
+    /// Every value of `key` and where it is set, in the order git reads
+    /// them, so the last is the one in effect.
+    pub fn values(&self, key: &str) -> Result<Vec<(String, ConfigLevel)>> {
+        let mut values = Vec::new();
+        let entries = match self.config.multivar(key, None) {
+            Ok(entries) => entries,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(values),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in &entries {
+            let entry = entry?;
+            if let Some(value) = entry.value() {
+                values.push((value.to_string(), entry.level()));
+            }
+        }
+        Ok(values)
+    }
+
+    /// Every variable whose name matches the regular expression `names`,
+    /// with its value and where it is set, in the order git reads them.
+    pub fn entries(&self, names: &str) -> Result<Vec<(String, String, ConfigLevel)>> {
+        let mut found = Vec::new();
+        for entry in &self.config.entries(Some(names))? {
+            let entry = entry?;
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                found.push((name.to_string(), value.to_string(), entry.level()));
+            }
+        }
+        Ok(found)
+    }
+
+    pub fn remote_transport(&self, remote: &str) -> Transport {
+        match self
+            .string(&format!("delete-branches-remote.{}.transport", remote))
+            .as_deref()
+        {
+            Some("api") => Transport::Api,
+            _ => Transport::Push,
+        }
+    }
+}
+
+/// Which config file a level is, as the user knows it.
+pub fn describe(level: ConfigLevel) -> &'static str {
+    match level {
+        ConfigLevel::ProgramData | ConfigLevel::System => "system config",
+        ConfigLevel::XDG | ConfigLevel::Global => "global config",
+        ConfigLevel::Local => "repository config",
+        _ => "other config",
+    }
+}
@@ -0,0 +1,109 @@
+//! Which actions take a single key and which need confirming first.
+//!
+//! Every action is safe by default. Setting it elsewhere, say in the system
+//! config of a production mirror, makes it ask `y/n` (`confirm`) or have the
+//! branch name typed out (`type`), optionally with its own wording, where
+//! `{branch}` stands for the branch name:
+//!
+//! ```ini
+//! [delete-branches "danger"]
+//!     delete = type
+//!     deleteWithUpstream = type
+//! [delete-branches "confirm"]
+//!     delete = "This is the production mirror. Delete '{branch}'?"
+//! ```
+
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::keys::BranchAction;
+use crate::session::Session;
+use crate::{Error, Result};
+use std::io::Write;
+
+/// The actions that change something, and so can be made to confirm.
+const GUARDED: [BranchAction; 6] = [
+    BranchAction::Publish,
+    BranchAction::Rename,
+    BranchAction::Checkout,
+    BranchAction::FormatPatch,
+    BranchAction::Delete,
+    BranchAction::DeleteWithUpstream,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Danger {
+    /// One key does it.
+    Safe,
+    /// Asks y/n.
+    Confirm,
+    /// Asks for the branch name to be typed.
+    TypeName,
+}
+
+pub struct Dangers {
+    levels: Vec<(BranchAction, Danger, Option<String>)>,
+}
+
+impl Dangers {
+    pub fn load(config: &Config) -> Result<Dangers> {
+        let mut levels = Vec::new();
+
+        for action in GUARDED {
+            let key = format!("delete-branches.danger.{}", action.config_name());
+            let danger = match config.string(&key).as_deref() {
+                None | Some("safe") => Danger::Safe,
+                Some("confirm") => Danger::Confirm,
+                Some("type") => Danger::TypeName,
+                Some(other) => return Err(Error::InvalidValue(key, other.to_string())),
+            };
+            let wording =
+                config.string(&format!("delete-branches.confirm.{}", action.config_name()));
+            levels.push((action, danger, wording));
+        }
+
+        Ok(Dangers { levels })
+    }
+
+    fn level(&self, action: BranchAction) -> Option<(Danger, Option<&str>)> {
+        self.levels
+            .iter()
+            .find(|(guarded, _, _)| *guarded == action)
+            .map(|(_, danger, wording)| (*danger, wording.as_deref()))
+    }
+}
+
+fn default_wording(action: BranchAction) -> &'static str {
+    match action {
+        BranchAction::Publish => "Push '{branch}'?",
+        BranchAction::Rename => "Rename '{branch}'?",
+        BranchAction::Checkout => "Check out '{branch}'?",
+        BranchAction::FormatPatch => "Export '{branch}' as patches and delete it?",
+        BranchAction::DeleteWithUpstream => "Delete '{branch}' and its remote branch?",
+        _ => "Delete '{branch}'?",
+    }
+}
+
+impl Session<'_> {
+    /// Whether `action` on `branch` may go ahead, asking first if it is
+    /// configured as dangerous.
+    pub fn action_confirmed(&mut self, action: BranchAction, branch: &Branch) -> Result<bool> {
+        let (danger, wording) = self.dangers.level(action).unwrap_or((Danger::Safe, None));
+        let wording = wording
+            .unwrap_or(default_wording(action))
+            .replace("{branch}", &branch.name);
+
+        let confirmed = match danger {
+            Danger::Safe => true,
+            Danger::Confirm => self.confirm(&wording)?,
+            Danger::TypeName => {
+                write!(self.stdout, "{} Type its name to confirm > ", wording)?;
+                self.stdout.flush()?;
+                self.input.read_line(&mut self.stdout)?.as_deref() == Some(&branch.name)
+            }
+        };
+        if !confirmed {
+            write!(self.stdout, "Leaving '{}' as it is\r\n", branch.name)?;
+        }
+        Ok(confirmed)
+    }
+}
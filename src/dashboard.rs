@@ -0,0 +1,167 @@
+//! `--dashboard`: an overview of the branches before the first question,
+//! with how many are merged, unmerged, gone upstream and never pushed, how
+//! old they are and which prefixes have the most, and a key to pick the ones
+//! to go through first. The rest follow, as they would have anyway.
+
+use crate::branch::{self, Branch};
+use crate::input::Key;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::Result;
+use crossterm::cursor::MoveTo;
+use crossterm::terminal::{Clear, ClearType};
+use std::io::Write;
+
+/// Age buckets of the histogram, each up to so many days.
+const AGES: [(&str, i64); 5] = [
+    ("< 1 week", 7),
+    ("< 1 month", 30),
+    ("< 3 months", 90),
+    ("< 1 year", 365),
+    ("older", i64::MAX),
+];
+
+const BAR_WIDTH: usize = 40;
+
+/// How many prefixes get a digit of their own.
+const TOP_PREFIXES: usize = 9;
+
+impl Session<'_> {
+    /// Shows the overview and splits `branches` into the batches to go
+    /// through, the ones picked first. `None` to quit without going through
+    /// any.
+    pub fn dashboard(&mut self, branches: Vec<Branch>) -> Result<Option<Vec<Vec<Branch>>>> {
+        let mut prefixes: Vec<(String, usize)> = Vec::new();
+        for branch in &branches {
+            let prefix = branch.prefix();
+            if prefix.is_empty() {
+                continue;
+            }
+            match prefixes.iter_mut().find(|(known, _)| known == prefix) {
+                Some((_, count)) => *count += 1,
+                None => prefixes.push((prefix.to_string(), 1)),
+            }
+        }
+        prefixes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        prefixes.truncate(TOP_PREFIXES);
+
+        self.draw_dashboard(&branches, &prefixes)?;
+
+        loop {
+            write!(self.stdout, "Go through first (a/m/u/g/o/s/1-9/q/?) > ")?;
+            self.stdout.flush()?;
+            let c = match self.input.next_key()? {
+                Key::Char(c) => c,
+                Key::Interrupt => {
+                    write!(self.stdout, "^C\r\nQuitting...\r\n")?;
+                    return Ok(None);
+                }
+            };
+            write!(self.stdout, "{}\r\n", c)?;
+
+            let picked: Vec<bool> = match c {
+                'a' => return Ok(Some(vec![branches])),
+                'm' => branches.iter().map(is_merged).collect(),
+                'u' => branches.iter().map(|branch| !is_merged(branch)).collect(),
+                'g' => branches.iter().map(is_gone).collect(),
+                'o' => branches.iter().map(|branch| branch.orphaned).collect(),
+                's' => branches
+                    .iter()
+                    .map(|branch| self.is_stale(branch))
+                    .collect(),
+                '1'..='9' => match prefixes.get(c as usize - '1' as usize) {
+                    Some((prefix, _)) => branches
+                        .iter()
+                        .map(|branch| branch.prefix() == prefix)
+                        .collect(),
+                    None => continue,
+                },
+                'q' => {
+                    write!(self.stdout, "Quitting...\r\n")?;
+                    return Ok(None);
+                }
+                _ => {
+                    write!(self.stdout, "a - All branches, oldest first\r\n")?;
+                    write!(self.stdout, "m - Merged branches first\r\n")?;
+                    write!(self.stdout, "u - Unmerged branches first\r\n")?;
+                    write!(self.stdout, "g - Branches whose upstream is gone first\r\n")?;
+                    write!(self.stdout, "o - Branches never pushed first\r\n")?;
+                    write!(self.stdout, "s - Stale branches first\r\n")?;
+                    write!(self.stdout, "1-9 - That prefix's branches first\r\n")?;
+                    write!(self.stdout, "q - Quit\r\n")?;
+                    continue;
+                }
+            };
+            let (mut first, mut rest) = (Vec::new(), Vec::new());
+            for (branch, picked) in branches.into_iter().zip(picked) {
+                if picked {
+                    first.push(branch);
+                } else {
+                    rest.push(branch);
+                }
+            }
+            return Ok(Some(vec![first, rest]));
+        }
+    }
+
+    fn draw_dashboard(&mut self, branches: &[Branch], prefixes: &[(String, usize)]) -> Result<()> {
+        if self.stdout.is_tty() {
+            crossterm::queue!(self.stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        }
+        let count =
+            |f: &dyn Fn(&Branch) -> bool| branches.iter().filter(|branch| f(branch)).count();
+        let heading = |text: &str| self.theme.paint(Role::Branch, text);
+
+        let merged = count(&is_merged);
+        let stale = count(&|branch| self.is_stale(branch));
+        let gone = count(&is_gone);
+        let orphaned = count(&|branch| branch.orphaned);
+        let mut text = format!("{}\r\n", heading(&format!("{} branches", branches.len())));
+        text += &format!(
+            "  [m] {} merged, [u] {} unmerged, [g] {} gone upstream, [o] {} never pushed, [s] {} stale\r\n\r\n",
+            merged,
+            branches.len() - merged,
+            gone,
+            orphaned,
+            stale
+        );
+
+        text += &format!("{}\r\n", heading("Last commit"));
+        let mut ages = [0; AGES.len()];
+        for branch in branches {
+            let days = branch::days_since(branch.time);
+            let bucket = AGES.iter().position(|(_, most)| days < *most);
+            ages[bucket.unwrap_or(AGES.len() - 1)] += 1;
+        }
+        let most = ages.iter().copied().max().unwrap_or(0).max(1);
+        for ((label, _), count) in AGES.iter().zip(ages) {
+            text += &format!(
+                "  {:<10} {:>5} {}\r\n",
+                label,
+                count,
+                self.theme
+                    .paint(Role::Badge, "█".repeat(count * BAR_WIDTH / most))
+            );
+        }
+
+        if !prefixes.is_empty() {
+            text += &format!("\r\n{}\r\n", heading("Top prefixes"));
+            for (i, (prefix, count)) in prefixes.iter().enumerate() {
+                text += &format!("  [{}] {:<30} {:>5}\r\n", i + 1, prefix, count);
+            }
+        }
+        write!(self.stdout, "{}\r\n", text)?;
+        Ok(())
+    }
+}
+
+fn is_merged(branch: &Branch) -> bool {
+    branch.merged == Some(true) || branch.squash_merged
+}
+
+fn is_gone(branch: &Branch) -> bool {
+    branch
+        .upstream
+        .as_ref()
+        .is_some_and(|upstream| upstream.gone)
+}
@@ -0,0 +1,119 @@
+//! Commit messages and author names in whatever encoding they were written
+//! in. git stores them as raw bytes, with an `encoding` header when they
+//! aren't UTF-8; old repositories sometimes only have `i18n.commitEncoding`
+//! set and no header at all.
+//!
+//! The encodings legacy repositories actually use are decoded here: UTF-8,
+//! ISO-8859-1, ISO-8859-15 and Windows-1252. Anything else comes out with
+//! invalid bytes replaced, as before.
+
+use git2::{Commit, Repository};
+
+/// Windows-1252 characters for 0x80 to 0x9f, where ISO-8859-1 has control
+/// characters. The five unassigned bytes are kept as those controls.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    Latin1,
+    Latin9,
+    Windows1252,
+}
+
+impl Encoding {
+    fn from_label(label: &str) -> Option<Encoding> {
+        let label = label.trim().to_ascii_lowercase().replace('_', "-");
+        Some(match label.as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Encoding::Utf8,
+            "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "l1" => Encoding::Latin1,
+            "iso-8859-15" | "iso8859-15" | "latin9" | "latin-9" => Encoding::Latin9,
+            "windows-1252" | "cp1252" => Encoding::Windows1252,
+            _ => return None,
+        })
+    }
+
+    fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&byte| char::from(byte)).collect(),
+            Encoding::Latin9 => bytes.iter().map(|&byte| latin9(byte)).collect(),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..=0x9f => WINDOWS_1252[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn latin9(byte: u8) -> char {
+    match byte {
+        0xa4 => '€',
+        0xa6 => 'Š',
+        0xa8 => 'š',
+        0xb4 => 'Ž',
+        0xb8 => 'ž',
+        0xbc => 'Œ',
+        0xbd => 'œ',
+        0xbe => 'Ÿ',
+        _ => char::from(byte),
+    }
+}
+
+/// How to read the text of commits in one repository.
+pub struct Decoder {
+    /// `i18n.commitEncoding`, for commits without an `encoding` header.
+    fallback: Option<Encoding>,
+}
+
+impl Decoder {
+    pub fn new(repo: &Repository) -> Decoder {
+        Decoder {
+            fallback: repo
+                .config()
+                .and_then(|config| config.get_string("i18n.commitEncoding"))
+                .ok()
+                .and_then(|label| Encoding::from_label(&label)),
+        }
+    }
+
+    /// The first line of the commit message.
+    pub fn subject(&self, commit: &Commit) -> String {
+        let message = commit.message_bytes();
+        let first_line = message
+            .split(|&byte| byte == b'\n')
+            .find(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .unwrap_or_default();
+        self.decode(commit, first_line).trim().to_string()
+    }
+
+    pub fn author_name(&self, commit: &Commit) -> String {
+        let author = commit.author();
+        self.decode(commit, author.name_bytes())
+    }
+
+    pub fn author_email(&self, commit: &Commit) -> String {
+        let author = commit.author();
+        self.decode(commit, author.email_bytes())
+    }
+
+    /// The commit's own `encoding` header wins. Without one, text that is
+    /// valid UTF-8 is taken as such, since that is what git writes by
+    /// default, and anything else is read as `i18n.commitEncoding` says.
+    fn decode(&self, commit: &Commit, bytes: &[u8]) -> String {
+        if let Some(encoding) = commit.message_encoding().and_then(Encoding::from_label) {
+            return encoding.decode(bytes);
+        }
+        match (std::str::from_utf8(bytes), self.fallback) {
+            (Ok(text), _) => text.to_string(),
+            (Err(_), Some(encoding)) => encoding.decode(bytes),
+            (Err(_), None) => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! `--events-socket <path>`: a line of JSON on a Unix domain socket for
+//! everything that happens in the session, for wrappers, status bars or
+//! audit daemons to follow along:
+//!
+//! ```json
+//! {"event":"branch_deleted","time":1700000000,"repository":"/src/app/.git/","run":"5f0c…","seq":3,"branch":"topic","tip":"1a2b…"}
+//! ```
+//!
+//! Events are `branch_presented`, `branch_deleted`, `undo` and
+//! `session_end`. `branch_deleted` and `undo` carry the sequence number the
+//! audit log has for them. A listener that goes away doesn't stop the session; the
+//! events after it are dropped.
+
+use crate::branch::Branch;
+use crate::json::Value;
+use crate::session::Summary;
+use crate::{run, Error, Result};
+use std::io::Write;
+use std::path::Path;
+
+pub struct Events {
+    #[cfg(unix)]
+    stream: Option<std::os::unix::net::UnixStream>,
+    repository: String,
+}
+
+impl Events {
+    /// Connects to `socket`, or sends nothing without one.
+    pub fn connect(socket: Option<&Path>, repository: String) -> Result<Events> {
+        #[cfg(unix)]
+        {
+            let stream = socket
+                .map(std::os::unix::net::UnixStream::connect)
+                .transpose()
+                .map_err(|e| Error::InvalidValue("--events-socket".to_string(), e.to_string()))?;
+            Ok(Events { stream, repository })
+        }
+
+        #[cfg(not(unix))]
+        match socket {
+            Some(_) => Err(Error::InvalidValue(
+                "--events-socket".to_string(),
+                "Unix domain sockets aren't available on this platform".to_string(),
+            )),
+            None => Ok(Events { repository }),
+        }
+    }
+
+    pub fn branch_presented(&mut self, branch: &Branch) {
+        self.branch_event("branch_presented", branch, Vec::new());
+    }
+
+    pub fn branch_deleted(&mut self, branch: &Branch, sequence: u64) {
+        self.branch_event("branch_deleted", branch, sequenced(sequence));
+    }
+
+    pub fn undo(&mut self, branch: &Branch, sequence: u64) {
+        self.branch_event("undo", branch, sequenced(sequence));
+    }
+
+    pub fn session_end(&mut self, summary: &Summary) {
+        self.emit(
+            "session_end",
+            vec![
+                ("deleted".to_string(), summary.deleted.clone().into()),
+                ("kept".to_string(), summary.kept.clone().into()),
+                ("snoozed".to_string(), summary.snoozed.clone().into()),
+            ],
+        );
+    }
+
+    fn branch_event(&mut self, event: &str, branch: &Branch, mut fields: Vec<(String, Value)>) {
+        fields.push(("branch".to_string(), branch.name.as_str().into()));
+        fields.push(("tip".to_string(), branch.id.to_string().into()));
+        self.emit(event, fields);
+    }
+
+    #[cfg(unix)]
+    fn emit(&mut self, event: &str, fields: Vec<(String, Value)>) {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return,
+        };
+
+        let mut json = vec![
+            ("event".to_string(), event.into()),
+            ("time".to_string(), chrono::Utc::now().timestamp().into()),
+            ("repository".to_string(), self.repository.as_str().into()),
+            ("run".to_string(), run::id().into()),
+        ];
+        json.extend(fields);
+
+        if writeln!(stream, "{}", Value::Object(json)).is_err() {
+            self.stream = None;
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn emit(&mut self, _event: &str, _fields: Vec<(String, Value)>) {}
+}
+
+fn sequenced(sequence: u64) -> Vec<(String, Value)> {
+    vec![("seq".to_string(), (sequence as i64).into())]
+}
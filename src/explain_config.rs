@@ -0,0 +1,325 @@
+//! `git delete-branches explain-config`: what a session would run with once
+//! every config file and flag has had its say, each setting with where it
+//! comes from, and what that session would do with each branch. A dry run
+//! of the policy, to read before trusting a cron job or CI step with it.
+//!
+//! It takes the same flags as a session, so
+//!
+//! ```sh
+//! git delete-branches explain-config --expired --script cleanup.txt
+//! ```
+//!
+//! shows what `git delete-branches --expired --script cleanup.txt` would
+//! delete. Nothing is asked: a protection `--unprotect` would lift is shown
+//! as lifted, the way it would be once confirmed.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch::Branch;
+use crate::config::{self, Config};
+use crate::glob::Case;
+use crate::protection::{self, Source};
+use crate::session::Session;
+use crate::theme::Role;
+use crate::{companion, retention, script, seatbelt, Result};
+use git2::ConfigLevel;
+use std::io::Write;
+
+impl Session<'_> {
+    /// Prints the settings in effect and the fate of `branches`, those of
+    /// the `found` branches that passed the filters.
+    pub fn explain_config(&mut self, found: usize, branches: Vec<Branch>) -> Result<()> {
+        let mut text = String::new();
+        self.explain_safety(&mut text);
+        self.explain_protections(&mut text)?;
+        self.explain_filters(&mut text);
+        self.explain_retention(&mut text)?;
+        self.explain_companions(&mut text)?;
+        self.explain_hooks(&mut text);
+        self.explain_keys(&mut text)?;
+        self.explain_branches(&mut text, found, branches)?;
+        write!(self.stdout, "{}", text)?;
+        Ok(())
+    }
+
+    fn heading(&self, text: &mut String, title: &str) {
+        if !text.is_empty() {
+            text.push_str("\r\n");
+        }
+        *text += &format!("{}\r\n", self.theme.paint(Role::Branch, title));
+    }
+
+    fn explain_safety(&self, text: &mut String) {
+        self.heading(text, "Safety");
+        let case = match self.case {
+            Case::Insensitive => "branch names match ignoring case",
+            Case::Sensitive => "branch names match case-sensitively",
+        };
+        let case_source = match self.cli.ignore_case {
+            Some(true) => "--ignore-case".to_string(),
+            Some(false) => "--no-ignore-case".to_string(),
+            None => ["delete-branches.ignoreCase", "core.ignoreCase"]
+                .iter()
+                .find_map(|key| Some(set_in(key, self.config.level(key)?)))
+                .unwrap_or_else(|| "default on this platform".to_string()),
+        };
+        line(text, case, &case_source);
+
+        let policy = self
+            .config
+            .git()
+            .open_level(ConfigLevel::System)
+            .is_ok_and(|system| system.get_bool(seatbelt::KEY).unwrap_or(false));
+        let seatbelt_source = if self.cli.seatbelt {
+            "--seatbelt".to_string()
+        } else if policy {
+            format!("{} in system config, organization policy", seatbelt::KEY)
+        } else {
+            source(&self.config, seatbelt::KEY)
+        };
+        match &self.seatbelt {
+            Some(seatbelt) => {
+                line(text, "seatbelt on", &seatbelt_source);
+                line(
+                    text,
+                    &format!("at most {} deletion(s) a session", seatbelt.limit),
+                    &source(&self.config, seatbelt::LIMIT_KEY),
+                );
+            }
+            None => line(text, "seatbelt off", &seatbelt_source),
+        }
+        if self.deferred {
+            let mark_source = if self.cli.immediate {
+                "the seatbelt, despite --immediate"
+            } else if self.cli.mark {
+                "--mark"
+            } else {
+                "default"
+            };
+            line(text, "deletions are reviewed first", mark_source);
+        } else if self.cli.immediate {
+            line(text, "deletions happen right away", "--immediate");
+        }
+        if let Some(path) = &self.cli.script {
+            line(
+                text,
+                &format!("decisions from {}", path.display()),
+                "--script",
+            );
+        }
+    }
+
+    fn explain_protections(&self, text: &mut String) -> Result<()> {
+        self.heading(text, "Protections");
+        line(
+            text,
+            &format!("{}, the base branch", BASE_BRANCH),
+            "built in",
+        );
+        for (pattern, level) in self.config.values(protection::KEY)? {
+            let mut protection_source = config::describe(level).to_string();
+            if matches!(level, ConfigLevel::ProgramData | ConfigLevel::System) {
+                protection_source += ", organization policy";
+            }
+            line(text, &pattern, &protection_source);
+        }
+        for pattern in &self.cli.unprotect {
+            line(
+                text,
+                &format!("lifted from {}, after asking", pattern),
+                "--unprotect",
+            );
+        }
+        Ok(())
+    }
+
+    fn explain_filters(&self, text: &mut String) {
+        self.heading(text, "Filters");
+        let cli = &self.cli;
+        let mut filters: Vec<(&str, String)> = Vec::new();
+        filters.extend(cli.only.iter().map(|pattern| ("--only", pattern.clone())));
+        if let Some(path) = &cli.from_file {
+            filters.push(("--from-file", path.display().to_string()));
+        }
+        filters.extend(cli.contains.iter().map(|rev| ("--contains", rev.clone())));
+        filters.extend(
+            cli.no_contains
+                .iter()
+                .map(|rev| ("--no-contains", rev.clone())),
+        );
+        filters.extend(cli.author.iter().map(|author| ("--author", author.clone())));
+        if cli.all_authors {
+            filters.push(("--all-authors", String::new()));
+        }
+        filters.extend(cli.owner.iter().map(|owner| ("--owner", owner.clone())));
+        if let Some(behind) = cli.behind_only {
+            filters.push(("--behind-only", behind.to_string()));
+        }
+        if cli.orphaned_only {
+            filters.push(("--orphaned-only", String::new()));
+        }
+        if let Some(count) = cli.keep_last_n_per_prefix {
+            filters.push(("--keep-last-n-per-prefix", count.to_string()));
+        }
+        if let Some(days) = cli.exclude_recent_authors_days {
+            filters.push(("--exclude-recent-authors-days", days.to_string()));
+        }
+        if let Some(since) = &cli.since {
+            filters.push(("--since", since.clone()));
+        }
+        if let Some(until) = &cli.until {
+            filters.push(("--until", until.clone()));
+        }
+        if cli.expired {
+            filters.push(("--expired", String::new()));
+        }
+
+        if filters.is_empty() {
+            line(text, "none, every branch is looked at", "");
+        }
+        for (flag, value) in filters {
+            line(text, &format!("{} {}", flag, value), "command line");
+        }
+    }
+
+    fn explain_retention(&self, text: &mut String) -> Result<()> {
+        self.heading(text, "Retention (the last matching pattern wins)");
+        let policies = self.config.values(retention::KEY)?;
+        if policies.is_empty() {
+            line(text, "none, staleness goes by score", "");
+        }
+        for (policy, level) in policies {
+            line(text, &policy, config::describe(level));
+        }
+        Ok(())
+    }
+
+    fn explain_companions(&self, text: &mut String) -> Result<()> {
+        self.heading(
+            text,
+            "Companion refs, offered for deletion with their branch",
+        );
+        let patterns = self.config.values(companion::KEY)?;
+        if patterns.is_empty() {
+            line(text, "none", "");
+        }
+        for (pattern, level) in patterns {
+            line(text, &pattern, config::describe(level));
+        }
+        Ok(())
+    }
+
+    fn explain_hooks(&self, text: &mut String) {
+        self.heading(text, "Hooks");
+        let mut any = false;
+        for hook in ["preDelete", "postDelete"] {
+            let key = format!("delete-branches.{}", hook);
+            let (command, level) = match (self.config.string(&key), self.config.level(&key)) {
+                (Some(command), Some(level)) => (command, level),
+                _ => continue,
+            };
+            any = true;
+            let mut hook_source = config::describe(level).to_string();
+            if self.cli.no_hooks {
+                hook_source += ", skipped for --no-hooks";
+            }
+            line(text, &format!("{} = {}", hook, command), &hook_source);
+        }
+        if !any {
+            line(text, "none", "");
+        }
+    }
+
+    fn explain_keys(&self, text: &mut String) -> Result<()> {
+        self.heading(text, "Keys");
+        let (profile, profile_source) = match &self.cli.keys {
+            Some(profile) => (profile.clone(), "--keys".to_string()),
+            None => match self.config.string("delete-branches.keys") {
+                Some(profile) => (profile, source(&self.config, "delete-branches.keys")),
+                None => ("default".to_string(), "default".to_string()),
+            },
+        };
+        line(text, &format!("profile {}", profile), &profile_source);
+        for (name, keys, level) in self.config.entries(r"^delete-branches\.keys\..+")? {
+            line(
+                text,
+                &format!("{} = {}", name, keys),
+                config::describe(level),
+            );
+        }
+        for help in self.keymap.help(&[]) {
+            *text += &format!("    {}\r\n", help);
+        }
+        Ok(())
+    }
+
+    fn explain_branches(
+        &self,
+        text: &mut String,
+        found: usize,
+        mut branches: Vec<Branch>,
+    ) -> Result<()> {
+        self.heading(
+            text,
+            &format!(
+                "Branches ({} of {} pass the filters)",
+                branches.len(),
+                found
+            ),
+        );
+        let protections = protection::load(&self.config)?;
+        let mut offered = 0;
+        for branch in &mut branches {
+            let lifted = self
+                .cli
+                .unprotect
+                .iter()
+                .any(|pattern| self.case.matches(pattern, &branch.name));
+            // Only organization policy holds out against `--unprotect`.
+            branch.protection = protections
+                .iter()
+                .find(|protection| self.case.matches(&protection.pattern, &branch.name))
+                .filter(|protection| !lifted || protection.source == Source::Policy)
+                .cloned();
+
+            let fate = if let Some(reason) = self.ignore_reason(branch) {
+                format!("kept, {}", reason)
+            } else if self.seatbelt.is_some()
+                && branch.merged == Some(false)
+                && !branch.squash_merged
+            {
+                "kept, the seatbelt only deletes merged branches".to_string()
+            } else if let Some(script) = &self.script {
+                offered += 1;
+                let (decision, why) = script.decide(&branch.name, self.case);
+                format!(
+                    "{}{}",
+                    script::name(decision),
+                    why.map(|why| format!(" ({})", why)).unwrap_or_default()
+                )
+            } else {
+                offered += 1;
+                "offered".to_string()
+            };
+            line(text, &branch.name, &fate);
+        }
+        *text += &format!("{} branch(es) would be acted on\r\n", offered);
+        Ok(())
+    }
+}
+
+fn line(text: &mut String, value: &str, source: &str) {
+    text.push_str(format!("  {:<40} {}", value, source).trim_end());
+    text.push_str("\r\n");
+}
+
+/// Where `key` is set, or that it is left at its default.
+fn source(config: &Config, key: &str) -> String {
+    match config.level(key) {
+        Some(level) => set_in(key, level),
+        None => "default".to_string(),
+    }
+}
+
+fn set_in(key: &str, level: ConfigLevel) -> String {
+    format!("{} in {}", key, config::describe(level))
+}
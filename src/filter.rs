@@ -0,0 +1,342 @@
+//! Narrows down which branches are offered in the session.
+
+use crate::backend::{Backend, BASE_BRANCH};
+use crate::branch::Branch;
+use crate::cli::Cli;
+use crate::encoding::Decoder;
+use crate::glob::{self, Case};
+use crate::owners;
+use crate::retention;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::{Error, Result};
+use chrono::Utc;
+use git2::{BranchType, Oid, Repository, Sort};
+use std::collections::{BTreeSet, HashSet};
+use std::io::{Read, Write};
+
+/// Drops the branches that don't match the filters given on the command line.
+pub fn apply(backend: &dyn Backend, cli: &Cli, branches: Vec<Branch>) -> Result<Vec<Branch>> {
+    // Before anything else narrows them down, so the newest are the newest
+    // of all.
+    let branches = match cli.keep_last_n_per_prefix {
+        Some(count) => retention::past_newest(branches, count),
+        None => branches,
+    };
+
+    // Nothing of their own left, and well behind: superseded rather than
+    // abandoned work.
+    let branches: Vec<Branch> = match cli.behind_only {
+        Some(behind) => branches
+            .into_iter()
+            .filter(|branch| {
+                branch.ahead == Some(0) && branch.behind.is_some_and(|count| count >= behind)
+            })
+            .collect(),
+        None => branches,
+    };
+    let branches: Vec<Branch> = if cli.orphaned_only {
+        branches
+            .into_iter()
+            .filter(|branch| branch.orphaned)
+            .collect()
+    } else {
+        branches
+    };
+
+    let branches = contains(backend, cli, branches)?;
+    let branches = authored(backend, cli, branches)?;
+    let branches = owned(backend, cli, branches)?;
+    let branches = inactive(backend, cli, branches)?;
+
+    if cli.since.is_none() && cli.until.is_none() {
+        return Ok(branches);
+    }
+
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let since = cli
+        .since
+        .as_deref()
+        .map(|rev| resolve(repo, rev))
+        .transpose()?;
+    let until = cli
+        .until
+        .as_deref()
+        .map(|rev| resolve(repo, rev))
+        .transpose()?;
+    let base = repo
+        .find_branch(BASE_BRANCH, BranchType::Local)
+        .ok()
+        .and_then(|branch| branch.get().target());
+
+    let mut kept = Vec::with_capacity(branches.len());
+    for branch in branches {
+        // Where the branch left the base branch; for merged branches that is
+        // the tip itself.
+        let fork_point = match (branch.fork_point, base) {
+            (Some(fork_point), _) => fork_point,
+            (None, Some(base)) => repo.merge_base(base, branch.id).unwrap_or(branch.id),
+            (None, None) => branch.id,
+        };
+
+        let after_since = match since {
+            Some(since) => is_ancestor(repo, since, fork_point)?,
+            None => true,
+        };
+        let before_until = match until {
+            Some(until) => is_ancestor(repo, fork_point, until)?,
+            None => true,
+        };
+
+        if after_since && before_until {
+            kept.push(branch);
+        }
+    }
+
+    Ok(kept)
+}
+
+/// `--contains` and `--no-contains`: like `git branch`, a branch passes with
+/// any one of the `--contains` commits in its history and none of the
+/// `--no-contains` ones.
+fn contains(backend: &dyn Backend, cli: &Cli, branches: Vec<Branch>) -> Result<Vec<Branch>> {
+    if cli.contains.is_empty() && cli.no_contains.is_empty() {
+        return Ok(branches);
+    }
+
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let resolve_all = |revs: &[String]| -> Result<Vec<Oid>> {
+        revs.iter().map(|rev| resolve(repo, rev)).collect()
+    };
+    let wanted = resolve_all(&cli.contains)?;
+    let unwanted = resolve_all(&cli.no_contains)?;
+
+    let mut kept = Vec::with_capacity(branches.len());
+    for branch in branches {
+        let has = |commits: &[Oid]| -> Result<bool> {
+            for &commit in commits {
+                if is_ancestor(repo, commit, branch.id)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        };
+        if (wanted.is_empty() || has(&wanted)?) && !has(&unwanted)? {
+            kept.push(branch);
+        }
+    }
+    Ok(kept)
+}
+
+/// `--author`: branches whose last commit, or with `--all-authors` any
+/// commit the base branch doesn't have, was written by someone matching.
+/// A pattern with `*` or `?` is a glob over the name, the email or all of
+/// `Name <email>`, anything else a case-insensitive part of the latter.
+fn authored(backend: &dyn Backend, cli: &Cli, branches: Vec<Branch>) -> Result<Vec<Branch>> {
+    if cli.author.is_empty() {
+        return Ok(branches);
+    }
+
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let base = repo
+        .find_branch(BASE_BRANCH, BranchType::Local)
+        .ok()
+        .and_then(|branch| branch.get().target());
+    let matches = |id: Oid| -> Result<bool> {
+        let commit = repo.find_commit(id)?;
+        let author = commit.author();
+        let name = String::from_utf8_lossy(author.name_bytes());
+        let email = String::from_utf8_lossy(author.email_bytes());
+        let full = format!("{} <{}>", name, email);
+        Ok(cli.author.iter().any(|pattern| {
+            if pattern.contains(['*', '?']) {
+                [&*name, &*email, &full]
+                    .iter()
+                    .any(|identity| glob::matches(pattern, identity))
+            } else {
+                full.to_lowercase().contains(&pattern.to_lowercase())
+            }
+        }))
+    };
+
+    let mut kept = Vec::with_capacity(branches.len());
+    for branch in branches {
+        let mut found = matches(branch.id)?;
+        if !found && cli.all_authors {
+            let mut walk = repo.revwalk()?;
+            walk.push(branch.id)?;
+            if let Some(base) = base {
+                walk.hide(base)?;
+            }
+            for id in walk {
+                if matches(id?)? {
+                    found = true;
+                    break;
+                }
+            }
+        }
+        if found {
+            kept.push(branch);
+        }
+    }
+    Ok(kept)
+}
+
+/// `--owner`: branches touching code CODEOWNERS gives to one of the owners.
+/// Their owners are filled in on the way, for `--owners` too.
+fn owned(backend: &dyn Backend, cli: &Cli, mut branches: Vec<Branch>) -> Result<Vec<Branch>> {
+    if cli.owner.is_empty() && !cli.owners {
+        return Ok(branches);
+    }
+    if let Some(repo) = backend.repo() {
+        owners::annotate(repo, &mut branches)?;
+    }
+    if !cli.owner.is_empty() {
+        branches.retain(|branch| {
+            branch
+                .owners
+                .iter()
+                .any(|owner| cli.owner.iter().any(|wanted| owners::is(owner, wanted)))
+        });
+    }
+    Ok(branches)
+}
+
+/// `--exclude-recent-authors-days`: on a shared clone, leaves out branches
+/// committed to in the last N days, and those last committed to by someone
+/// with a commit anywhere in the repository since, so colleagues' branches
+/// aren't cleaned up from under them. The user's own commits don't count.
+fn inactive(backend: &dyn Backend, cli: &Cli, branches: Vec<Branch>) -> Result<Vec<Branch>> {
+    let days = match cli.exclude_recent_authors_days {
+        Some(days) => days,
+        None => return Ok(branches),
+    };
+    let branches: Vec<Branch> = branches
+        .into_iter()
+        .filter(|branch| retention::age_days(branch) >= days)
+        .collect();
+    let repo = match backend.repo() {
+        Some(repo) => repo,
+        None => return Ok(branches),
+    };
+
+    let mut active = active_authors(repo, Utc::now().timestamp() - days * 86400)?;
+    // Whoever is cleaning up decides about their own branches.
+    if let Ok(email) = repo.config()?.get_string("user.email") {
+        active.remove(&email.to_lowercase());
+    }
+    let decoder = Decoder::new(repo);
+    let mut kept = Vec::with_capacity(branches.len());
+    for branch in branches {
+        let author = decoder.author_email(&repo.find_commit(branch.id)?);
+        if !active.contains(&author.to_lowercase()) {
+            kept.push(branch);
+        }
+    }
+    Ok(kept)
+}
+
+/// The emails of everyone with a commit on a branch or remote-tracking
+/// branch made since `since`, in seconds. The walk goes newest first and
+/// stops at the first older commit, so only recent history is read.
+fn active_authors(repo: &Repository, since: i64) -> Result<HashSet<String>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push_glob("refs/heads/*")?;
+    walk.push_glob("refs/remotes/*")?;
+
+    let decoder = Decoder::new(repo);
+    let mut authors = HashSet::new();
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        if commit.time().seconds() < since {
+            break;
+        }
+        authors.insert(decoder.author_email(&commit).to_lowercase());
+    }
+    Ok(authors)
+}
+
+fn resolve(repo: &Repository, rev: &str) -> Result<Oid> {
+    Ok(repo.revparse_single(rev)?.peel_to_commit()?.id())
+}
+
+/// Whether `ancestor` is `descendant` or reachable from it.
+fn is_ancestor(repo: &Repository, ancestor: Oid, descendant: Oid) -> Result<bool> {
+    Ok(ancestor == descendant || repo.graph_descendant_of(descendant, ancestor)?)
+}
+
+/// Whether `--stdin` gave the branches to offer.
+pub fn reads_stdin(cli: &Cli) -> bool {
+    cli.from_file
+        .as_deref()
+        .is_some_and(|path| path.as_os_str() == "-")
+}
+
+/// The branch names `--from-file` or `--stdin` gave, if either did. They come
+/// one per line, and what `git branch` prints around them is ignored: the
+/// `*` or `+` in front, anything after the name, and `(HEAD detached ...)`.
+pub fn listed(cli: &Cli) -> Result<Option<BTreeSet<String>>> {
+    let path = match &cli.from_file {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let mut contents = String::new();
+    if reads_stdin(cli) {
+        std::io::stdin().read_to_string(&mut contents)?;
+    } else {
+        contents = std::fs::read_to_string(path)?;
+    }
+
+    let names = contents
+        .lines()
+        .map(|line| line.trim_start_matches(['*', '+', ' ', '\t']))
+        .filter(|line| !line.starts_with('('))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| name.strip_prefix("refs/heads/").unwrap_or(name).to_string())
+        .collect();
+    Ok(Some(names))
+}
+
+/// Whether the branch called `name` gets looked at at all: it matches an
+/// `--only` glob, if there are any, and is among the `listed` names, if
+/// those were given.
+pub fn wanted(cli: &Cli, case: Case, listed: Option<&BTreeSet<String>>, name: &str) -> bool {
+    (cli.only.is_empty() || cli.only.iter().any(|pattern| case.matches(pattern, name)))
+        && listed.is_none_or(|names| {
+            names.contains(name)
+                || case == Case::Insensitive && names.iter().any(|listed| case.same(listed, name))
+        })
+}
+
+impl Session<'_> {
+    /// Warns about `--from-file` or `--stdin` names that aren't branches.
+    pub fn warn_unknown(&mut self, names: &BTreeSet<String>) -> Result<()> {
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => return Ok(()),
+        };
+        // Names differing only in case from a branch are that branch.
+        let mut branches = Vec::new();
+        if self.case == Case::Insensitive {
+            for branch in repo.branches(Some(BranchType::Local))? {
+                if let Some(name) = branch?.0.name()? {
+                    branches.push(name.to_string());
+                }
+            }
+        }
+        for name in names {
+            let known = repo.find_branch(name, BranchType::Local).is_ok()
+                || branches.iter().any(|branch| self.case.same(branch, name));
+            if !known {
+                let missing = format!("There is no branch '{}'", name);
+                write!(
+                    self.stdout,
+                    "{}\r\n",
+                    self.theme.paint(Role::Warning, missing)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+//! A synthetic branch set loaded from JSON, for exercising the interactive
+//! session without a repository:
+//!
+//! ```json
+//! {
+//!   "now": "2022-04-01T12:00:00",
+//!   "branches": [
+//!     { "name": "feature/login", "age_days": 40, "merged": true },
+//!     { "name": "feature/search", "pr": { "number": 12, "state": "open" }, "checks": "green" },
+//!     { "name": "release/1.0", "protected": true, "owners": ["@acme/release"] },
+//!     { "name": "wip", "age_days": 2, "head": true }
+//!   ]
+//! }
+//! ```
+//!
+//! `now` is optional and defaults to the current time; pinning it keeps the
+//! rendered timestamps stable with `--date iso` or `--date raw`. It may end
+//! in an offset, as in `2022-04-01T12:00:00+02:00`, and is UTC without one. Deletions only affect the in-memory set.
+
+use crate::backend::{Backend, BASE_BRANCH};
+use crate::branch::Branch;
+use crate::forge::{Ci, PullRequest};
+use crate::json::Value;
+use crate::{Error, Result};
+use chrono::{prelude::*, Duration};
+use git2::{ObjectType, Oid, Repository};
+use std::path::Path;
+
+pub struct Fixture {
+    branches: Vec<Branch>,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let fixture = Value::parse(&contents)
+            .map_err(|message| Error::Fixture(format!("{}: {}", path.display(), message)))?;
+        Self::from_json(&fixture)
+    }
+
+    fn from_json(fixture: &Value) -> Result<Self> {
+        let now = match fixture.get("now").and_then(Value::as_str) {
+            // Without an offset it is UTC.
+            Some(now) => DateTime::parse_from_rfc3339(now)
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(now, "%Y-%m-%dT%H:%M:%S")
+                        .map(|now| FixedOffset::east(0).from_utc_datetime(&now))
+                })
+                .map_err(|e| Error::Fixture(format!("invalid 'now' timestamp: {}", e)))?,
+            None => {
+                let now = Local::now();
+                let now = now.with_timezone(&now.offset().fix());
+                now.with_nanosecond(0).unwrap_or(now)
+            }
+        };
+
+        let entries = fixture
+            .get("branches")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::Fixture("missing 'branches' array".to_string()))?;
+
+        let mut branches = entries
+            .iter()
+            .map(|entry| {
+                let name = entry
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| Error::Fixture("branch without a 'name'".to_string()))?;
+                let age_days = entry.get("age_days").and_then(Value::as_f64).unwrap_or(0.0);
+
+                Ok(Branch {
+                    time: now - Duration::seconds((age_days * 86400.0) as i64),
+                    id: Oid::hash_object(ObjectType::Blob, name.as_bytes())?,
+                    name: name.to_string(),
+                    name_bytes: name.as_bytes().to_vec(),
+                    is_head: entry.get("head").and_then(Value::as_bool).unwrap_or(false),
+                    merged: entry.get("merged").and_then(Value::as_bool),
+                    squash_merged: entry
+                        .get("squash_merged")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                    fork_point: None,
+                    ahead: entry
+                        .get("ahead")
+                        .and_then(Value::as_f64)
+                        .map(|ahead| ahead as usize),
+                    behind: entry
+                        .get("behind")
+                        .and_then(Value::as_f64)
+                        .map(|behind| behind as usize),
+                    upstream: None,
+                    push: None,
+                    orphaned: entry
+                        .get("orphaned")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                    upstream_ahead_behind: None,
+                    pull_request: entry.get("pr").and_then(PullRequest::from_json),
+                    checks: entry
+                        .get("checks")
+                        .and_then(Value::as_str)
+                        .map(Ci::from_state),
+                    protected: entry
+                        .get("protected")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                    protection: None,
+                    stashes: Vec::new(),
+                    wip_commit: false,
+                    size: None,
+                    owners: entry
+                        .get("owners")
+                        .and_then(Value::as_array)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect(),
+                    case_clashes: Vec::new(),
+                    description: entry
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .map(String::from),
+                    keep: entry.get("keep").and_then(Value::as_bool).unwrap_or(false),
+                    worktree: None,
+                })
+            })
+            .filter(|branch| !matches!(branch, Ok(branch) if branch.name == BASE_BRANCH))
+            .collect::<Result<Vec<_>>>()?;
+
+        branches.sort_unstable_by_key(|branch| branch.time);
+
+        Ok(Fixture { branches })
+    }
+}
+
+impl Backend for Fixture {
+    fn scan(
+        &self,
+        wanted: &dyn Fn(&str) -> bool,
+        _progress: &(dyn Fn(usize, usize) + Sync),
+    ) -> Result<Vec<Branch>> {
+        Ok(self
+            .branches
+            .iter()
+            .filter(|branch| wanted(&branch.name))
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&mut self, branch: &Branch) -> Result<()> {
+        self.branches.retain(|b| b.name != branch.name);
+        Ok(())
+    }
+
+    fn restore(&mut self, branch: &Branch, force: bool) -> Result<()> {
+        if self.branches.iter().any(|b| b.name == branch.name) {
+            if !force {
+                return Err(Error::Fixture(format!(
+                    "branch '{}' already exists",
+                    branch.name
+                )));
+            }
+            self.branches.retain(|b| b.name != branch.name);
+        }
+        self.branches.push(branch.clone());
+        Ok(())
+    }
+
+    fn tip(&self, branch: &Branch) -> Option<Oid> {
+        self.branches
+            .iter()
+            .find(|b| b.name == branch.name)
+            .map(|b| b.id)
+    }
+
+    fn rename(&mut self, branch: &Branch, new_name: &str) -> Result<()> {
+        if self.branches.iter().any(|b| b.name == new_name) {
+            return Err(Error::Fixture(format!(
+                "branch '{}' already exists",
+                new_name
+            )));
+        }
+        for b in self.branches.iter_mut().filter(|b| b.name == branch.name) {
+            b.name = new_name.to_string();
+            b.name_bytes = new_name.as_bytes().to_vec();
+        }
+        Ok(())
+    }
+
+    fn has_local_changes(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn checkout(&mut self, branch: &Branch, _stash: bool) -> Result<()> {
+        for b in &mut self.branches {
+            b.is_head = b.name == branch.name;
+        }
+        Ok(())
+    }
+
+    fn checkout_base(&mut self, _stash: bool) -> Result<()> {
+        for b in &mut self.branches {
+            b.is_head = false;
+        }
+        Ok(())
+    }
+
+    fn repo(&self) -> Option<&Repository> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fixture;
+    use crate::backend::Backend;
+    use crate::branch::Branch;
+    use crate::json::Value;
+    use crate::Error;
+    use chrono::{DateTime, FixedOffset};
+
+    fn branches(json: &str) -> Vec<Branch> {
+        let fixture = Fixture::from_json(&Value::parse(json).unwrap())
+            .unwrap_or_else(|e| panic!("{} didn't load: {}", json, e));
+        fixture.branches().unwrap()
+    }
+
+    fn error(json: &str) -> String {
+        match Fixture::from_json(&Value::parse(json).unwrap()) {
+            Err(Error::Fixture(message)) => message,
+            Err(e) => panic!("{} failed otherwise: {}", json, e),
+            Ok(_) => panic!("{} loaded", json),
+        }
+    }
+
+    fn time(rfc3339: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap()
+    }
+
+    #[test]
+    fn now_with_an_offset() {
+        let branches = branches(
+            r#"{"now": "2022-04-01T12:00:00+02:00", "branches": [{"name": "a", "age_days": 1}]}"#,
+        );
+        assert_eq!(branches[0].time, time("2022-03-31T12:00:00+02:00"));
+        assert_eq!(branches[0].time.offset().local_minus_utc(), 2 * 3600);
+    }
+
+    #[test]
+    fn now_without_an_offset_is_utc() {
+        let branches = branches(
+            r#"{"now": "2022-04-01T12:00:00", "branches": [{"name": "a", "age_days": 0.5}]}"#,
+        );
+        assert_eq!(branches[0].time, time("2022-04-01T00:00:00Z"));
+        assert_eq!(branches[0].time.offset().local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn invalid_now() {
+        let message = error(r#"{"now": "yesterday", "branches": []}"#);
+        assert!(
+            message.starts_with("invalid 'now' timestamp"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn oldest_first_without_the_base_branch() {
+        let branches = branches(
+            r#"{"now": "2022-04-01T12:00:00", "branches": [
+                {"name": "new", "age_days": 1, "head": true},
+                {"name": "master", "age_days": 5},
+                {"name": "old", "age_days": 9, "merged": true}
+            ]}"#,
+        );
+        let names: Vec<&str> = branches.iter().map(|branch| branch.name.as_str()).collect();
+        assert_eq!(names, ["old", "new"]);
+        assert_eq!(branches[0].merged, Some(true));
+        assert!(!branches[0].is_head);
+        assert_eq!(branches[1].merged, None);
+        assert!(branches[1].is_head);
+    }
+
+    #[test]
+    fn missing_name() {
+        let message = error(r#"{"branches": [{"name": "a"}, {"age_days": 3}]}"#);
+        assert_eq!(message, "branch without a 'name'");
+    }
+
+    #[test]
+    fn missing_branches() {
+        assert_eq!(
+            error(r#"{"now": "2022-04-01T12:00:00"}"#),
+            "missing 'branches' array"
+        );
+    }
+
+    #[test]
+    fn names_from_escapes() {
+        let branches = branches(r#"{"branches": [{"name": "fix/\ud83d\ude00"}]}"#);
+        assert_eq!(branches[0].name, "fix/😀");
+        assert_eq!(branches[0].name_bytes, "fix/😀".as_bytes());
+    }
+}
@@ -0,0 +1,237 @@
+//! Forge data saved between runs in `.git/delete-branches-cache/forge.json`, so
+//! that a session can show pull requests, CI state and protection without
+//! waiting on the API. `sync` fills it ahead of time; `--check-prs` tops it
+//! up with whatever is missing.
+//!
+//! Entries are keyed by branch name and only trusted while the branch still
+//! points at the commit they were fetched for, and for a few hours at most.
+//! Older ones are kept for a while longer, for `--check-prs` to show when
+//! the API quota runs low.
+
+use crate::branch::Branch;
+use crate::forge::{remote_name, Ci, Forge, ForgeStatus, PullRequest};
+use crate::json::Value;
+use crate::{glob, state, Result};
+use git2::{Oid, Repository};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const FILE: &str = "forge.json";
+
+/// How long fetched data is trusted, in seconds.
+const TTL: i64 = 6 * 60 * 60;
+
+/// How long fetched data is kept, to fall back on when the API quota runs
+/// low, in seconds.
+const KEEP: i64 = 14 * 24 * 60 * 60;
+
+/// Fetched entries are flushed to disk every so often, so an interrupted
+/// `sync` doesn't lose everything.
+const SAVE_EVERY: usize = 50;
+
+pub struct ForgeCache {
+    path: PathBuf,
+    project: String,
+    branches: HashMap<String, Entry>,
+    /// Protected branch names or patterns, with when they were fetched.
+    protected: Option<(i64, Vec<String>)>,
+    unsaved: usize,
+}
+
+struct Entry {
+    tip: Oid,
+    fetched_at: i64,
+    status: ForgeStatus,
+}
+
+impl ForgeCache {
+    /// Loads the cache for `project`. A cache written for another project
+    /// (after `origin` was changed) or that can't be read starts out empty.
+    pub fn load(repo: &Repository, project: &str) -> Result<ForgeCache> {
+        let path = state::cache_dir(repo).join(FILE);
+        let mut cache = ForgeCache {
+            path,
+            project: project.to_string(),
+            branches: HashMap::new(),
+            protected: None,
+            unsaved: 0,
+        };
+
+        let json = match state::read(&cache.path)?.map(|contents| Value::parse(&contents)) {
+            Some(Ok(json)) => json,
+            _ => return Ok(cache),
+        };
+        if json.get("project").and_then(Value::as_str) != Some(project) {
+            return Ok(cache);
+        }
+
+        if let Some(Value::Object(branches)) = json.get("branches") {
+            cache.branches = branches
+                .iter()
+                .filter_map(|(name, entry)| Some((name.clone(), Entry::from_json(entry)?)))
+                .collect();
+        }
+
+        cache.protected = json.get("protected").and_then(|protected| {
+            let fetched_at = protected.get("fetched_at")?.as_f64()? as i64;
+            let patterns = protected
+                .get("patterns")?
+                .as_array()?
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(String::from))
+                .collect();
+            Some((fetched_at, patterns))
+        });
+
+        Ok(cache)
+    }
+
+    /// The cached status of `branch`, if it is still current.
+    pub fn fresh(&self, branch: &Branch) -> Option<&ForgeStatus> {
+        self.branches
+            .get(&branch.name)
+            .filter(|entry| entry.tip == branch.id && is_fresh(entry.fetched_at))
+            .map(|entry| &entry.status)
+    }
+
+    /// Whatever was last fetched for `branch` at its current tip, however
+    /// long ago, for when the quota doesn't allow asking again.
+    pub fn last_known(&self, branch: &Branch) -> Option<&ForgeStatus> {
+        self.branches
+            .get(&branch.name)
+            .filter(|entry| entry.tip == branch.id)
+            .map(|entry| &entry.status)
+    }
+
+    pub fn insert(&mut self, branch: &Branch, status: ForgeStatus) -> Result<()> {
+        self.branches.insert(
+            branch.name.clone(),
+            Entry {
+                tip: branch.id,
+                fetched_at: now(),
+                status,
+            },
+        );
+
+        self.unsaved += 1;
+        if self.unsaved >= SAVE_EVERY {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// The protected branch patterns, if they were fetched recently.
+    pub fn protected(&self) -> Option<&[String]> {
+        match &self.protected {
+            Some((fetched_at, patterns)) if is_fresh(*fetched_at) => Some(patterns),
+            _ => None,
+        }
+    }
+
+    /// Fetches the protected branch patterns unless they are fresh already.
+    pub fn refresh_protected(&mut self, forge: &Forge) -> Result<&[String]> {
+        if self.protected().is_none() {
+            self.protected = Some((now(), forge.protected_branches()?));
+            self.unsaved += 1;
+        }
+        Ok(self.protected().unwrap_or_default())
+    }
+
+    /// Marks the branches the forge protects, as far as the cache knows.
+    pub fn mark_protected(&self, branches: &mut [Branch]) {
+        let patterns = self.protected().unwrap_or_default();
+        for branch in branches {
+            let name = remote_name(branch);
+            branch.protected = patterns.iter().any(|pattern| glob::matches(pattern, &name));
+        }
+    }
+
+    /// Fills in whatever is fresh in the cache, without any requests.
+    pub fn annotate(&self, branches: &mut [Branch]) {
+        for branch in branches.iter_mut() {
+            if let Some(status) = self.fresh(branch) {
+                status.clone().apply(branch);
+            }
+        }
+        self.mark_protected(branches);
+    }
+
+    /// Writes the cache out, dropping entries too old to be used again.
+    pub fn save(&mut self) -> Result<()> {
+        self.branches
+            .retain(|_, entry| (0..KEEP).contains(&(now() - entry.fetched_at)));
+
+        let mut branches: Vec<_> = self.branches.iter().collect();
+        branches.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        let mut json = vec![
+            ("project".to_string(), self.project.as_str().into()),
+            (
+                "branches".to_string(),
+                Value::Object(
+                    branches
+                        .into_iter()
+                        .map(|(name, entry)| (name.clone(), entry.to_json()))
+                        .collect(),
+                ),
+            ),
+        ];
+        if let Some((fetched_at, patterns)) = &self.protected {
+            json.push((
+                "protected".to_string(),
+                Value::Object(vec![
+                    ("fetched_at".to_string(), (*fetched_at).into()),
+                    ("patterns".to_string(), patterns.clone().into()),
+                ]),
+            ));
+        }
+
+        state::write(&self.path, &Value::Object(json).to_string())?;
+        self.unsaved = 0;
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn from_json(entry: &Value) -> Option<Entry> {
+        Some(Entry {
+            tip: Oid::from_str(entry.get("tip")?.as_str()?).ok()?,
+            fetched_at: entry.get("fetched_at")?.as_f64()? as i64,
+            status: ForgeStatus {
+                pull_request: entry.get("pr").and_then(PullRequest::from_json),
+                checks: entry
+                    .get("checks")
+                    .and_then(Value::as_str)
+                    .map(Ci::from_state),
+            },
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("tip".to_string(), self.tip.to_string().into()),
+            ("fetched_at".to_string(), self.fetched_at.into()),
+            (
+                "pr".to_string(),
+                self.status
+                    .pull_request
+                    .as_ref()
+                    .map_or(Value::Null, PullRequest::to_json),
+            ),
+            (
+                "checks".to_string(),
+                self.status
+                    .checks
+                    .map_or(Value::Null, |ci| ci.name().into()),
+            ),
+        ])
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn is_fresh(fetched_at: i64) -> bool {
+    (0..TTL).contains(&(now() - fetched_at))
+}
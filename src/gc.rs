@@ -0,0 +1,90 @@
+//! `git delete-branches gc [--expire-reflogs]`: runs `git gc` so deleted
+//! branches give their disk space back, and says how much they gave.
+//!
+//! A plain `git gc` frees little right after a cleanup: `HEAD`'s reflog still
+//! reaches the deleted commits for `gc.reflogExpireUnreachable` (30 days),
+//! and unreachable objects are kept for `gc.pruneExpire` (2 weeks) after
+//! that. `--expire-reflogs` drops the reflog entries nothing else reaches
+//! and prunes at once, which also leaves `restore` only the trash to bring
+//! branches back from. What the trash holds is kept either way, its refs
+//! still point at it.
+
+use crate::backend::Backend;
+use crate::cli::Cli;
+use crate::size::human_bytes;
+use crate::trash::Trash;
+use crate::{state, Error, Result};
+use git2::Repository;
+use std::path::Path;
+use std::process::Command;
+
+pub fn run(backend: &dyn Backend, cli: &Cli) -> Result<()> {
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let objects = state::common_dir(repo).join("objects");
+    let before = disk_usage(&objects)?;
+
+    if cli.expire_reflogs {
+        println!("Expiring reflog entries of unreachable commits...");
+        git(
+            repo,
+            &["reflog", "expire", "--expire-unreachable=now", "--all"],
+        )?;
+        println!("Running git gc --prune=now...");
+        git(repo, &["gc", "--quiet", "--prune=now"])?;
+    } else {
+        println!("Running git gc...");
+        git(repo, &["gc", "--quiet"])?;
+    }
+
+    let after = disk_usage(&objects)?;
+    println!(
+        "Objects took {}, now {}: {} reclaimed",
+        human_bytes(before),
+        human_bytes(after),
+        human_bytes(before.saturating_sub(after))
+    );
+
+    let trashed: usize = Trash::all(repo)?
+        .iter()
+        .map(|trash| trash.entries.len())
+        .sum();
+    if trashed > 0 {
+        println!(
+            "{} deleted branch(es) are kept in the trash, for restore",
+            trashed
+        );
+    }
+    if !cli.expire_reflogs {
+        println!("Reflogs may still hold on to deleted branches, --expire-reflogs lets go of them");
+    }
+    Ok(())
+}
+
+fn git(repo: &Repository, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo.path())
+        .output()
+        .map_err(|e| Error::Gc(format!("running git: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::Gc(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The bytes taken by the files under `path`.
+fn disk_usage(path: &Path) -> Result<u64> {
+    let mut bytes = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        bytes += if metadata.is_dir() {
+            disk_usage(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(bytes)
+}
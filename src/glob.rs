@@ -0,0 +1,140 @@
+//! Shell-style wildcards, as used in branch patterns.
+
+use crate::config::Config;
+use crate::{Error, Result};
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for a single one.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*` if the rest fails to match.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    t = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether branch names that differ only in case are the same name to the
+/// patterns and names given to the session: `--only`, `--from-file`,
+/// `delete-branches.protect`, `--unprotect`, retention policies, scripts
+/// and `/`. Set with `--ignore-case` or `--no-ignore-case`, or in config:
+///
+/// ```ini
+/// [delete-branches]
+///     ignoreCase = true
+/// ```
+///
+/// Without either it follows `core.ignoreCase`, which `git init` sets on
+/// case-insensitive file systems, where `Feature/x` and `feature/x` are one
+/// ref file and so one branch; failing that, it goes by what the platform's
+/// file systems usually are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Case {
+    Sensitive,
+    Insensitive,
+}
+
+impl Case {
+    pub fn load(config: &Config, forced: Option<bool>) -> Result<Case> {
+        let ignore = match forced {
+            Some(ignore) => ignore,
+            None => match ["delete-branches.ignoreCase", "core.ignoreCase"]
+                .iter()
+                .find_map(|key| Some((key, config.string(key)?)))
+            {
+                Some((key, value)) => git2::Config::parse_bool(value.as_str())
+                    .map_err(|_| Error::InvalidValue(key.to_string(), value))?,
+                None => cfg!(any(windows, target_os = "macos")),
+            },
+        };
+        Ok(if ignore {
+            Case::Insensitive
+        } else {
+            Case::Sensitive
+        })
+    }
+
+    /// [`matches`], ignoring case if names do.
+    pub fn matches(self, pattern: &str, text: &str) -> bool {
+        match self {
+            Case::Sensitive => matches(pattern, text),
+            Case::Insensitive => matches(&pattern.to_lowercase(), &text.to_lowercase()),
+        }
+    }
+
+    /// Whether `a` and `b` name the same branch.
+    pub fn same(self, a: &str, b: &str) -> bool {
+        match self {
+            Case::Sensitive => a == b,
+            Case::Insensitive => a.to_lowercase() == b.to_lowercase(),
+        }
+    }
+
+    /// Whether `text` has `part` in it.
+    pub fn contains(self, text: &str, part: &str) -> bool {
+        match self {
+            Case::Sensitive => text.contains(part),
+            Case::Insensitive => text.to_lowercase().contains(&part.to_lowercase()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn literals() {
+        assert!(matches("main", "main"));
+        assert!(!matches("main", "mains"));
+        assert!(!matches("main", "mai"));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn stars() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "feature/x"));
+        assert!(matches("feature/*", "feature/"));
+        assert!(matches("feature/*", "feature/a/b"));
+        assert!(!matches("feature/*", "features/a"));
+        assert!(matches("*-wip", "login-wip"));
+        assert!(!matches("*-wip", "login-wip2"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+        assert!(matches("**x", "yyx"));
+    }
+
+    #[test]
+    fn question_marks() {
+        assert!(matches("v?", "v1"));
+        assert!(!matches("v?", "v"));
+        assert!(!matches("v?", "v12"));
+        assert!(matches("f?x/*", "fix/é"));
+        assert!(matches("?", "é"));
+    }
+}
@@ -0,0 +1,261 @@
+//! `git delete-branches graph`: how branches fork from and merge into the
+//! base branch, as a Graphviz DOT graph or, with `--mermaid`, a Mermaid
+//! flowchart.
+//!
+//! Fork points are drawn as small nodes strung together along the base
+//! branch, oldest first, with each branch hanging off the one it left from.
+//! Merged and squash-merged branches get a dashed edge into the base branch.
+
+use crate::anonymize::Anonymizer;
+use crate::backend::{Backend, BASE_BRANCH};
+use crate::branch::{self, Branch};
+use crate::cli::{Cli, GraphFormat};
+use crate::Result;
+use git2::Oid;
+
+const BASE: &str = "base";
+
+enum Kind {
+    Base,
+    ForkPoint,
+    Branch { merged: bool },
+}
+
+struct Node {
+    id: String,
+    label: String,
+    kind: Kind,
+}
+
+enum Style {
+    /// Along the base branch.
+    Base,
+    /// From a fork point to a branch.
+    Fork,
+    /// From a branch into the base branch.
+    Merge,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+    style: Style,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+pub fn run(backend: &dyn Backend, cli: &Cli) -> Result<()> {
+    let branches = backend.branches()?;
+    let anonymizer = cli.anonymize.then(Anonymizer::new);
+    let graph = build(backend, anonymizer.as_ref(), &branches);
+
+    print!(
+        "{}",
+        match cli.graph_format {
+            GraphFormat::Dot => graph.to_dot(),
+            GraphFormat::Mermaid => graph.to_mermaid(),
+        }
+    );
+    Ok(())
+}
+
+fn build(backend: &dyn Backend, anonymizer: Option<&Anonymizer>, branches: &[Branch]) -> Graph {
+    let mut graph = Graph::default();
+    graph.nodes.push(Node {
+        id: BASE.to_string(),
+        label: BASE_BRANCH.to_string(),
+        kind: Kind::Base,
+    });
+
+    // Merged branches are their own fork point, so only unmerged ones
+    // leave from somewhere along the base branch.
+    let mut fork_points: Vec<(i64, Oid)> = branches
+        .iter()
+        .filter(|branch| branch.merged != Some(true))
+        .filter_map(|branch| branch.fork_point)
+        .map(|oid| {
+            let time = backend
+                .repo()
+                .and_then(|repo| repo.find_commit(oid).ok())
+                .map_or(0, |commit| commit.time().seconds());
+            (time, oid)
+        })
+        .collect();
+    fork_points.sort_unstable();
+    fork_points.dedup();
+
+    // Numbered rather than named after the commit, which would give it away
+    // with `--anonymize`.
+    let fork_id = |oid: Oid| {
+        let i = fork_points
+            .iter()
+            .position(|(_, fork_point)| *fork_point == oid);
+        format!("fork_{}", i.unwrap_or_default())
+    };
+
+    for (_, oid) in &fork_points {
+        graph.nodes.push(Node {
+            id: fork_id(*oid),
+            label: match anonymizer {
+                Some(_) => String::new(),
+                None => oid.to_string()[..7].to_string(),
+            },
+            kind: Kind::ForkPoint,
+        });
+    }
+    let along_base: Vec<String> = fork_points
+        .iter()
+        .map(|(_, oid)| fork_id(*oid))
+        .chain([BASE.to_string()])
+        .collect();
+    for pair in along_base.windows(2) {
+        graph.edges.push(Edge {
+            from: pair[0].clone(),
+            to: pair[1].clone(),
+            label: None,
+            style: Style::Base,
+        });
+    }
+
+    for (i, branch) in branches.iter().enumerate() {
+        let id = format!("branch_{}", i);
+        let name = match anonymizer {
+            Some(anonymizer) => anonymizer.branch(&branch.name),
+            None => branch.name.clone(),
+        };
+        let merged = branch.merged == Some(true) || branch.squash_merged;
+        graph.nodes.push(Node {
+            id: id.clone(),
+            label: format!("{}\n{}", name, branch::age(branch.time)),
+            kind: Kind::Branch { merged },
+        });
+
+        if let (Some(false), Some(fork_point)) = (branch.merged, branch.fork_point) {
+            graph.edges.push(Edge {
+                from: fork_id(fork_point),
+                to: id.clone(),
+                label: branch.ahead.map(|ahead| format!("+{}", ahead)),
+                style: Style::Fork,
+            });
+        }
+        if merged {
+            graph.edges.push(Edge {
+                from: id,
+                to: BASE.to_string(),
+                label: Some(
+                    if branch.squash_merged {
+                        "squash-merged"
+                    } else {
+                        "merged"
+                    }
+                    .to_string(),
+                ),
+                style: Style::Merge,
+            });
+        }
+    }
+
+    graph
+}
+
+impl Graph {
+    fn to_dot(&self) -> String {
+        let mut dot = String::from(
+            "digraph branches {\n    rankdir=LR;\n    node [shape=box, style=rounded];\n",
+        );
+
+        for node in &self.nodes {
+            let attributes = match node.kind {
+                Kind::Base => "style=\"rounded,bold\"",
+                Kind::ForkPoint => "shape=circle, fontsize=8",
+                Kind::Branch { merged: true } => "color=green4",
+                Kind::Branch { merged: false } => "",
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\"{}{}];\n",
+                node.id,
+                dot_escape(&node.label),
+                if attributes.is_empty() { "" } else { ", " },
+                attributes
+            ));
+        }
+
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label=\"{}\"", dot_escape(label)));
+            }
+            match edge.style {
+                Style::Base => attributes.push("penwidth=2".to_string()),
+                Style::Fork => {}
+                Style::Merge => attributes.push("style=dashed, color=green4".to_string()),
+            }
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [{}];\n",
+                edge.from,
+                edge.to,
+                attributes.join(", ")
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+
+        for node in &self.nodes {
+            let label = mermaid_escape(&node.label);
+            mermaid.push_str(&match node.kind {
+                Kind::Base => format!("    {}[[\"{}\"]]\n", node.id, label),
+                Kind::ForkPoint => format!("    {}((\"{}\"))\n", node.id, label),
+                Kind::Branch { .. } => format!("    {}([\"{}\"])\n", node.id, label),
+            });
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.style {
+                Style::Base => "==>",
+                Style::Fork => "-->",
+                Style::Merge => "-.->",
+            };
+            let label = match &edge.label {
+                Some(label) => format!("|\"{}\"| ", mermaid_escape(label)),
+                None => String::new(),
+            };
+            mermaid.push_str(&format!(
+                "    {} {} {}{}\n",
+                edge.from, arrow, label, edge.to
+            ));
+        }
+
+        let merged: Vec<&str> = self
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, Kind::Branch { merged: true }))
+            .map(|node| node.id.as_str())
+            .collect();
+        if !merged.is_empty() {
+            mermaid.push_str("    classDef merged stroke:#080,color:#080\n");
+            mermaid.push_str(&format!("    class {} merged\n", merged.join(",")));
+        }
+
+        mermaid
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('\n', "<br>")
+}
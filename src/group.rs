@@ -0,0 +1,128 @@
+//! `--group-by-prefix`: triage whole namespaces before single branches.
+
+use crate::branch::{self, Branch};
+use crate::bulk::Bulk;
+use crate::input::Key;
+use crate::keys::BranchAction;
+use crate::session::{Flow, Session};
+use crate::Result;
+use std::io::Write;
+
+impl Session<'_> {
+    /// Asks about each prefix group, oldest first, before going into its
+    /// branches. Branches without a prefix are offered one by one as usual.
+    pub fn run_grouped(&mut self, branches: Vec<Branch>) -> Result<Flow> {
+        let mut groups: Vec<(String, Vec<Branch>)> = Vec::new();
+        for branch in branches {
+            match groups
+                .iter_mut()
+                .find(|(prefix, _)| prefix == branch.prefix())
+            {
+                Some((_, members)) => members.push(branch),
+                None => groups.push((branch.prefix().to_string(), vec![branch])),
+            }
+        }
+
+        // Branches arrive sorted by age, so each group's first is its oldest.
+        groups.sort_by_key(|(_, members)| members[0].time);
+
+        for (prefix, members) in groups {
+            let flow = if prefix.is_empty() || members.len() == 1 || self.rest.is_some() {
+                self.run(members)?
+            } else {
+                self.act_on_group(&prefix, members)?
+            };
+
+            if let Flow::Quit | Flow::Review = flow {
+                return Ok(flow);
+            }
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    fn act_on_group(&mut self, prefix: &str, members: Vec<Branch>) -> Result<Flow> {
+        loop {
+            write!(
+                self.stdout,
+                "{} — {} branches, oldest {} (e/d/s/q/?) > ",
+                prefix,
+                members.len(),
+                branch::age(members[0].time)
+            )?;
+            self.stdout.flush()?;
+
+            let c = match self.input.next_key()? {
+                Key::Char(c) => c,
+                Key::Interrupt => {
+                    write!(self.stdout, "^C\r\nQuitting...\r\n")?;
+                    return Ok(Flow::Quit);
+                }
+            };
+            write!(self.stdout, "{}\r\n", c)?;
+
+            match c {
+                'e' => return self.run(members),
+                's' => {
+                    for branch in members {
+                        self.keep(branch)?;
+                    }
+                    return Ok(Flow::Continue);
+                }
+                'd' => {
+                    self.input.drain()?;
+                    if !self.confirm(&format!(
+                        "Delete all {} branches in {}?",
+                        members.len(),
+                        prefix
+                    ))? {
+                        continue;
+                    }
+
+                    let mut members = members;
+                    members.sort_by_key(|branch| std::cmp::Reverse(branch.depth()));
+                    let mut bulk = Bulk::new(0, members.len());
+                    let mut stopped = false;
+                    for branch in members {
+                        stopped = stopped
+                            || !bulk.next(&mut self.stdout, &mut self.input, &self.theme)?;
+                        if stopped {
+                            self.keep(branch)?;
+                            continue;
+                        }
+                        let branch = self.prefetched(branch)?;
+                        if let Some(reason) = self.ignore_reason(&branch) {
+                            write!(
+                                self.stdout,
+                                "Ignoring '{}' because {}\r\n",
+                                branch.name, reason
+                            )?;
+                        } else if !self.force_confirmed(&branch)?
+                            || !self.action_confirmed(BranchAction::Delete, &branch)?
+                        {
+                            self.keep(branch)?;
+                        } else if let Flow::Quit = self.delete(branch)? {
+                            bulk.clear(&mut self.stdout)?;
+                            return Ok(Flow::Quit);
+                        }
+                        bulk.advance(&mut self.stdout, &self.theme)?;
+                    }
+                    return Ok(Flow::Continue);
+                }
+                'q' => {
+                    write!(self.stdout, "Quitting...\r\n")?;
+                    return Ok(Flow::Quit);
+                }
+                _ => {
+                    write!(
+                        self.stdout,
+                        "e - Expand the group and go through its branches\r\n"
+                    )?;
+                    write!(self.stdout, "d - Delete every branch in the group\r\n")?;
+                    write!(self.stdout, "s - Skip the group, keeping its branches\r\n")?;
+                    write!(self.stdout, "q - Quit\r\n")?;
+                }
+            }
+        }
+    }
+}
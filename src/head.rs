@@ -0,0 +1,122 @@
+//! Repositories whose HEAD isn't a branch checked out in a working tree.
+//!
+//! With HEAD detached no branch is current, so none is held back for being
+//! checked out, except the one a rebase or bisect in progress will return
+//! to: git still counts it as checked out, and deleting it would leave the
+//! rebase with nowhere to finish.
+//!
+//! A bare repository is cleaned up server-side: nothing can be checked out
+//! or rebased, the branch HEAD names is kept as the one clones get, and in
+//! a mirror (`remote.<name>.mirror`) `D` deletes a branch on that remote too.
+
+use crate::session::Session;
+use crate::theme::Role;
+use crate::Result;
+use git2::{Oid, Repository};
+use std::io::Write;
+
+pub enum Head {
+    /// HEAD is a branch, or will be one once it has a commit.
+    Branch,
+    Detached {
+        at: Oid,
+        /// The branch under way and what is happening to it, if a rebase or
+        /// bisect is in progress.
+        interrupted: Option<(String, &'static str)>,
+    },
+    Bare {
+        /// The branch HEAD names.
+        default: Option<String>,
+    },
+}
+
+impl Head {
+    pub fn of(repo: &Repository) -> Head {
+        if repo.is_bare() {
+            let default = repo.find_reference("HEAD").ok().and_then(|head| {
+                Some(
+                    head.symbolic_target()?
+                        .strip_prefix("refs/heads/")?
+                        .to_string(),
+                )
+            });
+            return Head::Bare { default };
+        }
+        match repo.head() {
+            Ok(head) if repo.head_detached().unwrap_or(false) => Head::Detached {
+                at: head.target().unwrap_or_else(Oid::zero),
+                interrupted: interrupted(repo),
+            },
+            _ => Head::Branch,
+        }
+    }
+
+    pub fn is_bare(&self) -> bool {
+        matches!(self, Head::Bare { .. })
+    }
+
+    /// Why `name` can't be deleted whatever `--include-head` says, if HEAD
+    /// holds on to it.
+    pub fn holds(&self, name: &str) -> Option<String> {
+        match self {
+            Head::Detached {
+                interrupted: Some((branch, what)),
+                ..
+            } if branch == name => Some(format!("it is {}", what)),
+            Head::Bare {
+                default: Some(default),
+            } if default == name => Some("it is the bare repository's HEAD".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The branch a rebase or bisect started from, and which of them is under
+/// way. git keeps it in the git directory until the rebase is done or the
+/// bisect is reset.
+pub fn interrupted(repo: &Repository) -> Option<(String, &'static str)> {
+    const STATES: [(&str, &str); 3] = [
+        ("rebase-merge/head-name", "being rebased"),
+        ("rebase-apply/head-name", "being rebased"),
+        ("BISECT_START", "being bisected"),
+    ];
+    STATES.iter().find_map(|(file, what)| {
+        let contents = std::fs::read_to_string(repo.path().join(file)).ok()?;
+        let name = contents.trim();
+        // A bisect started from a detached HEAD holds a commit id instead.
+        let name = name
+            .strip_prefix("refs/heads/")
+            .or_else(|| Oid::from_str(name).is_err().then_some(name))?;
+        Some((name.to_string(), *what))
+    })
+}
+
+impl Session<'_> {
+    /// Says how the session differs when HEAD is detached or there is no
+    /// working tree.
+    pub fn announce_head(&mut self) -> Result<()> {
+        let note = match &self.head {
+            Head::Branch => return Ok(()),
+            Head::Detached {
+                at,
+                interrupted: Some((branch, what)),
+            } => format!(
+                "HEAD is detached at {:.7} while '{}' is {}, so that branch is kept until it is done.",
+                at, branch, what
+            ),
+            Head::Detached { at, .. } => format!(
+                "HEAD is detached at {:.7}: no branch is checked out, so none is kept for being the current one.",
+                at
+            ),
+            Head::Bare { default } => format!(
+                "Bare repository: server-side cleanup, nothing is checked out or rebased{}.",
+                match default {
+                    Some(default) => format!(", and '{}' is kept as HEAD", default),
+                    None => String::new(),
+                }
+            ),
+        };
+        write!(self.stdout, "{}\r\n", self.theme.paint(Role::Badge, note))?;
+        Ok(())
+    }
+}
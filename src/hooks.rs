@@ -0,0 +1,115 @@
+//! Hooks the session runs along the way, and `--no-hooks` to get past them
+//! when a broken one stands between a repository and a cleanup it needs.
+//!
+//! Commands can be run around every deletion, say to push the branch to a
+//! backup remote first:
+//!
+//! ```ini
+//! [delete-branches]
+//!     preDelete = git push backup \"$DELETE_BRANCHES_SHA:refs/heads/$DELETE_BRANCHES_BRANCH\"
+//!     postDelete = echo \"$DELETE_BRANCHES_BRANCH\" >> ~/deleted-branches
+//! ```
+//!
+//! They run with `sh` in the working tree, or the git directory of a bare
+//! repository, and get the branch name, its tip and that directory as
+//! `DELETE_BRANCHES_BRANCH`, `DELETE_BRANCHES_SHA` and
+//! `DELETE_BRANCHES_REPO`. A `preDelete` that fails keeps the branch; a
+//! `postDelete` that fails is only reported.
+//!
+//! The git commands run for the rebase action would run the repository's
+//! `pre-rebase` and `post-checkout` hooks; with `--no-hooks` they are run
+//! with `core.hooksPath` pointed at nothing instead, and neither of the
+//! above runs. Skipping hooks is announced before the first prompt and
+//! noted in the audit log.
+
+use crate::branch::Branch;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::{audit, run, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+impl Session<'_> {
+    /// Says loudly that hooks are off, and records it.
+    pub fn announce_skipped_hooks(&mut self) -> Result<()> {
+        if !self.cli.no_hooks {
+            return Ok(());
+        }
+        write!(
+            self.stdout,
+            "{}\r\n",
+            self.theme.paint(
+                Role::Warning,
+                "--no-hooks: NO HOOKS WILL RUN THIS SESSION. Whatever they check or back up is up to you."
+            )
+        )?;
+        if let Some(repo) = self.backend.repo() {
+            audit::note(repo, run::next(), "hooks skipped")?;
+        }
+        Ok(())
+    }
+
+    /// Runs the `preDelete` hook for `branch`, if there is one. `false` if
+    /// it failed and the branch should be kept.
+    pub fn pre_delete_hook(&mut self, branch: &Branch) -> Result<bool> {
+        self.delete_hook("preDelete", branch)
+    }
+
+    /// Runs the `postDelete` hook for `branch`, if there is one.
+    pub fn post_delete_hook(&mut self, branch: &Branch) -> Result<()> {
+        self.delete_hook("postDelete", branch).map(|_| ())
+    }
+
+    fn delete_hook(&mut self, hook: &str, branch: &Branch) -> Result<bool> {
+        let command = match self.config.string(&format!("delete-branches.{}", hook)) {
+            Some(command) if !self.cli.no_hooks => command,
+            _ => return Ok(true),
+        };
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => return Ok(true),
+        };
+        let dir = repo.workdir().unwrap_or_else(|| repo.path());
+        let mut sh = Command::new("sh");
+        sh.args(["-c", &command])
+            .current_dir(dir)
+            .env("DELETE_BRANCHES_BRANCH", &branch.name)
+            .env("DELETE_BRANCHES_SHA", branch.id.to_string())
+            .env("DELETE_BRANCHES_REPO", dir);
+
+        self.stdout.flush()?;
+        let status = self.input.suspended(|| sh.status())??;
+        if status.success() {
+            return Ok(true);
+        }
+
+        let failed = match hook {
+            "preDelete" => format!(
+                "The {} hook failed ({}), keeping '{}'",
+                hook, status, branch.name
+            ),
+            _ => format!(
+                "The {} hook failed ({}) for '{}'",
+                hook, status, branch.name
+            ),
+        };
+        write!(
+            self.stdout,
+            "{}\r\n",
+            self.theme.paint(Role::Warning, failed)
+        )?;
+        Ok(false)
+    }
+
+    /// `git`, run in `workdir`, with the repository's hooks unless
+    /// `--no-hooks` is given.
+    pub fn git(&self, workdir: &Path) -> Command {
+        let mut git = Command::new("git");
+        if self.cli.no_hooks {
+            git.args(["-c", "core.hooksPath=/dev/null"]);
+        }
+        git.current_dir(workdir);
+        git
+    }
+}
@@ -0,0 +1,274 @@
+//! `git delete-branches init`: a starter config for a repository, going by
+//! the branches it already has. It looks at the default branch, the
+//! namespaces branches are kept in, locally and on remotes, and which of them
+//! bots push to, suggests protections and retention policies to match, asks
+//! about each, and writes the ones taken to the repository or global config,
+//! or prints them to share with the team.
+//!
+//! Namespaces are told apart by their first part, so `dependabot/npm/x` is
+//! in `dependabot/`. Suggestions already configured are left out, so `init`
+//! can be run again as the repository grows new habits.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch;
+use crate::input::Key;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::{protection, retention, Error, Result};
+use git2::{BranchType, ConfigLevel, Repository};
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// Names of branches that are usually long-lived.
+const LONG_LIVED: [&str; 5] = ["main", "master", "develop", "staging", "production"];
+
+/// Namespaces bots push to. Their branches are superseded by the next
+/// update, so they aren't worth keeping long.
+const BOTS: [&str; 5] = ["dependabot", "renovate", "snyk", "greenkeeper", "pyup"];
+
+/// Namespaces whose names say how long their branches live.
+const NAMESPACES: [(&str, &str); 11] = [
+    ("tmp", "14d"),
+    ("temp", "14d"),
+    ("wip", "14d"),
+    ("spike", "14d"),
+    ("experiment", "30d"),
+    ("hotfix", "30d"),
+    ("fix", "90d"),
+    ("bugfix", "90d"),
+    ("feature", "90d"),
+    ("feat", "90d"),
+    ("chore", "90d"),
+];
+
+/// How long bot branches are kept.
+const BOT_RETENTION: &str = "14d";
+
+/// Namespaces with at least this many branches get a policy even when
+/// their name says nothing about them.
+const BUSY_NAMESPACE: usize = 3;
+
+const OLDEST_SHOWN: usize = 5;
+
+struct Suggestion {
+    key: &'static str,
+    value: String,
+    why: String,
+}
+
+impl Session<'_> {
+    /// Looks the repository over, asks about each suggestion and saves the
+    /// ones taken.
+    pub fn init(&mut self) -> Result<()> {
+        let mut branches = self.backend.branches()?;
+        let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+        let default = default_branch(repo);
+        let remote = remote_branches(repo)?;
+
+        // A branch and its upstream are one branch.
+        let names: BTreeSet<&str> = branches
+            .iter()
+            .map(|branch| branch.name.as_str())
+            .chain(remote.iter().map(String::as_str))
+            .collect();
+        let mut namespaces: Vec<(String, usize)> = Vec::new();
+        for name in names {
+            let namespace = match name.split_once('/') {
+                Some((namespace, _)) => namespace,
+                None => continue,
+            };
+            match namespaces.iter_mut().find(|(known, _)| known == namespace) {
+                Some((_, count)) => *count += 1,
+                None => namespaces.push((namespace.to_string(), 1)),
+            }
+        }
+        namespaces.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut text = format!(
+            "{}\r\n  default branch {}, {} local branch(es), {} on remotes\r\n",
+            self.theme.paint(Role::Branch, "This repository"),
+            default,
+            branches.len(),
+            remote.len()
+        );
+        if !namespaces.is_empty() {
+            let listed: Vec<String> = namespaces
+                .iter()
+                .map(|(namespace, count)| {
+                    let bot = if BOTS.contains(&namespace.as_str()) {
+                        " (bot)"
+                    } else {
+                        ""
+                    };
+                    format!("{}/ {}{}", namespace, count, bot)
+                })
+                .collect();
+            text += &format!("  namespaces: {}\r\n", listed.join(", "));
+        }
+        branches.sort_by_key(|branch| branch.time);
+        if !branches.is_empty() {
+            let oldest: Vec<String> = branches
+                .iter()
+                .take(OLDEST_SHOWN)
+                .map(|branch| format!("{} ({})", branch.name, branch::age(branch.time)))
+                .collect();
+            text += &format!("  oldest: {}\r\n", oldest.join(", "));
+        }
+        write!(self.stdout, "{}\r\n", text)?;
+
+        let mut suggestions = Vec::new();
+        for name in LONG_LIVED.iter().copied().chain([default.as_str()]) {
+            let exists = branches.iter().any(|branch| branch.name == name)
+                || remote.iter().any(|remote| remote == name);
+            let why = if name == default {
+                "the default branch"
+            } else {
+                "usually long-lived"
+            };
+            if exists
+                && name != BASE_BRANCH
+                && !suggestions.iter().any(|s: &Suggestion| s.value == name)
+            {
+                suggestions.push(Suggestion {
+                    key: protection::KEY,
+                    value: name.to_string(),
+                    why: why.to_string(),
+                });
+            }
+        }
+        for (namespace, count) in &namespaces {
+            let pattern = format!("{}/*", namespace);
+            let (key, value, why) = if namespace == "release" {
+                (protection::KEY, pattern, "release branches".to_string())
+            } else if BOTS.contains(&namespace.as_str()) {
+                (
+                    retention::KEY,
+                    format!("{} {}", pattern, BOT_RETENTION),
+                    format!("{} bot branch(es), superseded by the next update", count),
+                )
+            } else if let Some((_, age)) = NAMESPACES.iter().find(|(known, _)| known == namespace) {
+                (
+                    retention::KEY,
+                    format!("{} {}", pattern, age),
+                    format!("{} {} branch(es)", count, namespace),
+                )
+            } else if *count >= BUSY_NAMESPACE {
+                (
+                    retention::KEY,
+                    format!("{} 90d", pattern),
+                    format!("{} branch(es) in it", count),
+                )
+            } else {
+                continue;
+            };
+            suggestions.push(Suggestion { key, value, why });
+        }
+
+        let configured = |key: &str, pattern: &str| {
+            self.config.values(key).is_ok_and(|values| {
+                values
+                    .iter()
+                    .any(|(value, _)| value.split_whitespace().next() == Some(pattern))
+            })
+        };
+        suggestions.retain(|suggestion| {
+            let pattern = suggestion.value.split_whitespace().next().unwrap_or("");
+            !configured(suggestion.key, pattern)
+        });
+        suggestions.sort_by_key(|suggestion| suggestion.key != protection::KEY);
+        if suggestions.is_empty() {
+            write!(
+                self.stdout,
+                "Nothing to suggest that isn't configured already.\r\n"
+            )?;
+            return Ok(());
+        }
+
+        let mut taken = Vec::new();
+        for suggestion in suggestions {
+            if self.confirm(&format!(
+                "{} = {} ({})?",
+                short(suggestion.key),
+                suggestion.value,
+                suggestion.why
+            ))? {
+                taken.push(suggestion);
+            }
+        }
+        if taken.is_empty() {
+            return Ok(());
+        }
+
+        let mut snippet = "[delete-branches]\r\n".to_string();
+        for suggestion in &taken {
+            snippet += &format!("    {} = {}\r\n", short(suggestion.key), suggestion.value);
+        }
+        loop {
+            write!(
+                self.stdout,
+                "Save to the repository config, the global config, or print it (r/g/p) > "
+            )?;
+            self.stdout.flush()?;
+            let c = match self.input.next_key()? {
+                Key::Char(c) => c,
+                Key::Interrupt => 'p',
+            };
+            write!(self.stdout, "{}\r\n", c)?;
+
+            let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+            let (mut config, place) = match c {
+                'r' => (repo.config()?.open_level(ConfigLevel::Local)?, "repository"),
+                'g' => (
+                    git2::Config::open_default()?.open_level(ConfigLevel::Global)?,
+                    "global",
+                ),
+                'p' => {
+                    write!(self.stdout, "\r\n{}", snippet)?;
+                    return Ok(());
+                }
+                _ => continue,
+            };
+            for suggestion in &taken {
+                // A regex no value matches adds a value instead of replacing one.
+                config.set_multivar(suggestion.key, "^$", &suggestion.value)?;
+            }
+            write!(self.stdout, "Added to the {} config:\r\n{}", place, snippet)?;
+            return Ok(());
+        }
+    }
+}
+
+/// The branch `origin/HEAD` points at, or the base branch.
+fn default_branch(repo: &Repository) -> String {
+    repo.find_reference("refs/remotes/origin/HEAD")
+        .ok()
+        .and_then(|head| {
+            Some(
+                head.symbolic_target()?
+                    .strip_prefix("refs/remotes/origin/")?
+                    .to_string(),
+            )
+        })
+        .unwrap_or_else(|| BASE_BRANCH.to_string())
+}
+
+/// The names of the branches on every remote, without the remote's name.
+fn remote_branches(repo: &Repository) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for branch in repo.branches(Some(BranchType::Remote))? {
+        let (branch, _) = branch?;
+        let name = match branch.name()?.and_then(|name| name.split_once('/')) {
+            Some((_, name)) if name != "HEAD" => name.to_string(),
+            _ => continue,
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// `protect` for `delete-branches.protect`.
+fn short(key: &str) -> &str {
+    key.rsplit('.').next().unwrap_or(key)
+}
@@ -0,0 +1,293 @@
+use crate::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::tty::IsTty;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The terminal, for reading keys when stdin is taken.
+const TERMINAL: &str = if cfg!(windows) { "CONIN$" } else { "/dev/tty" };
+
+/// What a Windows console takes for the end of input.
+const CTRL_Z: char = '\u{1a}';
+
+/// Keeps the terminal in raw mode for as long as it is alive, restoring it
+/// on drop so early returns and panics don't leave the shell unusable.
+pub struct RawMode;
+
+impl RawMode {
+    pub fn enable() -> Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        crossterm::terminal::disable_raw_mode().ok();
+    }
+}
+
+/// Reads keys through crossterm's event API, on Windows consoles as on
+/// Unix terminals. Only key presses come through, so a key isn't taken
+/// twice where the console reports its release too. Ctrl-C, Ctrl-D and
+/// Ctrl-Z, the end of input on Windows, don't raise signals in raw mode, so
+/// they are reported as `Key::Interrupt`, as are SIGTERM/SIGHUP/SIGINT
+/// delivered from outside the terminal; a second one of those terminates
+/// the program as usual. Other Ctrl chords are ignored rather than taken
+/// for their letter.
+///
+/// Without a terminal on both ends, or with `TERM=dumb`, raw mode isn't to
+/// be relied on, and whole lines are read from stdin instead: the first
+/// character of a line is the key, and the end of input is an interrupt.
+/// Lines may end in CRLF, and a Ctrl-Z line ends the input, as it does when
+/// typed into a Windows console.
+pub struct Input {
+    signaled: Arc<AtomicBool>,
+    /// Reading lines rather than keys.
+    lines: bool,
+    /// Nobody is there to answer, see `--script`.
+    unattended: bool,
+}
+
+pub enum Key {
+    Char(char),
+    Interrupt,
+}
+
+impl Input {
+    pub fn new() -> Result<Self> {
+        let signaled = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        let signals = [
+            signal_hook::consts::SIGTERM,
+            signal_hook::consts::SIGHUP,
+            signal_hook::consts::SIGINT,
+        ];
+        #[cfg(not(unix))]
+        let signals = [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT];
+        for signal in signals {
+            // The flag is only looked at while reading keys or lines, so a
+            // second signal, say during a scan or a push, terminates the
+            // way it would have without the handler. Registered first, this
+            // sees the flag as it was before the signal.
+            signal_hook::flag::register_conditional_default(signal, Arc::clone(&signaled))?;
+            signal_hook::flag::register(signal, Arc::clone(&signaled))?;
+        }
+
+        let dumb = std::env::var_os("TERM").is_some_and(|term| term == "dumb");
+        Ok(Input {
+            signaled,
+            lines: dumb || !io::stdin().is_tty() || !io::stdout().is_tty(),
+            unattended: false,
+        })
+    }
+
+    /// Raw mode for reading single keys, unless keys aren't read.
+    pub fn raw_mode(&self) -> Result<Option<RawMode>> {
+        if self.lines || self.unattended {
+            return Ok(None);
+        }
+        RawMode::enable().map(Some)
+    }
+
+    /// Reads keys from the terminal even though stdin isn't one, for when
+    /// stdin was taken by `--stdin`. Without a terminal to open there is
+    /// nothing left to read, as at the end of input.
+    pub fn set_stdin_taken(&mut self) {
+        let dumb = std::env::var_os("TERM").is_some_and(|term| term == "dumb");
+        let terminal = std::fs::File::open(TERMINAL).is_ok();
+        self.lines = dumb || !terminal || !io::stdout().is_tty();
+    }
+
+    /// Stops reading the terminal: every key after this is an interrupt,
+    /// every line is cancelled and nothing is pressed within any timeout.
+    pub fn set_unattended(&mut self) {
+        self.unattended = true;
+    }
+
+    /// Whether single key presses are read, and so whether waiting for one
+    /// with a timeout means anything.
+    pub fn reads_keys(&self) -> bool {
+        !self.lines && !self.unattended
+    }
+
+    /// Runs `f` with the terminal out of raw mode, for handing it over to
+    /// another program.
+    pub fn suspended<T>(&self, f: impl FnOnce() -> T) -> Result<T> {
+        if !self.reads_keys() {
+            return Ok(f());
+        }
+        crossterm::terminal::disable_raw_mode()?;
+        let result = f();
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(result)
+    }
+
+    /// The next line of stdin without its line ending, `None` at the end of
+    /// input or after a signal.
+    fn stdin_line(&self) -> Result<Option<String>> {
+        let mut line = String::new();
+        let read = io::stdin().lock().read_line(&mut line)?;
+        // PowerShell may start what it pipes with a byte order mark.
+        let line = line.trim_start_matches('\u{feff}');
+        if read == 0 || line.starts_with(CTRL_Z) || self.signaled.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    /// Reads a line of text with basic editing (Backspace, Ctrl-U), echoing
+    /// it as it is typed. Returns `None` if it was cancelled with Esc,
+    /// Ctrl-C, Ctrl-D or Ctrl-Z.
+    pub fn read_line(&mut self, stdout: &mut impl Write) -> Result<Option<String>> {
+        let mut line = String::new();
+        if self.unattended {
+            write!(stdout, "\r\n")?;
+            return Ok(None);
+        }
+        if self.lines {
+            return self.stdin_line();
+        }
+
+        loop {
+            if self.signaled.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+
+            if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+                match code {
+                    _ if ends_input(code, modifiers) => {
+                        write!(stdout, "\r\n")?;
+                        return Ok(None);
+                    }
+                    KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        for _ in line.drain(..) {
+                            write!(stdout, "\u{8} \u{8}")?;
+                        }
+                    }
+                    KeyCode::Char(_) if is_chord(modifiers) => {}
+                    KeyCode::Esc => {
+                        write!(stdout, "\r\n")?;
+                        return Ok(None);
+                    }
+                    KeyCode::Enter => {
+                        write!(stdout, "\r\n")?;
+                        return Ok(Some(line));
+                    }
+                    KeyCode::Backspace if line.pop().is_some() => {
+                        write!(stdout, "\u{8} \u{8}")?;
+                    }
+                    KeyCode::Char(c) => {
+                        line.push(c);
+                        write!(stdout, "{}", c)?;
+                    }
+                    _ => {}
+                }
+                stdout.flush()?;
+            }
+        }
+    }
+
+    pub fn next_key(&mut self) -> Result<Key> {
+        self.next_key_resizing(|_| Ok(()))
+    }
+
+    /// [`Input::next_key`], calling `resized` with the new width whenever
+    /// the terminal is resized while it waits, to redraw what is waiting.
+    pub fn next_key_resizing(
+        &mut self,
+        mut resized: impl FnMut(usize) -> Result<()>,
+    ) -> Result<Key> {
+        if self.unattended {
+            return Ok(Key::Interrupt);
+        }
+        if self.lines {
+            // Blank lines are skipped rather than taken as a key.
+            while let Some(line) = self.stdin_line()? {
+                if let Some(c) = line.trim().chars().next() {
+                    return Ok(Key::Char(c));
+                }
+            }
+            return Ok(Key::Interrupt);
+        }
+        loop {
+            if let Some(key) = self.wait_key(Duration::from_secs(60), &mut resized)? {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// Throws away the keys pressed ahead, so a held-down key repeating
+    /// can't answer what is asked next. `true` if Esc or an interrupt was
+    /// among them, or a signal came in.
+    pub fn drain(&mut self) -> Result<bool> {
+        let mut cancelled = self.signaled.load(Ordering::Relaxed);
+        if !self.reads_keys() {
+            return Ok(cancelled);
+        }
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+                cancelled |= code == KeyCode::Esc || ends_input(code, modifiers);
+            }
+        }
+        Ok(cancelled)
+    }
+
+    /// Waits up to `timeout` for a key, `None` if none was pressed.
+    pub fn key_within(&mut self, timeout: Duration) -> Result<Option<Key>> {
+        self.wait_key(timeout, &mut |_| Ok(()))
+    }
+
+    fn wait_key(
+        &mut self,
+        timeout: Duration,
+        resized: &mut dyn FnMut(usize) -> Result<()>,
+    ) -> Result<Option<Key>> {
+        if !self.reads_keys() {
+            return Ok(None);
+        }
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            if self.signaled.load(Ordering::Relaxed) {
+                return Ok(Some(Key::Interrupt));
+            }
+
+            if !event::poll(
+                Duration::from_millis(100).min(deadline.saturating_duration_since(Instant::now())),
+            )? {
+                continue;
+            }
+
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers }) => match code {
+                    _ if ends_input(code, modifiers) => return Ok(Some(Key::Interrupt)),
+                    KeyCode::Char(_) if is_chord(modifiers) => {}
+                    KeyCode::Char(c) => return Ok(Some(Key::Char(c))),
+                    _ => {}
+                },
+                Event::Resize(columns, _) => resized(columns.into())?,
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Ctrl-C, Ctrl-D or Ctrl-Z.
+fn ends_input(code: KeyCode, modifiers: KeyModifiers) -> bool {
+    matches!(code, KeyCode::Char('c' | 'd' | 'z')) && is_chord(modifiers)
+}
+
+/// Whether Ctrl is held for a chord. Windows reports AltGr as Ctrl and Alt
+/// together, and what it types is a character like any other.
+fn is_chord(modifiers: KeyModifiers) -> bool {
+    modifiers.contains(KeyModifiers::CONTROL) && !modifiers.contains(KeyModifiers::ALT)
+}
@@ -0,0 +1,276 @@
+//! Which key does what at the branch prompt.
+//!
+//! Keys come from a profile, `default` or `vim`, picked with `--keys` or
+//! `delete-branches.keys`, and can be remapped one action at a time:
+//!
+//! ```ini
+//! [delete-branches]
+//!     keys = vim
+//! [delete-branches "keys"]
+//!     delete = x
+//!     deleteWithUpstream = X
+//! ```
+//!
+//! A remapped action loses the keys the profile gave it. `?` always shows
+//! the help, which lists the keys as they are bound and says which actions
+//! can't do anything for the branch at hand.
+
+use crate::config::Config;
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BranchAction {
+    Keep,
+    KeepForever,
+    Publish,
+    Rename,
+    Checkout,
+    Rebase,
+    FormatPatch,
+    Delete,
+    DeleteWithUpstream,
+    DeleteRest,
+    KeepRest,
+    Review,
+    Search,
+    Snooze,
+    Explain,
+    Undo,
+    Quit,
+}
+
+impl BranchAction {
+    /// In the order they are listed in the prompt and the help.
+    pub const ALL: [BranchAction; 17] = [
+        BranchAction::Keep,
+        BranchAction::KeepForever,
+        BranchAction::Snooze,
+        BranchAction::Publish,
+        BranchAction::Rename,
+        BranchAction::Checkout,
+        BranchAction::Rebase,
+        BranchAction::FormatPatch,
+        BranchAction::Delete,
+        BranchAction::DeleteWithUpstream,
+        BranchAction::DeleteRest,
+        BranchAction::KeepRest,
+        BranchAction::Review,
+        BranchAction::Search,
+        BranchAction::Quit,
+        BranchAction::Undo,
+        BranchAction::Explain,
+    ];
+
+    /// The name used in `[delete-branches "keys"]`, lowercase since git
+    /// config variable names are case-insensitive.
+    pub fn config_name(self) -> &'static str {
+        match self {
+            BranchAction::Keep => "keep",
+            BranchAction::KeepForever => "keepforever",
+            BranchAction::Publish => "publish",
+            BranchAction::Rename => "rename",
+            BranchAction::Checkout => "checkout",
+            BranchAction::Rebase => "rebase",
+            BranchAction::FormatPatch => "formatpatch",
+            BranchAction::Delete => "delete",
+            BranchAction::DeleteWithUpstream => "deletewithupstream",
+            BranchAction::DeleteRest => "deleterest",
+            BranchAction::KeepRest => "keeprest",
+            BranchAction::Review => "review",
+            BranchAction::Search => "search",
+            BranchAction::Snooze => "snooze",
+            BranchAction::Explain => "explain",
+            BranchAction::Undo => "undo",
+            BranchAction::Quit => "quit",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            BranchAction::Keep => "Keep the branch",
+            BranchAction::KeepForever => {
+                "Keep the branch and never offer it again (sets branch.<name>.keep)"
+            }
+            BranchAction::Publish => "Keep the branch, pushing it and setting its upstream",
+            BranchAction::Rename => "Rename the branch",
+            BranchAction::Checkout => "Check out the branch and keep it",
+            BranchAction::Rebase => {
+                "Keep the branch, bringing it up to date with the base branch (fast-forward or git rebase)"
+            }
+            BranchAction::FormatPatch => {
+                "Export the branch's own commits as patches (like git format-patch), then delete it"
+            }
+            BranchAction::Delete => "Delete the branch",
+            BranchAction::DeleteWithUpstream => {
+                "Delete the branch and its upstream (or push destination) on the remote"
+            }
+            BranchAction::DeleteRest => "Delete this and every remaining branch, after confirming",
+            BranchAction::KeepRest => "Keep this and every remaining branch, and finish",
+            BranchAction::Review => {
+                "Skip to the review of the branches marked so far, leaving the rest for another time"
+            }
+            BranchAction::Search => {
+                "Jump to the next branch matching a substring or glob, keeping the ones before it"
+            }
+            BranchAction::Snooze => "Leave the branch for another time",
+            BranchAction::Explain => "Explain how stale the branch looks",
+            BranchAction::Undo => "Undo last deleted branch, again for the one before",
+            BranchAction::Quit => "Quit (also Ctrl-C, Ctrl-D or Ctrl-Z)",
+        }
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(char, BranchAction)>,
+}
+
+impl Keymap {
+    /// The keys of `profile` (or the configured one) with the configured
+    /// remappings applied.
+    pub fn load(config: &Config, profile: Option<&str>) -> Result<Keymap> {
+        let profile = profile
+            .map(String::from)
+            .or_else(|| config.string("delete-branches.keys"));
+        let mut keymap = match profile.as_deref() {
+            None | Some("default") => Keymap::default(),
+            Some("vim") => Keymap::vim(),
+            Some(other) => {
+                return Err(Error::InvalidValue("--keys".to_string(), other.to_string()))
+            }
+        };
+
+        for action in BranchAction::ALL {
+            let key = format!("delete-branches.keys.{}", action.config_name());
+            if let Some(keys) = config.string(&key) {
+                keymap.bindings.retain(|(_, bound)| *bound != action);
+                for c in keys.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+                    keymap.bindings.push((c, action));
+                }
+            }
+        }
+
+        keymap.check()?;
+        Ok(keymap)
+    }
+
+    fn vim() -> Keymap {
+        Keymap {
+            bindings: vec![
+                ('j', BranchAction::Keep),
+                ('n', BranchAction::Keep),
+                ('!', BranchAction::KeepForever),
+                ('s', BranchAction::Snooze),
+                ('P', BranchAction::Publish),
+                ('R', BranchAction::Rename),
+                ('o', BranchAction::Checkout),
+                ('b', BranchAction::Rebase),
+                ('f', BranchAction::FormatPatch),
+                ('x', BranchAction::Delete),
+                ('X', BranchAction::DeleteWithUpstream),
+                ('a', BranchAction::DeleteRest),
+                ('K', BranchAction::KeepRest),
+                ('=', BranchAction::Review),
+                ('/', BranchAction::Search),
+                ('q', BranchAction::Quit),
+                ('u', BranchAction::Undo),
+                ('e', BranchAction::Explain),
+            ],
+        }
+    }
+
+    /// Refuses keys bound to two actions, and rebinding `?`.
+    fn check(&self) -> Result<()> {
+        for (i, (c, action)) in self.bindings.iter().enumerate() {
+            let clash = self.bindings[..i]
+                .iter()
+                .any(|(other, bound)| other == c && bound != action);
+            if clash || *c == '?' {
+                return Err(Error::InvalidValue(
+                    format!("delete-branches.keys.{}", action.config_name()),
+                    c.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// What `c` is bound to, if anything.
+    pub fn action(&self, c: char) -> Option<BranchAction> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == c)
+            .map(|(_, action)| *action)
+    }
+
+    /// The first key bound to `action`, if any.
+    pub fn key(&self, action: BranchAction) -> Option<char> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(c, _)| *c)
+    }
+
+    fn keys(&self, action: BranchAction) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(c, _)| c.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// `k/!/s/p/r/c/b/f/d/D/a/K/=///q/u/e/?`, showing the first key of every bound action.
+    pub fn prompt(&self) -> String {
+        let mut keys: Vec<String> = BranchAction::ALL
+            .iter()
+            .filter_map(|&action| self.key(action).map(String::from))
+            .collect();
+        keys.push("?".to_string());
+        keys.join("/")
+    }
+
+    /// A line for every bound action, saying why if it is one of the
+    /// `unavailable` ones.
+    pub fn help(&self, unavailable: &[(BranchAction, String)]) -> Vec<String> {
+        let mut lines: Vec<String> = BranchAction::ALL
+            .iter()
+            .map(|&action| (self.keys(action), action))
+            .filter(|(keys, _)| !keys.is_empty())
+            .map(|(keys, action)| {
+                let line = format!("{} - {}", keys, action.description());
+                match unavailable.iter().find(|(bound, _)| *bound == action) {
+                    Some((_, why)) => format!("{} (unavailable: {})", line, why),
+                    None => line,
+                }
+            })
+            .collect();
+        lines.push("? - Show this help text".to_string());
+        lines
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: vec![
+                ('k', BranchAction::Keep),
+                ('!', BranchAction::KeepForever),
+                ('s', BranchAction::Snooze),
+                ('p', BranchAction::Publish),
+                ('r', BranchAction::Rename),
+                ('c', BranchAction::Checkout),
+                ('b', BranchAction::Rebase),
+                ('f', BranchAction::FormatPatch),
+                ('d', BranchAction::Delete),
+                ('D', BranchAction::DeleteWithUpstream),
+                ('a', BranchAction::DeleteRest),
+                ('K', BranchAction::KeepRest),
+                ('=', BranchAction::Review),
+                ('/', BranchAction::Search),
+                ('q', BranchAction::Quit),
+                ('u', BranchAction::Undo),
+                ('e', BranchAction::Explain),
+            ],
+        }
+    }
+}
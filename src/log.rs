@@ -0,0 +1,92 @@
+//! `--verbose` (`-v`) and `-vv`: what the tool does with the repository and
+//! how long each step takes, on stderr or in `--log-file`. For working out
+//! why a branch was classified the way it was, or what a remote made of a
+//! push.
+//!
+//! `-v` logs a line per branch on how it was classified, and one per
+//! deletion, push, prune and forge request. `-vv` adds the lookups behind
+//! those: commits, refs, upstream config, fork points, merge and squash
+//! checks. Each line starts with the seconds since the run started:
+//!
+//! ```text
+//! [   0.042s] feature/login: merged, fork point 1a2b3c4, 0 ahead 12 behind, upstream origin/feature/login (gone) (1.3ms)
+//! ```
+//!
+//! Nothing is logged without either flag, and nothing is worked out for it.
+
+use crate::Result;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    /// `-v`: classifications and anything that changes a repository or asks
+    /// a server.
+    Verbose = 1,
+    /// `-vv`: every lookup along the way.
+    Trace = 2,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Logs at `verbosity`, the number of `-v`s, to the end of `path` or else
+/// to stderr.
+pub fn init(verbosity: u8, path: Option<&Path>) -> Result<()> {
+    START.get_or_init(Instant::now);
+    LEVEL.store(verbosity, Ordering::Relaxed);
+    if let Some(path) = path {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *FILE.lock().unwrap() = Some(file);
+    }
+    Ok(())
+}
+
+pub fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+pub fn line(level: Level, message: impl Display) {
+    if !enabled(level) {
+        return;
+    }
+    let elapsed = START.get_or_init(Instant::now).elapsed().as_secs_f64();
+    match &mut *FILE.lock().unwrap() {
+        Some(file) => {
+            let _ = writeln!(file, "[{:8.3}s] {}", elapsed, message);
+        }
+        // A terminal in raw mode needs the carriage return.
+        None => eprint!("[{:8.3}s] {}\r\n", elapsed, message),
+    }
+}
+
+/// Runs `op`, then logs `what` it did with how long it took, and the error
+/// if it failed.
+pub fn timed<T, E: Display>(
+    level: Level,
+    what: impl FnOnce() -> String,
+    op: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<T, E> {
+    if !enabled(level) {
+        return op();
+    }
+    let start = Instant::now();
+    let result = op();
+    let took = took(start.elapsed());
+    match &result {
+        Ok(_) => line(level, format!("{} ({})", what(), took)),
+        Err(e) => line(level, format!("{} failed after {}: {}", what(), took, e)),
+    }
+    result
+}
+
+/// `elapsed` in milliseconds, the way log lines show it.
+pub fn took(elapsed: Duration) -> String {
+    format!("{:.1}ms", elapsed.as_secs_f64() * 1000.0)
+}
@@ -1,35 +1,222 @@
-use chrono::{prelude::*, Duration};
-use git2::{BranchType, Repository};
-use std::io::{self, Bytes, Read, Stdin, Write};
+mod anonymize;
+mod archive;
+mod audit;
+mod backend;
+mod backup;
+mod branch;
+mod branch_config;
+mod bulk;
+mod cli;
+mod companion;
+mod config;
+mod danger;
+mod dashboard;
+mod encoding;
+mod events;
+mod explain_config;
+mod filter;
+mod fixture;
+mod forge;
+mod forge_cache;
+mod gc;
+mod glob;
+mod graph;
+mod group;
+mod head;
+mod hooks;
+mod init;
+mod input;
+mod json;
+mod keys;
+mod log;
+mod merge;
+mod output;
+mod owners;
+mod patches;
+mod plan;
+mod prefetch;
+mod progress;
+mod protection;
+mod rebase;
+mod recurse;
+mod references;
+mod remote;
+mod report;
+mod restore;
+mod retention;
+mod review;
+mod run;
+mod score;
+mod script;
+mod seatbelt;
+mod session;
+mod size;
+mod state;
+mod sync;
+mod theme;
+mod tls;
+mod tombstone;
+mod trash;
+mod upload;
+mod wip;
+mod worktree;
+
+use backend::{Backend, GitBackend};
+use cli::{Cli, Command};
+use config::Config;
+use fixture::Fixture;
+use forge_cache::ForgeCache;
+use input::Input;
+use prefetch::{Extras, Prefetch};
+use progress::Progress;
+use session::{Flow, Session};
+use std::io::Write;
+
+/// How a run that didn't fail ended, as the exit status.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Done = 0,
+    /// There were no branches, or none matching the filters.
+    NothingMatched = 2,
+    /// Some deletions didn't go through.
+    DeletionsFailed = 3,
+}
 
 fn main() {
+    let mut status = Status::Done;
     let result = (|| -> Result<_> {
-        let repo = Repository::open_from_env()?;
+        let cli = Cli::parse()?;
+        log::init(cli.verbose, cli.log_file.as_deref())?;
 
-        crossterm::terminal::enable_raw_mode()?;
+        match cli.command {
+            Some(Command::Help) => {
+                print!("{}", cli::help());
+                return Ok(());
+            }
+            Some(Command::Completions(shell)) => {
+                print!("{}", cli::completions(shell));
+                return Ok(());
+            }
+            // Plans are plain files, no repository needed.
+            Some(Command::PlanDiff) => return plan::diff(&cli),
+            _ => {}
+        }
 
-        let mut stdout = io::stdout();
-        let mut stdin = io::stdin().bytes();
+        if cli.script.is_some() && cli.mark {
+            return Err(Error::InvalidValue(
+                "--script".to_string(),
+                "can't be combined with --mark".to_string(),
+            ));
+        }
+        if cli.immediate && cli.mark {
+            return Err(Error::InvalidValue(
+                "--immediate".to_string(),
+                "can't be combined with --mark".to_string(),
+            ));
+        }
 
-        let branches = get_branches(&repo)?;
+        // Subcommands still work on the current repository.
+        if !cli.recurse.is_empty() && cli.command.is_none() {
+            if cli.fixture.is_some() || cli.record.is_some() {
+                return Err(Error::InvalidValue(
+                    "--recurse".to_string(),
+                    "can't be combined with --fixture or --record".to_string(),
+                ));
+            }
+            if filter::reads_stdin(&cli) {
+                return Err(Error::InvalidValue(
+                    "--recurse".to_string(),
+                    "can't be combined with --stdin, stdin is read only once".to_string(),
+                ));
+            }
+            let mut input = Input::new()?;
+            if cli.script.is_some() {
+                input.set_unattended();
+            }
+            let _raw_mode = input.raw_mode()?;
+            status = recurse::run(cli, input)?;
+            return Ok(());
+        }
 
-        if branches.is_empty() {
-            write!(stdout, "No branches found (master ignored).\r\n")?;
-        } else {
-            let mut deleted_branch: Option<Branch> = None;
+        let mut backend: Box<dyn Backend> = match &cli.fixture {
+            Some(path) => Box::new(Fixture::load(path)?),
+            None => Box::new(GitBackend::open_from_env()?),
+        };
 
-            for branch in branches {
-                act_on_branch(branch, &mut stdout, &mut stdin, &mut deleted_branch, &repo)?;
+        let config = Config::load(backend.repo())?;
+
+        if let (Some(remote), Some(repo)) = (&cli.backup_remote, backend.repo()) {
+            if repo.find_remote(remote).is_err() {
+                return Err(Error::InvalidValue(
+                    "--backup-remote".to_string(),
+                    format!("there is no remote named '{}'", remote),
+                ));
             }
         }
 
+        match cli.command {
+            Some(Command::Sync) => return sync::run(backend.as_ref(), &config, cli.check_ci),
+            Some(Command::Report) => return report::run(backend.as_ref(), &cli),
+            Some(Command::Graph) => return graph::run(backend.as_ref(), &cli),
+            Some(Command::Restore) => return restore::run(backend.as_ref(), &config, &cli),
+            Some(Command::Gc) => return gc::run(backend.as_ref(), &cli),
+            _ => {}
+        }
+
+        let mut input = Input::new()?;
+        if filter::reads_stdin(&cli) {
+            input.set_stdin_taken();
+        }
+
+        let mut session = Session::new(backend.as_mut(), config, cli, input)?;
+        if session.cli.command == Some(Command::ExplainConfig) {
+            let (found, branches) = candidates(&mut session)?;
+            return session.explain_config(found, branches);
+        }
+        let _raw_mode = session.input.raw_mode()?;
+        if session.cli.command == Some(Command::Init) {
+            return session.init();
+        }
+        session.announce_skipped_hooks()?;
+        session.announce_seatbelt()?;
+        session.announce_head()?;
+
+        if session.cli.clean_archive {
+            return session.clean_archive();
+        }
+
+        let flow = triage(&mut session)?;
+        session.events.session_end(&session.summary);
+        if let Some(recording) = &session.recording {
+            recording.save()?;
+        }
+        if flow.is_some() {
+            session.summary.print(&mut session.stdout)?;
+        }
+
+        status = if flow.is_none() {
+            Status::NothingMatched
+        } else if !session.summary.failures().is_empty() {
+            Status::DeletionsFailed
+        } else {
+            Status::Done
+        };
+        if let Some(url) = &session.cli.upload_report {
+            let repository = session
+                .backend
+                .repo()
+                .map(|repo| repo.path().display().to_string())
+                .unwrap_or_default();
+            let report = upload::report(&session.summary, vec![repository], status);
+            if let Err(e) = upload::upload(url, &session.config, &report) {
+                eprint!("{}\r\n", e);
+            }
+        }
         Ok(())
     })();
 
-    crossterm::terminal::disable_raw_mode().ok();
-
     match result {
-        Ok(_) => {}
+        Ok(_) => std::process::exit(status as i32),
         Err(e) => {
             eprintln!("{}", e);
             std::process::exit(1);
@@ -37,174 +224,170 @@ fn main() {
     }
 }
 
-fn act_on_branch<'a>(
-    mut branch: Branch<'a>,
-    stdout: &mut std::io::Stdout,
-    stdin: &mut Bytes<Stdin>,
-    deleted_branch: &mut Option<Branch<'a>>,
-    repo: &Repository,
-) -> Result<()> {
-    if branch.is_head {
+/// Runs the session over the repository's branches, from listing them to
+/// pruning remotes. `None` if there turned out to be nothing to ask about.
+fn triage(session: &mut Session) -> Result<Option<Flow>> {
+    let (found, mut branches) = candidates(session)?;
+
+    let mut extras = Extras {
+        wip: session.cli.rebase_safe,
+        sizes: session.cli.sizes,
+        forge: None,
+    };
+    if session.cli.check_prs || session.cli.check_ci {
+        let repo = session.backend.repo().ok_or(Error::NeedsRepository)?;
+        let checked = forge::Forge::origin(repo, &session.config).and_then(|mut forge| {
+            forge.check_ci = session.cli.check_ci;
+            Ok((ForgeCache::load(repo, &forge.project)?, forge))
+        });
+        match checked {
+            Ok((cache, forge)) => extras.forge = Some((forge, cache)),
+            Err(e) => write!(session.stdout, "Could not check pull requests: {}\r\n", e)?,
+        }
+    } else if let Some(repo) = session.backend.repo() {
+        // Whatever `sync` or an earlier `--check-prs` left behind is
+        // shown for free.
+        let project = forge::origin_url(repo)
+            .ok()
+            .and_then(|url| forge::parse_remote_url(&url));
+        if let Some((_, project)) = project {
+            ForgeCache::load(repo, &project)?.annotate(&mut branches);
+        }
+    }
+
+    if branches.is_empty() && found > 0 {
         write!(
-            stdout,
-            "Ignoring '{}' because it is the current branch\r\n",
-            branch.name
+            session.stdout,
+            "None of the {} branch(es) match the filters.\r\n",
+            found
         )?;
-    } else {
-        match get_branch_action_from_user(stdout, stdin, &branch)? {
-            BranchAction::Quit => {
-                write!(stdout, "Quitting...\r\n")?;
-                return Ok(());
-            }
-            BranchAction::Keep => {
-                write!(stdout, "")?;
-            }
-            BranchAction::Delete => {
-                branch.delete()?;
-
-                write!(
-                    stdout,
-                    "Deleted branch '{}', to undo select 'u'\r\n",
-                    branch.name
-                )?;
-                *deleted_branch = Some(branch);
-            }
-            BranchAction::Undo => {
-                if let Some(branch) = &deleted_branch {
-                    write!(stdout, "Undoing deletion of branch '{}'\r\n", branch.name)?;
+        return Ok(None);
+    } else if branches.is_empty() {
+        write!(
+            session.stdout,
+            "No branches found ({} ignored).\r\n",
+            backend::BASE_BRANCH
+        )?;
+        return Ok(None);
+    }
 
-                    let commit = repo.find_commit(branch.id)?;
+    if extras.any() {
+        let repo = session.backend.repo().ok_or(Error::NeedsRepository)?;
+        session.prefetch = Some(Prefetch::start(repo, extras, branches.clone()));
+    }
 
-                    repo.branch(&branch.name, &commit, false)?;
-                } else {
-                    write!(stdout, "No branch to undo deletion of\r\n")?;
-                }
-                *deleted_branch = None;
+    let branches = session.resolve_protections(branches)?;
+    let branches = session.resolve_case_clashes(branches)?;
 
-                act_on_branch(branch, stdout, stdin, deleted_branch, repo)?;
-            }
+    let batches = if session.cli.dashboard && session.script.is_none() {
+        match session.dashboard(branches)? {
+            Some(batches) => batches,
+            None => return Ok(Some(Flow::Quit)),
         }
+    } else {
+        vec![branches]
+    };
+
+    let mut flow = Flow::Continue;
+    for batch in batches {
+        // A script decides branch by branch, so there is nothing to group.
+        flow = if session.cli.group_by_prefix && session.script.is_none() {
+            session.run_grouped(batch)?
+        } else {
+            session.run(batch)?
+        };
+        if let Flow::Quit | Flow::Review = flow {
+            break;
+        }
+    }
+
+    session.finish_prefetch()?;
+    if session.marks.is_some() {
+        session.review()?;
     }
 
-    Ok(())
+    session.finish_deletions()?;
+    session.prune_remotes(session.cli.prune)?;
+    Ok(Some(flow))
 }
 
-fn get_branch_action_from_user(
-    stdout: &mut std::io::Stdout,
-    stdin: &mut Bytes<Stdin>,
-    branch: &Branch,
-) -> Result<BranchAction> {
-    write!(
-        stdout,
-        "'{}' ({}) last commit at {} (k/d/q/u/?) > ",
-        branch.name,
-        &branch.id.to_string()[..7],
-        branch.time
+/// The repository's branches that pass the filters, with where they are
+/// checked out, and how many there were before filtering.
+fn candidates(session: &mut Session) -> Result<(usize, Vec<branch::Branch>)> {
+    let listed = filter::listed(&session.cli)?;
+    if let Some(names) = &listed {
+        session.warn_unknown(names)?;
+    }
+    let progress = if session.cli.quiet {
+        Progress::hidden()
+    } else {
+        Progress::new()
+    };
+    let case = session.case;
+    let branches = session.backend.scan(
+        &|name| filter::wanted(&session.cli, case, listed.as_ref(), name),
+        &|done, total| progress.report(done, total),
     )?;
-    stdout.flush()?;
+    progress.finish()?;
+    let found = branches.len();
+    session.retention.rank(&branches);
+    let mut branches = filter::apply(&*session.backend, &session.cli, branches)?;
+    if session.cli.expired {
+        branches.retain(|branch| session.retention.expired(branch) == Some(true));
+    }
 
-    let byte = match stdin.next() {
-        Some(byte) => byte?,
-        None => return get_branch_action_from_user(stdout, stdin, branch),
+    let worktrees = match session.backend.repo() {
+        Some(repo) if session.cli.all_worktrees => worktree::get_worktrees(repo)?,
+        _ => Vec::new(),
     };
 
-    let c = char::from(byte);
-    write!(stdout, "{}\r\n", c)?;
-
-    if c == '?' {
-        write!(stdout, "Here are what the commands mean\r\n")?;
-        write!(stdout, "k - Keep the branch\r\n")?;
-        write!(stdout, "d - Delete the branch\r\n")?;
-        write!(stdout, "u - Undo last deleted branch\r\n")?;
-        write!(stdout, "q - Quit\r\n")?;
-        write!(stdout, "? - Show this help text\r\n")?;
-        stdout.flush()?;
-        get_branch_action_from_user(stdout, stdin, branch)
-    } else {
-        BranchAction::try_from(c)
+    for branch in &mut branches {
+        branch.worktree = worktrees
+            .iter()
+            .find(|worktree| worktree.branch.as_ref() == Some(&branch.name))
+            .map(|worktree| worktree.path.clone());
     }
-}
 
-fn get_branches(repo: &Repository) -> Result<Vec<Branch>> {
-    let mut branches = repo
-        .branches(Some(BranchType::Local))?
-        .map(|branch| {
-            let (branch, _) = branch?;
-            let branch_name = branch.name_bytes()?;
-
-            let commit = branch.get().peel_to_commit()?;
-
-            let time = commit.time();
-            let offset = Duration::minutes(i64::from(time.offset_minutes()));
-            let time = NaiveDateTime::from_timestamp(time.seconds(), 0) + offset;
-
-            Ok(Branch {
-                time,
-                id: commit.id(),
-                name: String::from_utf8(branch_name.to_vec())?,
-                is_head: branch.is_head(),
-                branch,
-            })
-        })
-        .filter(|branch| {
-            let name = &branch.as_ref().unwrap().name;
-            name != "master"
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    branches.sort_unstable_by_key(|branch| branch.time);
-
-    Ok(branches)
+    session.summary.worktrees = worktrees;
+    Ok((found, branches))
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-struct Branch<'repo> {
-    time: NaiveDateTime,
-    id: git2::Oid,
-    name: String,
-    is_head: bool,
-    branch: git2::Branch<'repo>,
-}
-
-impl<'repo> Branch<'repo> {
-    fn delete(&mut self) -> Result<()> {
-        self.branch.delete().map_err(From::from)
-    }
-}
-
-enum BranchAction {
-    Quit,
-    Delete,
-    Keep,
-    Undo,
-}
-
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error(transparent)]
-    CrosstermError(#[from] crossterm::ErrorKind),
+    Crossterm(#[from] crossterm::ErrorKind),
 
     #[error(transparent)]
-    GitError(#[from] git2::Error),
+    Git(#[from] git2::Error),
 
-    #[error(transparent)]
-    FromUtf8Error(#[from] std::string::FromUtf8Error),
+    #[error("Unknown argument '{0}'")]
+    UnknownArgument(String),
 
-    #[error("\n\rInvalid input, Dont know what to do with '{0}'")]
-    InvalidInput(char),
-}
+    #[error("Missing value for '{0}'")]
+    MissingValue(String),
 
-impl TryFrom<char> for BranchAction {
-    type Error = Error;
+    #[error("Invalid value for '{0}': '{1}'")]
+    InvalidValue(String, String),
 
-    fn try_from(c: char) -> Result<Self> {
-        match c {
-            'q' => Ok(BranchAction::Quit),
-            'd' => Ok(BranchAction::Delete),
-            'k' => Ok(BranchAction::Keep),
-            'u' => Ok(BranchAction::Undo),
-            _ => Err(Error::InvalidInput(c)),
-        }
-    }
+    #[error("Invalid fixture: {0}")]
+    Fixture(String),
+
+    #[error("This needs a git repository and can't be used with --fixture")]
+    NeedsRepository,
+
+    #[error("Forge: {0}")]
+    Forge(String),
+
+    #[error("Uploading the report: {0}")]
+    Upload(String),
+
+    #[error("Collecting garbage: {0}")]
+    Gc(String),
+
+    #[error(
+        "{0}, and the local changes are left in stash@{{0}} ('git stash pop' brings them back)"
+    )]
+    LeftStashed(git2::Error),
 }
@@ -0,0 +1,409 @@
+//! Whether a branch made it into the base branch, by a real merge or as a
+//! squashed commit.
+//!
+//! Squash merges leave no trace in the history, so they are spotted by
+//! comparing the patch id of the branch's whole change with those of the
+//! commits the base branch gained since the branch forked. That is slow on
+//! big repositories, so results are kept in
+//! `.git/delete-branches-cache/merge.json`: per branch keyed by its tip and
+//! the base tip, so moving either one invalidates the entry, and per base
+//! commit for patch ids, which never change.
+//!
+//! Whether a branch is merged at all, where it forked and how far ahead and
+//! behind it is come from a [`Reachability`] index of the base branch's
+//! history: one walk up front, after which each branch only walks its own
+//! commits and the base commits it lacks, instead of asking libgit2 for a
+//! merge base and ahead/behind counts, which walk the base branch's history
+//! again every time.
+
+use crate::json::Value;
+use crate::log::{self, Level};
+use crate::{state, Result};
+use git2::{Oid, Repository, Sort};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+const FILE: &str = "merge.json";
+
+/// How far back along the base branch to look for a squashed commit.
+const MAX_SQUASH_DEPTH: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeInfo {
+    /// The tip is reachable from the base branch.
+    pub merged: bool,
+    /// The branch's changes were applied to the base branch as one commit.
+    pub squash_merged: bool,
+    /// Where the branch left the base branch.
+    pub fork_point: Oid,
+    /// Commits on the branch that the base branch doesn't have.
+    pub ahead: usize,
+    /// Commits on the base branch that the branch doesn't have.
+    pub behind: usize,
+}
+
+pub struct MergeCache {
+    path: PathBuf,
+    branches: HashMap<(Oid, Oid), MergeInfo>,
+    patch_ids: HashMap<Oid, Oid>,
+    /// What was looked up this run; everything else is dropped on save.
+    used_branches: HashSet<(Oid, Oid)>,
+    used_patch_ids: HashSet<Oid>,
+}
+
+impl MergeCache {
+    /// Loads the cache, starting out empty if it is missing or unreadable.
+    pub fn load(repo: &Repository) -> Result<MergeCache> {
+        let mut cache = MergeCache {
+            path: state::cache_dir(repo).join(FILE),
+            branches: HashMap::new(),
+            patch_ids: HashMap::new(),
+            used_branches: HashSet::new(),
+            used_patch_ids: HashSet::new(),
+        };
+
+        let json = match state::read(&cache.path)?.map(|contents| Value::parse(&contents)) {
+            Some(Ok(json)) => json,
+            _ => return Ok(cache),
+        };
+
+        if let Some(Value::Object(branches)) = json.get("branches") {
+            cache.branches = branches
+                .iter()
+                .filter_map(|(key, info)| {
+                    let (tip, base) = key.split_once(':')?;
+                    let key = (Oid::from_str(tip).ok()?, Oid::from_str(base).ok()?);
+                    let info = MergeInfo {
+                        merged: info.get("merged")?.as_bool()?,
+                        squash_merged: info.get("squash_merged")?.as_bool()?,
+                        fork_point: Oid::from_str(info.get("fork_point")?.as_str()?).ok()?,
+                        ahead: info.get("ahead")?.as_f64()? as usize,
+                        behind: info.get("behind")?.as_f64()? as usize,
+                    };
+                    Some((key, info))
+                })
+                .collect();
+        }
+
+        if let Some(Value::Object(patch_ids)) = json.get("patch_ids") {
+            cache.patch_ids = patch_ids
+                .iter()
+                .filter_map(|(commit, patch_id)| {
+                    Some((
+                        Oid::from_str(commit).ok()?,
+                        Oid::from_str(patch_id.as_str()?).ok()?,
+                    ))
+                })
+                .collect();
+        }
+
+        Ok(cache)
+    }
+
+    /// Writes out the entries looked up since loading.
+    pub fn save(&self) -> Result<()> {
+        let mut branches: Vec<_> = self
+            .branches
+            .iter()
+            .filter(|(key, _)| self.used_branches.contains(key))
+            .map(|((tip, base), info)| {
+                let info = Value::Object(vec![
+                    ("merged".to_string(), info.merged.into()),
+                    ("squash_merged".to_string(), info.squash_merged.into()),
+                    ("fork_point".to_string(), info.fork_point.to_string().into()),
+                    ("ahead".to_string(), info.ahead.into()),
+                    ("behind".to_string(), info.behind.into()),
+                ]);
+                (format!("{}:{}", tip, base), info)
+            })
+            .collect();
+        branches.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let mut patch_ids: Vec<_> = self
+            .patch_ids
+            .iter()
+            .filter(|(commit, _)| self.used_patch_ids.contains(commit))
+            .map(|(commit, patch_id)| (commit.to_string(), patch_id.to_string().into()))
+            .collect();
+        patch_ids.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let json = Value::Object(vec![
+            ("branches".to_string(), Value::Object(branches)),
+            ("patch_ids".to_string(), Value::Object(patch_ids)),
+        ]);
+        state::write(&self.path, &json.to_string())
+    }
+
+    fn branch(&mut self, tip: Oid, base: Oid) -> Option<MergeInfo> {
+        let info = *self.branches.get(&(tip, base))?;
+        self.used_branches.insert((tip, base));
+        Some(info)
+    }
+
+    fn patch_id(&mut self, commit: Oid) -> Option<Oid> {
+        let patch_id = *self.patch_ids.get(&commit)?;
+        self.used_patch_ids.insert(commit);
+        Some(patch_id)
+    }
+}
+
+/// Every commit the base branch has, with its generation: one more than
+/// the highest of its parents', so a commit's ancestors all have lower
+/// generations than it does. Built the first time a branch isn't in the
+/// cache, by whichever thread gets there first.
+pub struct Reachability {
+    base: Oid,
+    generations: OnceLock<HashMap<Oid, u32>>,
+    building: Mutex<()>,
+}
+
+impl Reachability {
+    pub fn new(base: Oid) -> Reachability {
+        Reachability {
+            base,
+            generations: OnceLock::new(),
+            building: Mutex::new(()),
+        }
+    }
+
+    fn generations(&self, repo: &Repository) -> Result<&HashMap<Oid, u32>> {
+        if let Some(generations) = self.generations.get() {
+            return Ok(generations);
+        }
+        let _building = self.building.lock().unwrap();
+        if let Some(generations) = self.generations.get() {
+            return Ok(generations);
+        }
+
+        let generations = log::timed(
+            Level::Trace,
+            || format!("index the history of {:.7}", self.base),
+            || -> Result<HashMap<Oid, u32>> {
+                let mut walk = repo.revwalk()?;
+                walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+                walk.push(self.base)?;
+
+                // Parents come out before their children.
+                let mut generations = HashMap::new();
+                for oid in walk {
+                    let oid = oid?;
+                    let generation = repo
+                        .find_commit(oid)?
+                        .parent_ids()
+                        .filter_map(|parent| generations.get(&parent))
+                        .max()
+                        .map_or(1, |generation| generation + 1);
+                    generations.insert(oid, generation);
+                }
+                Ok(generations)
+            },
+        )?;
+
+        Ok(self.generations.get_or_init(|| generations))
+    }
+
+    fn contains(&self, repo: &Repository, commit: Oid) -> Result<bool> {
+        Ok(self.generations(repo)?.contains_key(&commit))
+    }
+
+    /// Where `tip` left the base branch. Only `tip`'s own commits are
+    /// walked, which is also how many it is ahead by; of the base commits
+    /// it reaches, the newest is the fork point. A branch with no history
+    /// in common forks at its tip.
+    fn fork(&self, repo: &Repository, tip: Oid) -> Result<Fork> {
+        let generations = self.generations(repo)?;
+        let mut fork = Fork {
+            point: tip,
+            ahead: 0,
+            reached: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        let mut pending = vec![tip];
+        while let Some(commit) = pending.pop() {
+            if !seen.insert(commit) {
+                continue;
+            }
+            if generations.contains_key(&commit) {
+                fork.reached.push(commit);
+                continue;
+            }
+            fork.ahead += 1;
+            pending.extend(repo.find_commit(commit)?.parent_ids());
+        }
+
+        if let Some(&point) = fork.reached.iter().max_by_key(|commit| generations[commit]) {
+            fork.point = point;
+        }
+        Ok(fork)
+    }
+
+    /// How many base commits a branch that reaches the base branch at
+    /// `reached` lacks. The base branch is walked down from its tip newest
+    /// generation first, so each commit is counted only once all of its
+    /// children have passed on whether the branch has it, and the walk ends
+    /// as soon as everything left in it is the branch's.
+    fn behind(&self, repo: &Repository, reached: &[Oid]) -> Result<usize> {
+        let generations = self.generations(repo)?;
+        if reached.is_empty() {
+            return Ok(generations.len());
+        }
+
+        // Whether the branch has each commit walked so far.
+        let mut has: HashMap<Oid, bool> = reached.iter().map(|&commit| (commit, true)).collect();
+        has.entry(self.base).or_insert(false);
+        let mut pending: BinaryHeap<(u32, Oid)> = has
+            .keys()
+            .map(|commit| (generations[commit], *commit))
+            .collect();
+        let mut lacking = has.values().filter(|&&has| !has).count();
+
+        let mut behind = 0;
+        while lacking > 0 {
+            let commit = match pending.pop() {
+                Some((_, commit)) => commit,
+                None => break,
+            };
+            let branch_has = has[&commit];
+            if !branch_has {
+                behind += 1;
+                lacking -= 1;
+            }
+            for parent in repo.find_commit(commit)?.parent_ids() {
+                match has.get_mut(&parent) {
+                    Some(parent_has) => {
+                        if branch_has && !*parent_has {
+                            *parent_has = true;
+                            lacking -= 1;
+                        }
+                    }
+                    None => {
+                        has.insert(parent, branch_has);
+                        pending.push((generations[&parent], parent));
+                        if !branch_has {
+                            lacking += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(behind)
+    }
+}
+
+/// What walking a branch's own commits found.
+struct Fork {
+    /// Where the branch left the base branch.
+    point: Oid,
+    /// The commits walked, those the base branch doesn't have.
+    ahead: usize,
+    /// The base commits the walk stopped at.
+    reached: Vec<Oid>,
+}
+
+/// Works out how `tip` relates to the base branch `reachability` indexes,
+/// consulting and filling `cache`.
+///
+/// The cache sits behind a mutex so that the threads collecting branch
+/// metadata can share it; it is only locked for lookups and insertions.
+pub fn merge_info(
+    repo: &Repository,
+    cache: &Mutex<MergeCache>,
+    reachability: &Reachability,
+    tip: Oid,
+) -> Result<MergeInfo> {
+    let base = reachability.base;
+    if let Some(info) = cache.lock().unwrap().branch(tip, base) {
+        log::line(Level::Trace, format!("merge info of {:.7} cached", tip));
+        return Ok(info);
+    }
+
+    let merged = reachability.contains(repo, tip)?;
+    let fork = log::timed(
+        Level::Trace,
+        || format!("fork point of {:.7}", tip),
+        || reachability.fork(repo, tip),
+    )?;
+    let fork_point = fork.point;
+    let squash_merged = !merged
+        && log::timed(
+            Level::Trace,
+            || format!("squash check of {:.7} since {:.7}", tip, fork_point),
+            || is_squash_merged(repo, cache, tip, base, fork_point),
+        )?;
+    let ahead = fork.ahead;
+    let behind = log::timed(
+        Level::Trace,
+        || format!("base commits {:.7} lacks", tip),
+        || reachability.behind(repo, &fork.reached),
+    )?;
+
+    let info = MergeInfo {
+        merged,
+        squash_merged,
+        fork_point,
+        ahead,
+        behind,
+    };
+    let mut cache = cache.lock().unwrap();
+    cache.branches.insert((tip, base), info);
+    cache.used_branches.insert((tip, base));
+    Ok(info)
+}
+
+fn is_squash_merged(
+    repo: &Repository,
+    cache: &Mutex<MergeCache>,
+    tip: Oid,
+    base: Oid,
+    fork_point: Oid,
+) -> Result<bool> {
+    let branch_patch_id = match change_patch_id(repo, fork_point, tip)? {
+        Some(patch_id) => patch_id,
+        None => return Ok(false),
+    };
+
+    let mut commit = repo.find_commit(base)?;
+    for _ in 0..MAX_SQUASH_DEPTH {
+        if commit.id() == fork_point {
+            break;
+        }
+        let parent = match commit.parent(0) {
+            Ok(parent) => parent,
+            Err(_) => break,
+        };
+
+        let cached = cache.lock().unwrap().patch_id(commit.id());
+        let patch_id = match cached {
+            Some(patch_id) => Some(patch_id),
+            None => {
+                let patch_id = change_patch_id(repo, parent.id(), commit.id())?;
+                if let Some(patch_id) = patch_id {
+                    let mut cache = cache.lock().unwrap();
+                    cache.patch_ids.insert(commit.id(), patch_id);
+                    cache.used_patch_ids.insert(commit.id());
+                }
+                patch_id
+            }
+        };
+
+        if patch_id == Some(branch_patch_id) {
+            return Ok(true);
+        }
+        commit = parent;
+    }
+
+    Ok(false)
+}
+
+/// The patch id of everything that changed between two commits, `None` if
+/// nothing did.
+fn change_patch_id(repo: &Repository, from: Oid, to: Oid) -> Result<Option<Oid>> {
+    let from = repo.find_commit(from)?.tree()?;
+    let to = repo.find_commit(to)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&from), Some(&to), None)?;
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(diff.patchid(None)?))
+}
@@ -0,0 +1,139 @@
+//! Where a session writes to: stdout, or with `--quiet` nothing but the
+//! names of the branches it deletes, one per line, for scripts to read:
+//!
+//! ```sh
+//! git delete-branches --quiet --script cleanup.txt | xargs -n1 notify-owner
+//! ```
+//!
+//! Errors still go to stderr, and the exit status tells scripts how it went:
+//! 0 when it went through, 1 on an error, 2 when no branch matched the
+//! filters, so there was nothing to do, and 3 when some deletions didn't go
+//! through.
+
+use crossterm::tty::IsTty;
+use std::io::{self, Stdout, Write};
+use std::str::Chars;
+
+pub struct Output {
+    stdout: Stdout,
+    quiet: bool,
+}
+
+impl Output {
+    pub fn new(quiet: bool) -> Output {
+        Output {
+            stdout: io::stdout(),
+            quiet,
+        }
+    }
+
+    /// Whether the session is drawn on a terminal, rather than piped or
+    /// quiet.
+    pub fn is_tty(&self) -> bool {
+        !self.quiet && self.stdout.is_tty()
+    }
+
+    /// How many columns the terminal has, when the session is drawn on one.
+    pub fn width(&self) -> Option<usize> {
+        if !self.is_tty() {
+            return None;
+        }
+        crossterm::terminal::size()
+            .ok()
+            .map(|(columns, _)| columns.into())
+    }
+
+    /// How many rows the terminal has, when the session is drawn on one.
+    pub fn height(&self) -> Option<usize> {
+        if !self.is_tty() {
+            return None;
+        }
+        crossterm::terminal::size()
+            .ok()
+            .map(|(_, rows)| rows.into())
+    }
+
+    /// Says `name` is deleted, which is all `--quiet` prints.
+    pub fn deleted(&mut self, name: &str) -> io::Result<()> {
+        if !self.quiet {
+            return Ok(());
+        }
+        // A terminal in raw mode needs the carriage return, a pipe doesn't.
+        let newline = if self.stdout.is_tty() { "\r\n" } else { "\n" };
+        write!(self.stdout, "{}{}", name, newline)?;
+        self.stdout.flush()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.quiet {
+            return Ok(buf.len());
+        }
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+/// `text` cut down to `width` columns, ending in an ellipsis if anything had
+/// to go. Color escape sequences take up no columns, and the colors are reset
+/// after the cut so none runs on.
+pub fn truncate(text: &str, width: usize) -> String {
+    if columns(text) <= width {
+        return text.to_string();
+    }
+
+    let mut cut = String::new();
+    let mut used = 0;
+    let mut styled = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            cut.push(c);
+            cut.push_str(&escape(&mut chars));
+            styled = true;
+            continue;
+        }
+        // Leaving the last column for the ellipsis.
+        if used + 1 >= width {
+            break;
+        }
+        cut.push(c);
+        used += 1;
+    }
+    cut.push('…');
+    if styled {
+        cut.push_str("\x1b[0m");
+    }
+    cut
+}
+
+/// How many columns `text` takes up on a terminal.
+pub fn columns(text: &str) -> usize {
+    let mut count = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            escape(&mut chars);
+        } else {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// The rest of an escape sequence whose `ESC` was just read: `[`, its
+/// parameters and the letter ending it.
+fn escape(chars: &mut Chars) -> String {
+    let mut sequence: String = chars.next().into_iter().collect();
+    for c in chars.by_ref() {
+        sequence.push(c);
+        if ('@'..='~').contains(&c) {
+            break;
+        }
+    }
+    sequence
+}
@@ -0,0 +1,266 @@
+//! `--owners` and `--owner`: who a branch belongs to, going by the
+//! CODEOWNERS file of the base branch and the paths the branch touches, so
+//! cleanup lists can go to the teams that own the code.
+//!
+//! The file is looked for where GitHub and GitLab look, `.github/CODEOWNERS`,
+//! then `CODEOWNERS`, then `docs/CODEOWNERS`. Each line is a gitignore-style
+//! pattern followed by its owners, and the last line matching a path decides
+//! who owns it; a matching line without owners leaves the path unowned.
+//! GitLab's `[Section]` headers are skipped, their rules read as if they
+//! were all in one section.
+//!
+//! A branch's paths are those it changes since it left the base branch or,
+//! once merged, those its tip commit changed. Its owners are everyone
+//! owning one of them, most files first.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch::Branch;
+use crate::glob;
+use crate::Result;
+use git2::{BranchType, Oid, Repository, Tree};
+use std::collections::HashMap;
+
+const PLACES: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+struct Rule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+
+impl CodeOwners {
+    /// The CODEOWNERS file in `tree`, if it has one.
+    pub fn find(repo: &Repository, tree: &Tree) -> Result<Option<CodeOwners>> {
+        for place in PLACES {
+            let entry = match tree.get_path(place.as_ref()) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            if let Ok(blob) = entry.to_object(repo)?.into_blob() {
+                return Ok(Some(CodeOwners::parse(&String::from_utf8_lossy(
+                    blob.content(),
+                ))));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn parse(text: &str) -> CodeOwners {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+            .filter_map(|line| {
+                // An owner list can end in a comment.
+                let line = line.split(" #").next().unwrap_or(line);
+                let mut words = line.split_whitespace();
+                let pattern = words.next()?.replace("\\#", "#");
+                Some(Rule {
+                    pattern,
+                    owners: words.map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Who owns `path`, from the last rule matching it.
+    pub fn owners(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| matches(&rule.pattern, path))
+            .map_or(&[], |rule| &rule.owners)
+    }
+}
+
+/// Fills in `owners` for every branch, when the base branch has a
+/// CODEOWNERS file.
+pub fn annotate(repo: &Repository, branches: &mut [Branch]) -> Result<()> {
+    let base = match repo
+        .find_branch(BASE_BRANCH, BranchType::Local)
+        .ok()
+        .and_then(|branch| branch.get().target())
+    {
+        Some(base) => base,
+        None => return Ok(()),
+    };
+    let codeowners = match CodeOwners::find(repo, &repo.find_commit(base)?.tree()?)? {
+        Some(codeowners) => codeowners,
+        None => return Ok(()),
+    };
+
+    for branch in branches.iter_mut() {
+        let mut files: HashMap<&str, usize> = HashMap::new();
+        for path in touched(repo, base, branch)? {
+            for owner in codeowners.owners(&path) {
+                *files.entry(owner).or_default() += 1;
+            }
+        }
+        let mut owners: Vec<(&str, usize)> = files.into_iter().collect();
+        owners.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        branch.owners = owners
+            .into_iter()
+            .map(|(owner, _)| owner.to_string())
+            .collect();
+    }
+    Ok(())
+}
+
+/// Whether `owner` is what `--owner` asked for: the same owner, ignoring
+/// case and the `@`, or the team of that name in any organization, so
+/// `payments-team` finds `@acme/payments-team`.
+pub fn is(owner: &str, wanted: &str) -> bool {
+    let owner = owner.trim_start_matches('@').to_lowercase();
+    let wanted = wanted.trim_start_matches('@').to_lowercase();
+    owner == wanted
+        || owner
+            .rsplit_once('/')
+            .is_some_and(|(_, team)| team == wanted)
+}
+
+/// The paths the branch changes over the base branch, or that its tip
+/// commit changed if it has nothing of its own.
+fn touched(repo: &Repository, base: Oid, branch: &Branch) -> Result<Vec<String>> {
+    let tip = repo.find_commit(branch.id)?;
+    let from = match branch.fork_point {
+        Some(fork_point) => fork_point,
+        None => repo.merge_base(base, branch.id).unwrap_or(branch.id),
+    };
+    // A root commit is diffed against nothing, adding everything.
+    let from = if from == branch.id {
+        match tip.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        }
+    } else {
+        Some(repo.find_commit(from)?.tree()?)
+    };
+
+    let diff = repo.diff_tree_to_tree(from.as_ref(), Some(&tip.tree()?), None)?;
+    let mut paths = Vec::new();
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path().and_then(|path| path.to_str()) {
+                if !paths.iter().any(|known| known == path) {
+                    paths.push(path.to_string());
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Whether a CODEOWNERS `pattern` covers `path`, as gitignore would match
+/// it: a pattern with a `/` anywhere but the end is anchored at the top,
+/// one without matches at any depth, a trailing `/` only matches
+/// directories, and a match on a directory covers everything in it.
+fn matches(pattern: &str, path: &str) -> bool {
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+    let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    if anchored {
+        covers(&pattern, &path, directory)
+    } else {
+        (0..path.len()).any(|start| covers(&pattern, &path[start..], directory))
+    }
+}
+
+/// Whether `pattern` matches all of `path`, or a directory it starts with.
+fn covers(pattern: &[&str], path: &[&str], directory: bool) -> bool {
+    match pattern.split_first() {
+        // All of the path matched a directory-only pattern, but the last
+        // part is the file itself.
+        None => !(path.is_empty() && directory),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| covers(rest, &path[skip..], directory)),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, names)) if glob::matches(segment, name) => covers(rest, names, directory),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is, matches, CodeOwners};
+
+    #[test]
+    fn everything() {
+        assert!(matches("*", "README.md"));
+        assert!(matches("*", "src/main.rs"));
+        assert!(matches("*.rs", "src/main.rs"));
+        assert!(!matches("*.rs", "src/main.rsx"));
+    }
+
+    #[test]
+    fn anchored_directories() {
+        assert!(matches("/docs/", "docs/index.md"));
+        assert!(matches("/docs/", "docs/a/b.md"));
+        assert!(!matches("/docs/", "src/docs/index.md"));
+        // A trailing `/` only matches directories, not a file of that name.
+        assert!(!matches("/docs/", "docs"));
+        assert!(matches("/docs", "docs"));
+        assert!(matches("src/lib/", "src/lib/x.rs"));
+        assert!(!matches("src/lib/", "a/src/lib/x.rs"));
+    }
+
+    #[test]
+    fn directories_at_any_depth() {
+        assert!(matches("docs/", "docs/index.md"));
+        assert!(matches("docs/", "src/docs/index.md"));
+        assert!(!matches("docs/", "src/docs"));
+        assert!(matches("docs", "src/docs"));
+        assert!(!matches("docs/", "documents/index.md"));
+    }
+
+    #[test]
+    fn double_stars() {
+        assert!(matches("**/x", "x"));
+        assert!(matches("**/x", "a/b/x"));
+        assert!(matches("**/x", "a/x/y"));
+        assert!(!matches("**/x", "a/xy"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(matches("a/**/b", "a/x/b/c"));
+        assert!(!matches("a/**/b", "x/a/b"));
+        assert!(!matches("a/**/b", "a/x/c"));
+    }
+
+    #[test]
+    fn last_rule_wins() {
+        let codeowners = CodeOwners::parse(
+            "# Everything\n\
+             * @acme/core\n\
+             \n\
+             [Docs]\n\
+             /docs/ @acme/writers @bob # and Bob\n\
+             /docs/generated/\n\
+             docs/\\#notes.md @carol\n",
+        );
+        assert_eq!(codeowners.owners("src/main.rs"), ["@acme/core"]);
+        assert_eq!(
+            codeowners.owners("docs/index.md"),
+            ["@acme/writers", "@bob"]
+        );
+        // A rule without owners leaves what it matches unowned.
+        assert!(codeowners.owners("docs/generated/api.md").is_empty());
+        assert_eq!(codeowners.owners("docs/#notes.md"), ["@carol"]);
+    }
+
+    #[test]
+    fn owner_names() {
+        assert!(is("@alice", "alice"));
+        assert!(is("@Alice", "@alice"));
+        assert!(is("@acme/payments-team", "payments-team"));
+        assert!(is("@acme/payments-team", "@ACME/payments-team"));
+        assert!(!is("@acme/payments-team", "acme"));
+        assert!(!is("@acme/payments-team", "payments"));
+        assert!(!is("@alice", "ali"));
+    }
+}
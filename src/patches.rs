@@ -0,0 +1,141 @@
+//! The patch action: a branch whose work might be wanted again, just not as
+//! a branch, is written out the way `git format-patch` would, one mbox file
+//! per commit it has that the base branch doesn't, and then deleted. `git am`
+//! applies them back anywhere.
+//!
+//! Each branch gets a directory of its own under `delete-branches.patchDir`,
+//! taken from the top of the working tree when relative, or under
+//! `.git/delete-branches/patches/` when that isn't set:
+//!
+//! ```ini
+//! [delete-branches]
+//!     patchDir = ~/patches
+//! ```
+//!
+//! Merge commits are left out, as `git format-patch` leaves them out.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::session::{Flow, Session};
+use crate::state;
+use crate::theme::Role;
+use crate::Result;
+use git2::{BranchType, Repository, Sort};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How much of a commit's summary goes into its patch's file name, as in
+/// `git format-patch`.
+const NAME_LENGTH: usize = 52;
+
+impl Session<'_> {
+    /// Exports `branch` as patches, then deletes it.
+    pub fn format_patch(&mut self, branch: Branch) -> Result<Flow> {
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => {
+                write!(self.stdout, "Patches can't be exported in fixture mode\r\n")?;
+                return self.act_on_branch(branch);
+            }
+        };
+
+        match export(repo, &self.config, &branch) {
+            Ok(None) => write!(
+                self.stdout,
+                "'{}' has no commits of its own, there is nothing to export\r\n",
+                branch.name
+            )?,
+            Ok(Some((dir, count))) => write!(
+                self.stdout,
+                "Exported {} patch(es) from '{}' to {}\r\n",
+                count,
+                branch.name,
+                dir.display()
+            )?,
+            Err(e) => {
+                let failed = format!("Could not export '{}': {}", branch.name, e);
+                write!(
+                    self.stdout,
+                    "{}\r\n",
+                    self.theme.paint(Role::Warning, failed)
+                )?;
+                return self.act_on_branch(branch);
+            }
+        }
+        self.delete(branch)
+    }
+}
+
+/// Writes the commits on `branch` that aren't on the base branch as a patch
+/// series, returning the directory and how many there were. `None` if there
+/// weren't any.
+fn export(repo: &Repository, config: &Config, branch: &Branch) -> Result<Option<(PathBuf, usize)>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    walk.push(branch.id)?;
+    if let Some(base) = repo
+        .find_branch(BASE_BRANCH, BranchType::Local)
+        .ok()
+        .and_then(|base| base.get().target())
+    {
+        walk.hide(base)?;
+    }
+    let mut commits = Vec::new();
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() <= 1 {
+            commits.push(commit);
+        }
+    }
+    if commits.is_empty() {
+        return Ok(None);
+    }
+
+    let patch_dir = match config.git().get_path("delete-branches.patchDir") {
+        Ok(dir) => repo.workdir().unwrap_or_else(|| repo.path()).join(dir),
+        Err(_) => state::dir(repo).join("patches"),
+    };
+    // A series exported earlier is left alone.
+    let mut dir = patch_dir.join(&branch.name);
+    if dir.exists() {
+        dir = patch_dir.join(format!("{}-{:.7}", branch.name, branch.id));
+    }
+    fs::create_dir_all(&dir)?;
+
+    let total = commits.len();
+    for (i, commit) in commits.iter().enumerate() {
+        let parent = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let mut diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+        let email = diff.format_email(i + 1, total, commit, None)?;
+        let name = format!(
+            "{:04}-{}.patch",
+            i + 1,
+            file_name(commit.summary().unwrap_or(""))
+        );
+        fs::write(dir.join(name), &*email)?;
+    }
+    Ok(Some((dir, total)))
+}
+
+/// `summary` as `git format-patch` names files after it: runs of anything
+/// but letters, digits, `.` and `_` become a single `-`.
+fn file_name(summary: &str) -> String {
+    let mut name = String::new();
+    for c in summary.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let mut name: String = name.chars().take(NAME_LENGTH).collect();
+    while name.ends_with(['-', '.']) {
+        name.pop();
+    }
+    name
+}
@@ -0,0 +1,73 @@
+//! `git delete-branches plan diff <old> <new>`: what changed between two
+//! plans written with `--record` (or by hand for `--script`), for going
+//! back and forth with a reviewer before replaying one.
+//!
+//! Lines are compared by pattern, so a recorded plan compares branch by
+//! branch. A pattern missing from a plan counts as kept, as it would be when
+//! replaying it.
+
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::review::Decision;
+use crate::script::{self, Script};
+use crate::theme::{Role, Theme};
+use crate::{Error, Result};
+
+pub fn diff(cli: &Cli) -> Result<()> {
+    let (old, new) = match &cli.plans[..] {
+        [old, new] => (Script::load(old)?, Script::load(new)?),
+        _ => return Err(Error::MissingValue("plan diff".to_string())),
+    };
+    let theme = Theme::load(&Config::load(None)?, cli.color, cli.date)?;
+
+    let decision = |plan: &Script, pattern: &str| {
+        plan.rules()
+            .iter()
+            .find(|(other, _)| other == pattern)
+            .map_or(Decision::Keep, |(_, decision)| *decision)
+    };
+    let deletes = |decision| matches!(decision, Decision::Delete | Decision::DeleteWithUpstream);
+
+    let mut patterns: Vec<&str> = Vec::new();
+    for (pattern, _) in old.rules().iter().chain(new.rules()) {
+        if !patterns.contains(&pattern.as_str()) {
+            patterns.push(pattern);
+        }
+    }
+
+    let (mut added, mut removed, mut changed) = (Vec::new(), Vec::new(), Vec::new());
+    for pattern in patterns {
+        let (before, after) = (decision(&old, pattern), decision(&new, pattern));
+        let line = format!(
+            "{}  {} -> {}",
+            pattern,
+            script::name(before),
+            script::name(after)
+        );
+        match (deletes(before), deletes(after)) {
+            (false, true) => added.push(line),
+            (true, false) => removed.push(line),
+            _ if before != after => changed.push(line),
+            _ => {}
+        }
+    }
+
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        println!("The plans make the same decisions");
+        return Ok(());
+    }
+    for (heading, lines, sign, role) in [
+        ("Now deleted", &added, '+', Role::Deleted),
+        ("No longer deleted", &removed, '-', Role::Merged),
+        ("Otherwise changed", &changed, '~', Role::Badge),
+    ] {
+        if lines.is_empty() {
+            continue;
+        }
+        println!("{} ({}):", heading, lines.len());
+        for line in lines {
+            println!("{}", theme.paint(role, format!("{} {}", sign, line)));
+        }
+    }
+    Ok(())
+}
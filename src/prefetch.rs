@@ -0,0 +1,228 @@
+//! The slow extras, `--sizes`, `--check-prs` and `--interactive-rebase-safe`,
+//! worked out on a thread of their own a few branches ahead of the one being
+//! asked about. The first question only waits for the first few branches,
+//! and the rest are usually ready by the time they come up.
+//!
+//! The thread has its own handle on the repository, since git2's can't be
+//! shared. When the session ends early it stops after the batch at hand,
+//! keeping in the caches whatever it had for the branches it didn't get to.
+
+use crate::branch::Branch;
+use crate::forge::{self, Forge};
+use crate::forge_cache::ForgeCache;
+use crate::session::Session;
+use crate::size::Sizes;
+use crate::theme::Role;
+use crate::{wip, Error, Result};
+use git2::Repository;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+/// How many branches are worked out at a time, as many as the forge is
+/// asked about at once.
+const BATCH: usize = 8;
+
+/// Rather than fail part way or sit out a reset, leave a little of the quota
+/// and make do with older data. Interactive sessions don't wait for the rate
+/// limit to reset.
+const LIMITS: forge::QuotaLimits = forge::QuotaLimits {
+    reserve: 10,
+    max_wait: std::time::Duration::ZERO,
+    degrade: true,
+};
+
+/// Which extras to work out.
+pub struct Extras {
+    pub wip: bool,
+    pub sizes: bool,
+    /// The forge to ask, with its cache.
+    pub forge: Option<(Forge, ForgeCache)>,
+}
+
+impl Extras {
+    pub fn any(&self) -> bool {
+        self.wip || self.sizes || self.forge.is_some()
+    }
+}
+
+enum Message {
+    Ready(Vec<Branch>),
+    Note(Role, String),
+    Failed(Error),
+}
+
+pub struct Prefetch {
+    messages: Receiver<Message>,
+    /// Branches worked out ahead of being asked about, by name.
+    ready: HashMap<Vec<u8>, Branch>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Prefetch {
+    /// Starts working out `extras` for `branches`, in the order given.
+    pub fn start(repo: &Repository, extras: Extras, branches: Vec<Branch>) -> Prefetch {
+        let (sender, messages) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let path = repo.path().to_path_buf();
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || {
+            let failed = match Repository::open(path) {
+                Ok(repo) => work(&repo, extras, branches, &stopped, &sender).err(),
+                Err(e) => Some(e.into()),
+            };
+            if let Some(e) = failed {
+                let _ = sender.send(Message::Failed(e));
+            }
+        });
+
+        Prefetch {
+            messages,
+            ready: HashMap::new(),
+            stop,
+        }
+    }
+}
+
+fn work(
+    repo: &Repository,
+    extras: Extras,
+    mut branches: Vec<Branch>,
+    stop: &AtomicBool,
+    sender: &Sender<Message>,
+) -> Result<()> {
+    let mut sizes = if extras.sizes {
+        Sizes::new(repo)?
+    } else {
+        None
+    };
+    let mut forge = extras.forge;
+    if let Some((forge, cache)) = &mut forge {
+        // Not being allowed to list protected branches shouldn't stop the
+        // rest.
+        let _ = cache.refresh_protected(forge);
+    }
+
+    let mut degraded = 0;
+    let mut done = 0;
+    while done < branches.len() && !stop.load(Ordering::Relaxed) {
+        let end = (done + BATCH).min(branches.len());
+        let batch = &mut branches[done..end];
+        if extras.wip {
+            wip::annotate(repo, batch)?;
+        }
+        if let Some(sizes) = &mut sizes {
+            sizes.annotate(batch)?;
+        }
+        if let Some((checked, cache)) = &mut forge {
+            cache.mark_protected(batch);
+            match forge::fetch_statuses(checked, cache, batch, LIMITS, &|_| {}) {
+                Ok(count) => degraded += count,
+                Err(e) => {
+                    cache.save()?;
+                    let note = format!("Could not check pull requests: {}", e);
+                    let _ = sender.send(Message::Note(Role::Warning, note));
+                    forge = None;
+                }
+            }
+        }
+        if sender.send(Message::Ready(batch.to_vec())).is_err() {
+            break;
+        }
+        done = end;
+    }
+
+    if let Some(sizes) = sizes {
+        sizes.save(&branches[done..])?;
+    }
+    if let Some((forge, mut cache)) = forge {
+        cache.save()?;
+        if let Some(rate_limit) = forge.rate_limit() {
+            let quota = format!(
+                "API quota: {} request(s) left{}",
+                rate_limit.remaining,
+                rate_limit
+                    .limit
+                    .map_or(String::new(), |limit| format!(" of {}", limit))
+            );
+            let _ = sender.send(Message::Note(Role::Badge, quota));
+        }
+        if degraded > 0 {
+            let offline = format!(
+                "API quota is low, {} branch(es) show cached pull requests (or none)",
+                degraded
+            );
+            let _ = sender.send(Message::Note(Role::Warning, offline));
+        }
+    }
+    Ok(())
+}
+
+/// Copies the extras worked out for `from` onto `branch`, which may have
+/// changed otherwise since the two were one.
+fn fill(branch: &mut Branch, from: Branch) {
+    branch.stashes = from.stashes;
+    branch.wip_commit = from.wip_commit;
+    branch.size = from.size;
+    branch.pull_request = from.pull_request;
+    branch.checks = from.checks;
+    branch.protected = from.protected;
+}
+
+impl Session<'_> {
+    /// `branch` with its extras, waiting for them if the thread hasn't got
+    /// to it yet.
+    pub fn prefetched(&mut self, mut branch: Branch) -> Result<Branch> {
+        let prefetch = match &mut self.prefetch {
+            Some(prefetch) => prefetch,
+            None => return Ok(branch),
+        };
+        loop {
+            if let Some(ready) = prefetch.ready.remove(&branch.name_bytes) {
+                fill(&mut branch, ready);
+                return Ok(branch);
+            }
+            match prefetch.messages.recv() {
+                Ok(Message::Ready(batch)) => prefetch.ready.extend(
+                    batch
+                        .into_iter()
+                        .map(|ready| (ready.name_bytes.clone(), ready)),
+                ),
+                Ok(Message::Note(role, note)) => {
+                    write!(self.stdout, "{}\r\n", self.theme.paint(role, note))?;
+                }
+                Ok(Message::Failed(e)) => return Err(e),
+                // Done, without this branch.
+                Err(_) => return Ok(branch),
+            }
+        }
+    }
+
+    /// Stops working out extras, once the thread is done with the batch at
+    /// hand, and prints what it had to say about them.
+    pub fn finish_prefetch(&mut self) -> Result<()> {
+        let prefetch = match self.prefetch.take() {
+            Some(prefetch) => prefetch,
+            None => return Ok(()),
+        };
+        prefetch.stop.store(true, Ordering::Relaxed);
+        for message in prefetch.messages {
+            match message {
+                Message::Ready(_) => {}
+                Message::Note(role, note) => {
+                    write!(self.stdout, "{}\r\n", self.theme.paint(role, note))?;
+                }
+                // The branches are dealt with, so it is worth no more than
+                // a warning.
+                Message::Failed(e) => {
+                    let note = format!("Could not work out every branch's extras: {}", e);
+                    write!(self.stdout, "{}\r\n", self.theme.paint(Role::Warning, note))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
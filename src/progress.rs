@@ -0,0 +1,87 @@
+//! The spinner shown while branches are scanned, for repositories big enough
+//! that collecting their metadata takes a noticeable while. It only appears
+//! once a scan has gone on for a moment and stdout is a terminal, and it is
+//! cleared again before anything else is written.
+
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::tty::IsTty;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// How long a scan runs before the spinner shows up.
+const DELAY: Duration = Duration::from_millis(300);
+
+/// How often the spinner is redrawn at most.
+const INTERVAL: Duration = Duration::from_millis(80);
+
+pub struct Progress {
+    enabled: bool,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+struct State {
+    drawn: Option<Instant>,
+    frame: usize,
+}
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress::shown(io::stdout().is_tty())
+    }
+
+    /// A spinner that never appears, for `--quiet`.
+    pub fn hidden() -> Progress {
+        Progress::shown(false)
+    }
+
+    fn shown(enabled: bool) -> Progress {
+        Progress {
+            enabled,
+            started: Instant::now(),
+            state: Mutex::new(State {
+                drawn: None,
+                frame: 0,
+            }),
+        }
+    }
+
+    /// Notes that `done` of `total` branches have been scanned, redrawing
+    /// the spinner when it is due. Safe to call from any thread.
+    pub fn report(&self, done: usize, total: usize) {
+        if !self.enabled || self.started.elapsed() < DELAY {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state
+            .drawn
+            .is_some_and(|drawn| drawn.elapsed() < INTERVAL && done < total)
+        {
+            return;
+        }
+
+        state.frame = (state.frame + 1) % FRAMES.len();
+        state.drawn = Some(Instant::now());
+        let mut stdout = io::stdout();
+        // A spinner that can't be drawn isn't worth failing the scan over.
+        let _ = write!(
+            stdout,
+            "\r{} Scanning {}/{} branches…",
+            FRAMES[state.frame], done, total
+        )
+        .and_then(|_| stdout.flush());
+    }
+
+    /// Clears the spinner, if it was ever drawn.
+    pub fn finish(&self) -> io::Result<()> {
+        if self.state.lock().unwrap().drawn.is_none() {
+            return Ok(());
+        }
+        let mut stdout = io::stdout();
+        write!(stdout, "\r")?;
+        crossterm::execute!(stdout, Clear(ClearType::CurrentLine))
+    }
+}
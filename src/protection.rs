@@ -0,0 +1,126 @@
+//! Branches that must not be deleted, configured as glob patterns:
+//!
+//! ```ini
+//! [delete-branches]
+//!     protect = release/*
+//!     protect = develop
+//! ```
+//!
+//! Where a pattern is set decides how hard it is to get around.
+//! `--unprotect` lifts protections from the user's or the repository's
+//! config after asking, but patterns in the system config are organization
+//! policy and can't be lifted at all.
+
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::session::Session;
+use crate::Result;
+use git2::ConfigLevel;
+use std::fmt;
+use std::io::Write;
+
+pub const KEY: &str = "delete-branches.protect";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    /// The system-wide config, managed by whoever administers the machine.
+    Policy,
+    User,
+    Repository,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Protection {
+    pub pattern: String,
+    pub source: Source,
+}
+
+impl fmt::Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self.source {
+            Source::Policy => "organization policy (system config)",
+            Source::User => "your global config",
+            Source::Repository => "the repository config",
+        };
+        write!(f, "'{}' in {}", self.pattern, source)
+    }
+}
+
+/// Every `delete-branches.protect` pattern, organization policy first.
+pub fn load(config: &Config) -> Result<Vec<Protection>> {
+    let mut protections = Vec::new();
+    let entries = match config.git().multivar(KEY, None) {
+        Ok(entries) => entries,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(protections),
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in &entries {
+        let entry = entry?;
+        let source = match entry.level() {
+            ConfigLevel::ProgramData | ConfigLevel::System => Source::Policy,
+            ConfigLevel::XDG | ConfigLevel::Global => Source::User,
+            _ => Source::Repository,
+        };
+        if let Some(pattern) = entry.value() {
+            protections.push(Protection {
+                pattern: pattern.to_string(),
+                source,
+            });
+        }
+    }
+
+    protections.sort_by_key(|protection| protection.source);
+    Ok(protections)
+}
+
+impl Session<'_> {
+    /// Works out which branches are protected for this session. Where
+    /// `--unprotect` clashes with a configured protection the user decides,
+    /// unless organization policy settles it.
+    pub fn resolve_protections(&mut self, mut branches: Vec<Branch>) -> Result<Vec<Branch>> {
+        let protections = load(&self.config)?;
+        if protections.is_empty() {
+            return Ok(branches);
+        }
+
+        for branch in &mut branches {
+            let protection = match protections
+                .iter()
+                .find(|protection| self.case.matches(&protection.pattern, &branch.name))
+            {
+                Some(protection) => protection.clone(),
+                None => continue,
+            };
+
+            let unprotect = match self
+                .cli
+                .unprotect
+                .iter()
+                .find(|pattern| self.case.matches(pattern, &branch.name))
+            {
+                Some(pattern) => pattern.clone(),
+                None => {
+                    branch.protection = Some(protection);
+                    continue;
+                }
+            };
+
+            if protection.source == Source::Policy {
+                write!(
+                    self.stdout,
+                    "Not unprotecting '{}' for --unprotect '{}': it is protected by {}\r\n",
+                    branch.name, unprotect, protection
+                )?;
+                branch.protection = Some(protection);
+            } else if !self.confirm(&format!(
+                "'{}' is protected by {}, but --unprotect '{}' matches it. Allow deleting it?",
+                branch.name, protection, unprotect
+            ))? {
+                branch.protection = Some(protection);
+            }
+        }
+
+        Ok(branches)
+    }
+}
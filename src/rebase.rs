@@ -0,0 +1,140 @@
+//! The rebase action: a branch worth keeping but far behind gets brought up
+//! to date on the way, by moving it forward when it has nothing of its own
+//! and by handing the terminal to `git rebase` otherwise.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch::Branch;
+use crate::session::{Flow, Session};
+use crate::Result;
+use git2::{BranchType, RepositoryState};
+use std::io::Write;
+
+impl Session<'_> {
+    pub fn rebase(&mut self, mut branch: Branch) -> Result<Flow> {
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => {
+                write!(self.stdout, "Branches can't be rebased in fixture mode\r\n")?;
+                return self.act_on_branch(branch);
+            }
+        };
+        let base = match repo
+            .find_branch(BASE_BRANCH, BranchType::Local)
+            .ok()
+            .and_then(|base| base.get().target())
+        {
+            Some(base) => base,
+            None => {
+                write!(
+                    self.stdout,
+                    "There is no '{}' to rebase onto\r\n",
+                    BASE_BRANCH
+                )?;
+                return self.act_on_branch(branch);
+            }
+        };
+        if branch.has_lossy_name() {
+            write!(
+                self.stdout,
+                "Branches named in invalid UTF-8 can't be rebased\r\n"
+            )?;
+            return self.act_on_branch(branch);
+        }
+        if branch.id == base || repo.graph_descendant_of(branch.id, base)? {
+            write!(
+                self.stdout,
+                "'{}' is already up to date with '{}'\r\n",
+                branch.name, BASE_BRANCH
+            )?;
+            return self.act_on_branch(branch);
+        }
+
+        // Nothing of its own: the ref can just move, unless it is checked
+        // out and the working tree would have to follow.
+        if repo.graph_descendant_of(base, branch.id)? && !self.is_current(&branch) {
+            repo.reference(
+                &format!("refs/heads/{}", branch.name),
+                base,
+                true,
+                &format!("delete-branches: fast-forward to {}", BASE_BRANCH),
+            )?;
+            write!(
+                self.stdout,
+                "Fast-forwarded '{}' to '{}'\r\n",
+                branch.name, BASE_BRANCH
+            )?;
+            branch.id = base;
+            branch.behind = Some(0);
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+
+        let workdir = match repo.workdir() {
+            Some(workdir) => workdir.to_path_buf(),
+            None => {
+                write!(self.stdout, "A bare repository can't be rebased in\r\n")?;
+                return self.act_on_branch(branch);
+            }
+        };
+        if self.backend.has_local_changes()? {
+            write!(
+                self.stdout,
+                "Commit or stash the working tree's changes before rebasing\r\n"
+            )?;
+            return self.act_on_branch(branch);
+        }
+
+        // `git rebase <base> <branch>` leaves the branch checked out, so
+        // whatever was before is checked out again afterwards.
+        let previous = repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand().map(String::from));
+
+        write!(
+            self.stdout,
+            "Running git rebase {} {}\r\n",
+            BASE_BRANCH, branch.name
+        )?;
+        self.stdout.flush()?;
+        let mut rebase = self.git(&workdir);
+        rebase.args(["rebase", BASE_BRANCH, &branch.name]);
+        let rebased = self.input.suspended(|| rebase.status())??;
+
+        let repo = self.backend.repo().ok_or(crate::Error::NeedsRepository)?;
+        if !rebased.success() {
+            if repo.state() != RepositoryState::Clean {
+                write!(
+                    self.stdout,
+                    "The rebase stopped; finish it with git rebase --continue or --abort\r\nQuitting...\r\n"
+                )?;
+                return Ok(Flow::Quit);
+            }
+            write!(self.stdout, "Could not rebase '{}'\r\n", branch.name)?;
+            return self.act_on_branch(branch);
+        }
+
+        if let Some(previous) = previous.filter(|previous| previous != &branch.name) {
+            let mut checkout = self.git(&workdir);
+            checkout.args(["checkout", "--quiet", &previous]);
+            self.input.suspended(|| checkout.status())??;
+        }
+
+        if let Some(tip) = repo
+            .find_branch(&branch.name, BranchType::Local)?
+            .get()
+            .target()
+        {
+            branch.id = tip;
+        }
+        branch.behind = Some(0);
+        write!(
+            self.stdout,
+            "Rebased '{}' onto '{}'\r\n",
+            branch.name, BASE_BRANCH
+        )?;
+        self.keep(branch)?;
+        Ok(Flow::Continue)
+    }
+}
@@ -0,0 +1,120 @@
+//! `--recurse <dir>`: one session over every repository found under a
+//! directory, each introduced with a header, and a single summary at the
+//! end. Quitting in one repository skips the rest.
+
+use crate::backend::{Backend, GitBackend};
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::input::Input;
+use crate::output::Output;
+use crate::session::{Flow, Session, Summary};
+use crate::{triage, upload, Result, Status};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub fn run(cli: Cli, mut input: Input) -> Result<Status> {
+    let mut repositories = Vec::new();
+    for dir in &cli.recurse {
+        for path in discover(dir)? {
+            let name = match path.strip_prefix(dir) {
+                Ok(relative) if relative.as_os_str().is_empty() => dir.display().to_string(),
+                Ok(relative) => relative.display().to_string(),
+                Err(_) => path.display().to_string(),
+            };
+            repositories.push((name, path));
+        }
+    }
+
+    let mut stdout = Output::new(cli.quiet);
+    if repositories.is_empty() {
+        write!(stdout, "No repositories found.\r\n")?;
+        return Ok(Status::NothingMatched);
+    }
+
+    let mut summary = Summary::default();
+    let mut matched = false;
+    // The repositories gone through, for the uploaded report.
+    let mut names = Vec::new();
+    let count = repositories.len();
+    for (i, (name, path)) in repositories.into_iter().enumerate() {
+        write!(stdout, "\r\n== {} ({}/{}) ==\r\n", name, i + 1, count)?;
+
+        let mut backend = match GitBackend::open(&path) {
+            Ok(backend) => backend,
+            Err(e) => {
+                write!(stdout, "Skipping, could not open it: {}\r\n", e)?;
+                continue;
+            }
+        };
+        let config = Config::load(backend.repo())?;
+        let mut session = Session::new(&mut backend, config, cli.clone(), input)?;
+        session.announce_skipped_hooks()?;
+        session.announce_seatbelt()?;
+        session.announce_head()?;
+
+        let flow = if session.cli.clean_archive {
+            session.clean_archive().map(|_| None)
+        } else {
+            triage(&mut session)
+        };
+        session.events.session_end(&session.summary);
+        let Session {
+            input: returned,
+            summary: repository_summary,
+            ..
+        } = session;
+        input = returned;
+        summary.absorb(repository_summary, &name);
+        names.push(name);
+
+        match flow? {
+            Some(Flow::Quit) => {
+                matched = true;
+                break;
+            }
+            Some(Flow::Continue | Flow::Review) => matched = true,
+            None => {}
+        }
+    }
+
+    if !cli.clean_archive {
+        summary.print(&mut stdout)?;
+    }
+    let status = if !summary.failures().is_empty() {
+        Status::DeletionsFailed
+    } else if !matched && !cli.clean_archive {
+        Status::NothingMatched
+    } else {
+        Status::Done
+    };
+    if let Some(url) = &cli.upload_report {
+        let report = upload::report(&summary, names, status);
+        if let Err(e) = upload::upload(url, &Config::load(None)?, &report) {
+            eprint!("{}\r\n", e);
+        }
+    }
+    Ok(status)
+}
+
+/// Repositories under `dir`, including `dir` itself, in path order. Their
+/// own subdirectories aren't searched, and neither are hidden ones.
+fn discover(dir: &Path) -> Result<Vec<PathBuf>> {
+    if dir.join(".git").exists() {
+        return Ok(vec![dir.to_path_buf()]);
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    let mut repositories = Vec::new();
+    for entry in entries {
+        repositories.extend(discover(&entry)?);
+    }
+    Ok(repositories)
+}
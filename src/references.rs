@@ -0,0 +1,105 @@
+//! Branch names spelled out in CI configuration. A workflow triggered on
+//! `release/2.x` or a Jenkinsfile checking out `deploy` goes quietly wrong
+//! once that branch is gone, so deleting one asks first.
+//!
+//! Only tracked files are looked at, as they are in the working tree, and a
+//! name only counts where it isn't part of a longer one: `feature` doesn't
+//! match `feature/login`.
+
+use crate::branch::Branch;
+use crate::glob;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::Result;
+use std::io::Write;
+
+/// Where CI systems keep their configuration.
+const CI_FILES: [&str; 9] = [
+    ".github/workflows/*",
+    ".gitlab-ci.yml",
+    ".gitlab/ci/*",
+    "Jenkinsfile*",
+    ".circleci/*",
+    ".travis.yml",
+    "azure-pipelines.yml",
+    "bitbucket-pipelines.yml",
+    ".buildkite/*",
+];
+
+/// A line of CI configuration naming a branch.
+pub struct Reference {
+    pub path: String,
+    pub line: usize,
+}
+
+/// Every line of the working tree's CI configuration that names `branch`.
+pub fn find(repo: &git2::Repository, branch: &str) -> Result<Vec<Reference>> {
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut references = Vec::new();
+    for entry in repo.index()?.iter() {
+        let path = String::from_utf8_lossy(&entry.path).into_owned();
+        if !CI_FILES.iter().any(|pattern| glob::matches(pattern, &path)) {
+            continue;
+        }
+        // Deleted or unreadable files just don't reference anything.
+        let contents = match std::fs::read_to_string(workdir.join(&path)) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        for (i, line) in contents.lines().enumerate() {
+            if names(line, branch) {
+                references.push(Reference {
+                    path: path.clone(),
+                    line: i + 1,
+                });
+            }
+        }
+    }
+    Ok(references)
+}
+
+/// Whether `line` has `branch` in it, not as part of a longer name. A
+/// full stop after it only continues the name if more of one follows.
+fn names(line: &str, branch: &str) -> bool {
+    let part_of_name = |c: char| c.is_alphanumeric() || "-_/".contains(c);
+    line.match_indices(branch).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let mut after = line[start + branch.len()..].chars();
+        let continued = match after.next() {
+            Some('.') => after.next().is_some_and(part_of_name),
+            next => next.is_some_and(part_of_name),
+        };
+        !before.is_some_and(|c| part_of_name(c) || c == '.') && !continued
+    })
+}
+
+impl Session<'_> {
+    /// Asks before deleting a branch CI configuration names. `true` when
+    /// nothing names it.
+    pub fn references_confirmed(&mut self, branch: &Branch) -> Result<bool> {
+        let references = match self.backend.repo() {
+            Some(repo) => find(repo, &branch.name)?,
+            None => return Ok(true),
+        };
+        if references.is_empty() {
+            return Ok(true);
+        }
+
+        for reference in &references {
+            let named = format!(
+                "'{}' is named in {}:{}",
+                branch.name, reference.path, reference.line
+            );
+            write!(
+                self.stdout,
+                "{}\r\n",
+                self.theme.paint(Role::Warning, named)
+            )?;
+        }
+        self.confirm("CI may depend on it, delete anyway?")
+    }
+}
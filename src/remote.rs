@@ -0,0 +1,225 @@
+use crate::branch::Upstream;
+use crate::config::{Config, Transport};
+use crate::forge::Forge;
+use crate::log::{self, Level};
+use crate::tls::Tls;
+use crate::{Error, Result};
+use git2::{
+    Cred, CredentialType, FetchOptions, FetchPrune, PushOptions, RemoteCallbacks, Repository,
+};
+use std::cell::RefCell;
+
+/// Deletes the upstream branch on its remote using the transport configured
+/// for that remote, returning which one was used.
+pub fn delete_upstream(
+    repo: &Repository,
+    config: &Config,
+    upstream: &Upstream,
+) -> Result<Transport> {
+    let transport = config.remote_transport(&upstream.remote);
+    log::line(
+        Level::Verbose,
+        format!(
+            "deleting {}/{} by {}",
+            upstream.remote,
+            upstream.branch_name(),
+            match transport {
+                Transport::Push => "push",
+                Transport::Api => "the forge API",
+            }
+        ),
+    );
+
+    match transport {
+        Transport::Push => push_delete(repo, config, upstream)?,
+        Transport::Api => {
+            let remote = repo.find_remote(&upstream.remote)?;
+            let url = remote.url().ok_or_else(|| {
+                Error::Forge(format!("remote '{}' has no usable URL", upstream.remote))
+            })?;
+            Forge::for_remote(config, &upstream.remote, url)?
+                .delete_branch(upstream.branch_name())?;
+
+            // Nothing was fetched, so drop the stale remote-tracking ref by hand.
+            if let Ok(mut tracking) = repo.find_reference(&upstream.tracking) {
+                tracking.delete()?;
+            }
+        }
+    }
+
+    Ok(transport)
+}
+
+fn push_delete(repo: &Repository, config: &Config, upstream: &Upstream) -> Result<()> {
+    push(
+        repo,
+        config,
+        &upstream.remote,
+        &format!(":{}", upstream.merge),
+    )
+}
+
+/// Pushes `name` to `remote` under the same name and makes it the branch's
+/// upstream, like `git push -u`.
+pub fn publish(repo: &Repository, config: &Config, name: &str, remote: &str) -> Result<Upstream> {
+    let merge = format!("refs/heads/{}", name);
+    push(repo, config, remote, &format!("{0}:{0}", merge))?;
+
+    // libgit2 normally updates the remote-tracking ref after a push, but
+    // setting an upstream requires it, so make sure it exists.
+    let tracking = format!("refs/remotes/{}/{}", remote, name);
+    let id = repo.refname_to_id(&merge)?;
+    repo.reference(&tracking, id, true, "delete-branches: publish")?;
+
+    repo.find_branch(name, git2::BranchType::Local)?
+        .set_upstream(Some(&format!("{}/{}", remote, name)))?;
+
+    Ok(Upstream {
+        remote: remote.to_string(),
+        merge,
+        tracking,
+        gone: false,
+    })
+}
+
+/// The remote new branches get pushed to: `remote.pushDefault`, `origin`, or
+/// the only remote there is.
+pub fn default_push_remote(repo: &Repository, config: &Config) -> Option<String> {
+    if let Some(remote) = config.string("remote.pushDefault") {
+        return Some(remote);
+    }
+
+    let remotes = repo.remotes().ok()?;
+    let remotes: Vec<&str> = remotes.iter().flatten().collect();
+    match remotes.as_slice() {
+        [only] => Some(only.to_string()),
+        _ if remotes.contains(&"origin") => Some("origin".to_string()),
+        _ => None,
+    }
+}
+
+/// Pushes `refspec` to `remote`, failing if the remote rejects it.
+pub fn push(repo: &Repository, config: &Config, remote: &str, refspec: &str) -> Result<()> {
+    log::timed(
+        Level::Verbose,
+        || format!("push {} to {}", refspec, remote),
+        || push_refspec(repo, config, remote, refspec),
+    )
+}
+
+fn push_refspec(repo: &Repository, config: &Config, remote: &str, refspec: &str) -> Result<()> {
+    let mut remote = match repo.find_remote(remote)? {
+        // A mirror fetches the remote's branches onto the local ones, and
+        // libgit2 would update those after the push like remote-tracking
+        // refs, deleting the local branch along with the remote one.
+        found
+            if config
+                .git()
+                .get_bool(&format!("remote.{}.mirror", remote))
+                .unwrap_or(false) =>
+        {
+            let url = found.pushurl().or(found.url()).unwrap_or_default();
+            repo.remote_anonymous(url)?
+        }
+        found => found,
+    };
+    let rejection = RefCell::new(None);
+
+    {
+        let mut callbacks = remote_callbacks(config, remote.url().unwrap_or_default())?;
+        callbacks.push_update_reference(|refname, status| {
+            if let Some(status) = status {
+                *rejection.borrow_mut() = Some(format!("{}: {}", refname, status));
+            }
+            Ok(())
+        });
+
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(&[refspec], Some(&mut options))?;
+    }
+
+    match rejection.into_inner() {
+        Some(message) => Err(git2::Error::from_str(&format!("push rejected {}", message)).into()),
+        None => Ok(()),
+    }
+}
+
+/// Fetches `remote` with prune semantics, returning the remote-tracking
+/// branches (`origin/topic`) that were removed because their branch no longer exists on the remote.
+pub fn prune(repo: &Repository, config: &Config, remote: &str) -> Result<Vec<String>> {
+    log::timed(
+        Level::Verbose,
+        || format!("fetch --prune {}", remote),
+        || fetch_pruned(repo, config, remote),
+    )
+}
+
+fn fetch_pruned(repo: &Repository, config: &Config, remote: &str) -> Result<Vec<String>> {
+    let mut remote = repo.find_remote(remote)?;
+    let pruned = RefCell::new(Vec::new());
+
+    {
+        let mut callbacks = remote_callbacks(config, remote.url().unwrap_or_default())?;
+        callbacks.update_tips(|refname, _, new| {
+            if new.is_zero() {
+                let name = refname.strip_prefix("refs/remotes/").unwrap_or(refname);
+                pruned.borrow_mut().push(name.to_string());
+            }
+            true
+        });
+
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks).prune(FetchPrune::On);
+        remote.fetch(&[] as &[&str], Some(&mut options), None)?;
+    }
+
+    Ok(pruned.into_inner())
+}
+
+/// Callbacks that authenticate the way git would: the ssh agent for ssh
+/// remotes, the credential helper for https ones. TLS for an http(s) `url`
+/// is set up the way git would too.
+pub fn remote_callbacks<'a>(config: &'a Config, url: &str) -> Result<RemoteCallbacks<'a>> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+
+    // `http.sslVerify` has nothing to say about ssh host keys, which libgit2
+    // asks the same callback about.
+    if url.starts_with("https://") || url.starts_with("http://") {
+        let tls = Tls::for_url(config, url);
+        tls.configure_libgit2()?;
+        if !tls.verify {
+            // Only asked when libgit2's own verification failed.
+            callbacks.certificate_check(|cert, _| cert.as_x509().is_some());
+        }
+    }
+
+    callbacks.credentials(move |url, username, allowed| {
+        // libgit2 keeps asking for as long as we hand out credentials, so
+        // stop once the first ones were clearly rejected.
+        attempts += 1;
+        if attempts > 3 {
+            log::line(Level::Verbose, format!("{} rejected every credential", url));
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            log::timed(
+                Level::Trace,
+                || format!("ssh agent key for {}, attempt {}", url, attempts),
+                || Cred::ssh_key_from_agent(username.unwrap_or("git")),
+            )
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            log::timed(
+                Level::Trace,
+                || format!("credential helper for {}, attempt {}", url, attempts),
+                || Cred::credential_helper(config.git(), url, username),
+            )
+        } else {
+            Cred::default()
+        }
+    });
+
+    Ok(callbacks)
+}
@@ -0,0 +1,177 @@
+//! `git delete-branches report`: branch hygiene statistics without the
+//! interactive session, as text or `--json`.
+
+use crate::anonymize::Anonymizer;
+use crate::backend::Backend;
+use crate::branch::{self, Branch};
+use crate::cli::Cli;
+use crate::encoding::Decoder;
+use crate::forge;
+use crate::forge_cache::ForgeCache;
+use crate::json::Value;
+use crate::owners;
+use crate::Result;
+use std::collections::HashSet;
+
+/// Branches whose last commit is older than this count as stale.
+const STALE_DAYS: i64 = 90;
+
+struct Row {
+    name: String,
+    author: Option<String>,
+    age_days: i64,
+    status: &'static str,
+    upstream: &'static str,
+    pull_request: Option<String>,
+    /// With `--owners`.
+    owners: Option<Vec<String>>,
+}
+
+pub fn run(backend: &dyn Backend, cli: &Cli) -> Result<()> {
+    let mut branches = backend.branches()?;
+    if let Some(repo) = backend.repo() {
+        let project = forge::origin_url(repo)
+            .ok()
+            .and_then(|url| forge::parse_remote_url(&url));
+        if let Some((_, project)) = project {
+            ForgeCache::load(repo, &project)?.annotate(&mut branches);
+        }
+        if cli.owners {
+            owners::annotate(repo, &mut branches)?;
+        }
+    }
+
+    let anonymizer = cli.anonymize.then(Anonymizer::new);
+    let rows: Vec<Row> = branches
+        .iter()
+        .map(|branch| row(backend, cli, anonymizer.as_ref(), branch))
+        .collect();
+
+    if cli.json {
+        println!("{}", to_json(&rows));
+    } else {
+        print_text(&rows);
+    }
+
+    Ok(())
+}
+
+fn row(backend: &dyn Backend, cli: &Cli, anonymizer: Option<&Anonymizer>, branch: &Branch) -> Row {
+    let author = backend.repo().and_then(|repo| {
+        let commit = repo.find_commit(branch.id).ok()?;
+        Some(Decoder::new(repo).author_email(&commit))
+    });
+
+    Row {
+        name: match anonymizer {
+            Some(anonymizer) => anonymizer.branch(&branch.name),
+            None => branch.name.clone(),
+        },
+        author: match anonymizer {
+            Some(anonymizer) => author.map(|author| anonymizer.identity(&author)),
+            None => author,
+        },
+        age_days: branch::days_since(branch.time).max(0),
+        status: match (branch.merged, branch.squash_merged) {
+            (Some(true), _) => "merged",
+            (_, true) => "squash-merged",
+            (Some(false), _) => "unmerged",
+            (None, _) => "unknown",
+        },
+        upstream: match &branch.upstream {
+            None if branch.orphaned => "unpushed",
+            None => "none",
+            Some(upstream) if upstream.gone => "gone",
+            Some(_) => "tracking",
+        },
+        pull_request: branch
+            .pull_request
+            .as_ref()
+            .map(|pr| pr.state.name().to_string()),
+        owners: cli.owners.then(|| branch.owners.clone()),
+    }
+}
+
+fn to_json(rows: &[Row]) -> Value {
+    let branches = rows
+        .iter()
+        .map(|row| {
+            let mut fields = vec![
+                ("name".to_string(), row.name.as_str().into()),
+                ("author".to_string(), row.author.clone().into()),
+                ("age_days".to_string(), row.age_days.into()),
+                ("status".to_string(), row.status.into()),
+                ("upstream".to_string(), row.upstream.into()),
+                ("pr".to_string(), row.pull_request.clone().into()),
+            ];
+            if let Some(owners) = &row.owners {
+                let owners: Vec<Value> = owners.iter().map(|owner| owner.as_str().into()).collect();
+                fields.push(("owners".to_string(), owners.into()));
+            }
+            Value::Object(fields)
+        })
+        .collect();
+
+    Value::Object(vec![
+        ("totals".to_string(), Value::Object(totals(rows))),
+        ("branches".to_string(), Value::Array(branches)),
+    ])
+}
+
+fn totals(rows: &[Row]) -> Vec<(String, Value)> {
+    let count = |f: &dyn Fn(&Row) -> bool| rows.iter().filter(|row| f(row)).count();
+    let authors: HashSet<_> = rows.iter().filter_map(|row| row.author.as_ref()).collect();
+
+    vec![
+        ("branches".to_string(), rows.len().into()),
+        (
+            "merged".to_string(),
+            count(&|row| row.status == "merged").into(),
+        ),
+        (
+            "squash_merged".to_string(),
+            count(&|row| row.status == "squash-merged").into(),
+        ),
+        (
+            "upstream_gone".to_string(),
+            count(&|row| row.upstream == "gone").into(),
+        ),
+        (
+            "unpushed".to_string(),
+            count(&|row| row.upstream == "unpushed").into(),
+        ),
+        (
+            format!("older_than_{}_days", STALE_DAYS),
+            count(&|row| row.age_days > STALE_DAYS).into(),
+        ),
+        ("authors".to_string(), authors.len().into()),
+    ]
+}
+
+fn print_text(rows: &[Row]) {
+    let width = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+    for row in rows {
+        println!(
+            "{:width$}  {:>5}d  {:13}  upstream {:8}  {}{}{}",
+            row.name,
+            row.age_days,
+            row.status,
+            row.upstream,
+            row.author.as_deref().unwrap_or("-"),
+            row.pull_request
+                .as_ref()
+                .map_or(String::new(), |pr| format!("  PR {}", pr)),
+            match &row.owners {
+                Some(owners) if owners.is_empty() => "  owners -".to_string(),
+                Some(owners) => format!("  owners {}", owners.join(",")),
+                None => String::new(),
+            },
+            width = width
+        );
+    }
+
+    println!();
+    for (name, value) in totals(rows) {
+        println!("{}: {}", name.replace('_', " "), value);
+    }
+}
@@ -0,0 +1,307 @@
+//! `git delete-branches restore [<branch>]`: lists the trash, or brings a
+//! deleted branch back from it, upstream and all.
+//!
+//! A branch the trash doesn't have, because it was emptied out or the branch
+//! was deleted some other way, can often still be found: the audit log
+//! remembers every tip this tool deleted, `HEAD`'s reflog where the branch
+//! was last left, and other branches' reflogs what they were created or
+//! merged from. Those are offered as candidates, labelled with how sure they
+//! are to be the branch's last commit.
+
+use crate::backend::Backend;
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::encoding::Decoder;
+use crate::trash::Trash;
+use crate::{audit, run, state, Error, Result};
+use chrono::{Local, TimeZone};
+use git2::{Oid, Repository};
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Confidence {
+    /// Where the branch was when this tool deleted it.
+    High,
+    /// Where the branch was when it was last used, it may have moved since.
+    Medium,
+    /// Somewhere the branch once was.
+    Low,
+}
+
+impl Confidence {
+    fn label(self) -> &'static str {
+        match self {
+            Confidence::High => "high",
+            Confidence::Medium => "medium",
+            Confidence::Low => "low",
+        }
+    }
+}
+
+struct Candidate {
+    tip: Oid,
+    confidence: Confidence,
+    source: String,
+    time: i64,
+}
+
+/// Most candidates offered for one branch.
+const MAX_CANDIDATES: usize = 10;
+
+pub fn run(backend: &dyn Backend, config: &Config, cli: &Cli) -> Result<()> {
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let mut trashes = Trash::all(repo)?;
+
+    let name = match &cli.branch {
+        Some(name) => name,
+        None => {
+            list(&trashes);
+            return Ok(());
+        }
+    };
+
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        return Err(Error::InvalidValue(
+            "restore".to_string(),
+            format!("a branch named '{}' already exists", name),
+        ));
+    }
+
+    // The latest deletion of the name, whoever deleted it, if it is still
+    // in a trash.
+    let in_trash = trashes
+        .iter()
+        .enumerate()
+        .flat_map(|(t, trash)| {
+            trash
+                .entries
+                .iter()
+                .enumerate()
+                .map(move |(i, entry)| (t, i, entry))
+        })
+        .filter(|(_, _, entry)| &entry.name == name)
+        .max_by_key(|(_, _, entry)| entry.deleted_at)
+        .map(|(t, i, _)| (t, i));
+    if let Some((t, i)) = in_trash {
+        let trash = &mut trashes[t];
+        let entry = trash.entries.remove(i);
+        create(repo, name, entry.tip)?;
+        entry.config.write(repo, name)?;
+        trash.purge(repo, config)?;
+        trash.save()?;
+        println!(
+            "Restored '{}' at {} from {}",
+            name,
+            short(entry.tip),
+            match &trash.user {
+                Some(user) if *user == state::user() => "the trash".to_string(),
+                Some(user) => format!("{}'s trash", user),
+                None => "the shared trash".to_string(),
+            }
+        );
+        return Ok(());
+    }
+
+    let candidates = candidates(repo, name)?;
+    if candidates.is_empty() {
+        println!("'{}' isn't in the trash, the audit log or any reflog", name);
+        return Ok(());
+    }
+
+    println!(
+        "'{}' isn't in the trash. It may have been at one of these:",
+        name
+    );
+    let decoder = Decoder::new(repo);
+    for (i, candidate) in candidates.iter().enumerate() {
+        let subject = repo
+            .find_commit(candidate.tip)
+            .map(|commit| decoder.subject(&commit))
+            .unwrap_or_default();
+        println!(
+            "  {:>2}  {}  {:<6}  {}, {}: \"{}\"",
+            i + 1,
+            short(candidate.tip),
+            candidate.confidence.label(),
+            candidate.source,
+            date(candidate.time),
+            subject
+        );
+    }
+
+    print!(
+        "Restore which one? (1-{}, Enter to cancel) > ",
+        candidates.len()
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    let chosen = answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| candidates.get(n.checked_sub(1)?));
+    match chosen {
+        Some(candidate) => {
+            create(repo, name, candidate.tip)?;
+            println!("Restored '{}' at {}", name, short(candidate.tip));
+        }
+        None => println!("Nothing was restored"),
+    }
+    Ok(())
+}
+
+/// Everything in every trash, latest first, with who deleted it.
+fn list(trashes: &[Trash]) {
+    let mut entries: Vec<_> = trashes
+        .iter()
+        .flat_map(|trash| {
+            trash
+                .entries
+                .iter()
+                .map(move |entry| (trash.user.as_deref(), entry))
+        })
+        .collect();
+    if entries.is_empty() {
+        println!("The trash is empty");
+        return;
+    }
+
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.deleted_at));
+    let width = entries
+        .iter()
+        .map(|(_, entry)| entry.name.len())
+        .max()
+        .unwrap_or(0);
+    for (user, entry) in entries {
+        println!(
+            "{:<width$}  {}  deleted {}{}",
+            entry.name,
+            short(entry.tip),
+            date(entry.deleted_at),
+            user.map_or(String::new(), |user| format!(" by {}", user)),
+            width = width
+        );
+    }
+}
+
+fn create(repo: &Repository, name: &str, tip: Oid) -> Result<()> {
+    repo.branch(name, &repo.find_commit(tip)?, false)?;
+    audit::append(repo, run::next(), "restored", name, tip)
+}
+
+/// Every commit `name` may have pointed at that still exists, surest and
+/// then latest first, one per commit.
+fn candidates(repo: &Repository, name: &str) -> Result<Vec<Candidate>> {
+    let mut found = Vec::new();
+
+    for entry in audit::entries(repo)? {
+        if entry.event == "deleted" && entry.branch == name {
+            found.push(Candidate {
+                tip: entry.tip,
+                confidence: Confidence::High,
+                source: match &entry.user {
+                    Some(user) => format!("deleted by {}, as the audit log has it", user),
+                    None => "deleted, as the audit log has it".to_string(),
+                },
+                time: entry.time,
+            });
+        }
+    }
+
+    if let Ok(reflog) = repo.reflog("HEAD") {
+        for entry in reflog.iter() {
+            let message = entry.message().unwrap_or_default();
+            let time = entry.committer().when().seconds();
+            if let Some((from, _)) = message
+                .strip_prefix("checkout: moving from ")
+                .and_then(|moving| moving.split_once(" to "))
+            {
+                if from == name {
+                    found.push(Candidate {
+                        tip: entry.id_old(),
+                        confidence: Confidence::Medium,
+                        source: "last left, by HEAD's reflog".to_string(),
+                        time,
+                    });
+                }
+            }
+            if message
+                .strip_prefix("checkout: moving from ")
+                .is_some_and(|moving| moving.ends_with(&format!(" to {}", name)))
+            {
+                found.push(Candidate {
+                    tip: entry.id_new(),
+                    confidence: Confidence::Low,
+                    source: "checked out, by HEAD's reflog".to_string(),
+                    time,
+                });
+            }
+        }
+    }
+
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let refname = match branch.get().name() {
+            Some(refname) => refname.to_string(),
+            None => continue,
+        };
+        let other = branch.name().ok().flatten().unwrap_or_default().to_string();
+        let reflog = match repo.reflog(&refname) {
+            Ok(reflog) => reflog,
+            Err(_) => continue,
+        };
+
+        for entry in reflog.iter() {
+            let message = entry.message().unwrap_or_default();
+            let time = entry.committer().when().seconds();
+            let tip = if message == format!("branch: Created from {}", name) {
+                Some((entry.id_new(), format!("'{}' was created from it", other)))
+            } else if message.starts_with(&format!("merge {}: Fast-forward", name)) {
+                Some((
+                    entry.id_new(),
+                    format!("'{}' was fast-forwarded to it", other),
+                ))
+            } else if message.starts_with(&format!("merge {}:", name)) {
+                // The second parent of the merge is what was merged in.
+                repo.find_commit(entry.id_new())
+                    .and_then(|merge| merge.parent_id(1))
+                    .ok()
+                    .map(|parent| (parent, format!("'{}' merged it", other)))
+            } else {
+                None
+            };
+            if let Some((tip, source)) = tip {
+                found.push(Candidate {
+                    tip,
+                    confidence: Confidence::Low,
+                    source,
+                    time,
+                });
+            }
+        }
+    }
+
+    found.retain(|candidate| repo.find_commit(candidate.tip).is_ok());
+    found.sort_by_key(|candidate| (candidate.confidence, std::cmp::Reverse(candidate.time)));
+    let mut seen = Vec::new();
+    found.retain(|candidate| {
+        let first = !seen.contains(&candidate.tip);
+        seen.push(candidate.tip);
+        first
+    });
+    found.truncate(MAX_CANDIDATES);
+    Ok(found)
+}
+
+fn short(oid: Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+fn date(time: i64) -> String {
+    Local
+        .timestamp(time, 0)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
@@ -0,0 +1,173 @@
+//! How long branches in each namespace are meant to live, for teams that
+//! think of `tmp/` and `release/` branches differently:
+//!
+//! ```ini
+//! [delete-branches]
+//!     retention = tmp/* 7d
+//!     retention = feature/* 90d
+//!     retention = release/* never
+//! ```
+//!
+//! Ages are in days (`d`), weeks (`w`), months of 30 days (`m`) or years
+//! (`y`). Where patterns overlap the last one listed wins, so a
+//! repository's own config can override a global one. A branch under a
+//! retention policy is stale once it is older than that, or never, instead
+//! of by its score; `--expired` only offers the ones past it.
+//!
+//! Branches CI makes, one per release or deploy, are better kept by count
+//! than by age: `newest N` keeps the N most recent branches the pattern
+//! covers, and the older ones are past it.
+//!
+//! ```ini
+//! [delete-branches]
+//!     retention = deploy/* newest 5
+//! ```
+//!
+//! `--keep-last-n-per-prefix N` does the same for every namespace at once,
+//! leaving the newest N of each out of the session; with a `--script`
+//! saying `* delete`, the rest are deleted unattended.
+
+use crate::branch::{self, Branch};
+use crate::config::Config;
+use crate::glob::Case;
+use crate::{Error, Result};
+use std::collections::HashMap;
+
+pub const KEY: &str = "delete-branches.retention";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Days(i64),
+    Never,
+    /// Only the newest so many branches.
+    Newest(usize),
+}
+
+pub struct Retention {
+    policies: Vec<(String, Limit, String)>,
+    case: Case,
+    /// How many newer branches share each branch's policy, for `newest`.
+    newer: HashMap<String, usize>,
+}
+
+impl Retention {
+    pub fn load(config: &Config, case: Case) -> Result<Retention> {
+        let mut policies = Vec::new();
+        let entries = match config.git().multivar(KEY, None) {
+            Ok(entries) => entries,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(Retention {
+                    policies,
+                    case,
+                    newer: HashMap::new(),
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        for entry in &entries {
+            let entry = entry?;
+            let value = entry.value().unwrap_or_default();
+            let invalid = || Error::InvalidValue(KEY.to_string(), value.to_string());
+
+            let (pattern, age) = value
+                .trim()
+                .split_once(char::is_whitespace)
+                .ok_or_else(invalid)?;
+            let age = age.trim();
+            let limit = parse_age(age).ok_or_else(invalid)?;
+            policies.push((pattern.to_string(), limit, age.to_string()));
+        }
+
+        Ok(Retention {
+            policies,
+            case,
+            newer: HashMap::new(),
+        })
+    }
+
+    /// Counts, for `newest` policies, how many of `branches` are newer than
+    /// each branch under the same policy.
+    pub fn rank(&mut self, branches: &[Branch]) {
+        let mut newest: Vec<&Branch> = branches.iter().collect();
+        newest.sort_by_key(|branch| std::cmp::Reverse(branch.time));
+        let mut counted: HashMap<&str, usize> = HashMap::new();
+        let mut newer = HashMap::new();
+        for branch in newest {
+            if let Some((pattern, Limit::Newest(_), _)) = self.policy(branch) {
+                let count = counted.entry(pattern).or_default();
+                newer.insert(branch.name.clone(), *count);
+                *count += 1;
+            }
+        }
+        self.newer = newer;
+    }
+
+    /// The policy covering `branch`: its pattern, limit and the age as
+    /// configured.
+    pub fn policy(&self, branch: &Branch) -> Option<(&str, Limit, &str)> {
+        self.policies
+            .iter()
+            .rev()
+            .find(|(pattern, _, _)| self.case.matches(pattern, &branch.name))
+            .map(|(pattern, limit, age)| (pattern.as_str(), *limit, age.as_str()))
+    }
+
+    /// Whether `branch` is older than its policy allows, `None` if no
+    /// policy covers it.
+    pub fn expired(&self, branch: &Branch) -> Option<bool> {
+        self.policy(branch).map(|(_, limit, _)| match limit {
+            Limit::Days(days) => age_days(branch) > days,
+            Limit::Never => false,
+            Limit::Newest(count) => self.newer(branch).is_some_and(|newer| newer >= count),
+        })
+    }
+
+    /// How many newer branches share `branch`'s `newest` policy, once
+    /// [`Retention::rank`] has counted them.
+    pub fn newer(&self, branch: &Branch) -> Option<usize> {
+        self.newer.get(&branch.name).copied()
+    }
+}
+
+/// `--keep-last-n-per-prefix`: all but the newest `count` branches of each
+/// namespace, top-level branches counting as one.
+pub fn past_newest(branches: Vec<Branch>, count: usize) -> Vec<Branch> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut newest = branches;
+    newest.sort_by_key(|branch| std::cmp::Reverse(branch.time));
+    let mut past: Vec<Branch> = newest
+        .into_iter()
+        .filter(|branch| {
+            let newer = seen.entry(branch.prefix().to_string()).or_default();
+            *newer += 1;
+            *newer > count
+        })
+        .collect();
+    past.sort_by_key(|branch| branch.time);
+    past
+}
+
+pub fn age_days(branch: &Branch) -> i64 {
+    branch::days_since(branch.time)
+}
+
+fn parse_age(age: &str) -> Option<Limit> {
+    if age == "never" {
+        return Some(Limit::Never);
+    }
+    if let Some(count) = age.strip_prefix("newest") {
+        return count.trim().parse().ok().map(Limit::Newest);
+    }
+
+    let unit = age.chars().last()?;
+    let count: i64 = age[..age.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit {
+        'd' => 1,
+        'w' => 7,
+        'm' => 30,
+        'y' => 365,
+        _ => return None,
+    };
+    Some(Limit::Days(count * days))
+}
@@ -0,0 +1,294 @@
+//! Decide on every branch first, delete at the end, unless `--immediate`.
+//!
+//! During the session deletions are only marked, and `=` skips to the end
+//! with whatever is marked so far. Afterwards the decisions are shown
+//! grouped, and branches can be moved between groups to catch a slip of the
+//! finger before anything is actually deleted. Nothing is deleted until that
+//! is confirmed, and the first deletion that doesn't go through stops the
+//! rest, so what is left is known rather than half done.
+
+use crate::branch::Branch;
+use crate::bulk::Bulk;
+use crate::session::{Flow, Session};
+use crate::theme::Role;
+use crate::Result;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Delete,
+    DeleteWithUpstream,
+    Keep,
+    Snooze,
+}
+
+pub struct Mark {
+    pub branch: Branch,
+    pub decision: Decision,
+}
+
+impl Mark {
+    pub fn deletes(&self) -> bool {
+        matches!(
+            self.decision,
+            Decision::Delete | Decision::DeleteWithUpstream
+        )
+    }
+}
+
+impl Session<'_> {
+    pub fn mark(&mut self, branch: Branch, decision: Decision) -> Result<Flow> {
+        if decision != Decision::Keep {
+            write!(
+                self.stdout,
+                "Marked '{}' to {}\r\n",
+                branch.name,
+                describe(decision)
+            )?;
+        }
+        if let Some(marks) = &mut self.marks {
+            marks.push(Mark { branch, decision });
+        }
+        Ok(Flow::Continue)
+    }
+
+    pub fn keep(&mut self, branch: Branch) -> Result<()> {
+        if self.marks.is_some() {
+            self.mark(branch, Decision::Keep)?;
+        } else {
+            self.record(&branch.name, Decision::Keep);
+            self.summary.kept.push(branch.name);
+        }
+        Ok(())
+    }
+
+    pub fn snooze(&mut self, branch: Branch) -> Result<()> {
+        if self.marks.is_some() {
+            self.mark(branch, Decision::Snooze)?;
+        } else {
+            self.record(&branch.name, Decision::Snooze);
+            self.summary.snoozed.push(branch.name);
+        }
+        Ok(())
+    }
+
+    /// Asks about `branch` again after its deletion was called off, or keeps
+    /// it while the review is being carried out, there being no prompt
+    /// left to ask at.
+    pub fn not_deleted(&mut self, branch: Branch) -> Result<Flow> {
+        if self.deferred && self.marks.is_none() {
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+        self.act_on_branch(branch)
+    }
+
+    /// Undo while marking: the most recently marked deletion is kept instead.
+    pub fn unmark_last_deletion(&mut self) -> Result<()> {
+        let mark = self.marks.iter_mut().flatten().rev().find(|mark| {
+            matches!(
+                mark.decision,
+                Decision::Delete | Decision::DeleteWithUpstream
+            )
+        });
+
+        match mark {
+            Some(mark) => {
+                mark.decision = Decision::Keep;
+                let name = mark.branch.name.clone();
+                write!(self.stdout, "Keeping '{}' after all\r\n", name)?;
+            }
+            None => write!(self.stdout, "No branch to undo deletion of\r\n")?,
+        }
+        Ok(())
+    }
+
+    /// Shows the marked decisions for a last look, then carries them out.
+    /// With no deletion marked there is nothing to look over.
+    pub fn review(&mut self) -> Result<()> {
+        let mut marks = match self.marks.take() {
+            Some(marks) if !marks.is_empty() => marks,
+            _ => return Ok(()),
+        };
+        if !marks.iter().any(Mark::deletes) {
+            return self.settle(marks);
+        }
+
+        loop {
+            print_marks(&mut self.stdout, &marks)?;
+            write!(
+                self.stdout,
+                "Move branches with e.g. 'k 1 3' (d = delete, D = with remote, k = keep, s = snooze),\r\n\
+                 Enter to go ahead, q to quit without deleting anything\r\n> "
+            )?;
+            self.stdout.flush()?;
+
+            let line = match self.input.read_line(&mut self.stdout)? {
+                Some(line) => line,
+                None => "q".to_string(),
+            };
+
+            let mut words = line.split_whitespace();
+            let decision = match words.next() {
+                None => {
+                    let deleting = marks.iter().filter(|mark| mark.deletes()).count();
+                    if deleting == 0
+                        || self.confirm(&format!("Delete {} branch(es) now?", deleting))?
+                    {
+                        break;
+                    }
+                    continue;
+                }
+                Some("q") => {
+                    write!(self.stdout, "Nothing was deleted\r\n")?;
+                    return self.settle(marks);
+                }
+                Some("d") => Decision::Delete,
+                Some("D") => Decision::DeleteWithUpstream,
+                Some("k") => Decision::Keep,
+                Some("s") => Decision::Snooze,
+                Some(other) => {
+                    write!(self.stdout, "Unknown group '{}'\r\n", other)?;
+                    continue;
+                }
+            };
+
+            for word in words {
+                let mark = word
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| marks.get_mut(n.checked_sub(1)?));
+                match mark {
+                    Some(mark)
+                        if decision == Decision::DeleteWithUpstream
+                            && mark.branch.remote_target().is_none() =>
+                    {
+                        write!(
+                            self.stdout,
+                            "'{}' has no remote branch to delete\r\n",
+                            mark.branch.name
+                        )?;
+                    }
+                    Some(mark) => mark.decision = decision,
+                    None => write!(self.stdout, "No branch number '{}'\r\n", word)?,
+                }
+            }
+        }
+
+        marks.sort_by_key(|mark| std::cmp::Reverse(mark.branch.depth()));
+        let mut bulk = Bulk::new(0, marks.len());
+        let mut stopped = false;
+        // The branch whose deletion didn't go through, and the ones kept
+        // for it.
+        let mut failed: Option<(String, Vec<String>)> = None;
+        for mark in marks {
+            stopped = stopped || !bulk.next(&mut self.stdout, &mut self.input, &self.theme)?;
+            let decision = match (mark.decision, &mut failed) {
+                (Decision::Delete | Decision::DeleteWithUpstream, Some((_, left))) => {
+                    left.push(mark.branch.name.clone());
+                    Decision::Keep
+                }
+                (Decision::Delete | Decision::DeleteWithUpstream, None) if stopped => {
+                    Decision::Keep
+                }
+                (decision, _) => decision,
+            };
+            let name = mark.branch.name.clone();
+            let failures = self.summary.failed.len();
+            let flow = match decision {
+                Decision::Delete => self.delete(mark.branch)?,
+                Decision::DeleteWithUpstream => self.delete_with_upstream(mark.branch)?,
+                Decision::Keep => {
+                    self.keep(mark.branch)?;
+                    Flow::Continue
+                }
+                Decision::Snooze => {
+                    self.snooze(mark.branch)?;
+                    Flow::Continue
+                }
+            };
+            if failed.is_none() && self.summary.failed.len() > failures {
+                failed = Some((name, Vec::new()));
+            }
+            if let Flow::Quit = flow {
+                bulk.clear(&mut self.stdout)?;
+                break;
+            }
+            if !stopped {
+                bulk.advance(&mut self.stdout, &self.theme)?;
+            }
+        }
+
+        if let Some((name, left)) = failed.filter(|(_, left)| !left.is_empty()) {
+            let stopped = format!(
+                "Stopped after '{}' could not be deleted, keeping the {} branch(es) left: {}",
+                name,
+                left.len(),
+                left.join(", ")
+            );
+            write!(
+                self.stdout,
+                "{}\r\n",
+                self.theme.paint(Role::Warning, stopped)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Keeps or snoozes every marked branch, deleting none.
+    fn settle(&mut self, marks: Vec<Mark>) -> Result<()> {
+        for mark in marks {
+            match mark.decision {
+                Decision::Snooze => self.snooze(mark.branch)?,
+                _ => self.keep(mark.branch)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Delete => "delete",
+        Decision::DeleteWithUpstream => "delete with its remote branch",
+        Decision::Keep => "keep",
+        Decision::Snooze => "snooze",
+    }
+}
+
+fn print_marks(stdout: &mut impl Write, marks: &[Mark]) -> Result<()> {
+    write!(stdout, "\r\nReview\r\n")?;
+
+    let groups = [
+        (
+            "Will delete",
+            &[Decision::Delete, Decision::DeleteWithUpstream][..],
+        ),
+        ("Kept", &[Decision::Keep][..]),
+        ("Snoozed", &[Decision::Snooze][..]),
+    ];
+    for (title, decisions) in groups {
+        let members: Vec<_> = marks
+            .iter()
+            .enumerate()
+            .filter(|(_, mark)| decisions.contains(&mark.decision))
+            .collect();
+        write!(stdout, "{} ({}):\r\n", title, members.len())?;
+        for (i, mark) in members {
+            write!(stdout, "  {:>3}  {}", i + 1, mark.branch.name)?;
+            if let (Decision::DeleteWithUpstream, Some(upstream)) =
+                (mark.decision, mark.branch.remote_target())
+            {
+                write!(
+                    stdout,
+                    " (and {}/{})",
+                    upstream.remote,
+                    upstream.branch_name()
+                )?;
+            }
+            write!(stdout, "\r\n")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! What ties together the records one invocation leaves behind: a random
+//! UUID for the run, and a sequence number for every action in it, counting
+//! from 1. Both go into the audit log and the `--events-socket` events, and
+//! the summary names the run, so a chat notification, an audit entry and
+//! whatever a listener did with the event can be matched up and put in
+//! order.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+static ID: OnceLock<String> = OnceLock::new();
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// This run's UUID.
+pub fn id() -> &'static str {
+    ID.get_or_init(uuid)
+}
+
+/// The sequence number of the next action.
+pub fn next() -> u64 {
+    SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// A version 4 UUID. The standard library's hasher keys are random per
+/// process, which is all the randomness a correlation id needs.
+fn uuid() -> String {
+    let half = || {
+        let mut hasher = RandomState::new().build_hasher();
+        SystemTime::now().hash(&mut hasher);
+        std::process::id().hash(&mut hasher);
+        hasher.finish()
+    };
+    let high = (half() & !0xf000) | 0x4000;
+    let low = (half() & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
@@ -0,0 +1,171 @@
+//! How stale a branch looks, as a weighted sum of a few factors. Teams can
+//! tune the weights to their own idea of stale:
+//!
+//! ```ini
+//! [delete-branches "weights"]
+//!     age = 1        ; per month since the last commit, up to a year
+//!     merged = 10    ; merged or squash-merged into the base branch
+//!     gone = 5       ; the upstream was deleted
+//!     ahead = -0.5   ; per commit the base branch doesn't have
+//!     pr = 5         ; the pull request was merged or closed (open counts
+//!                    ; against, four times as much)
+//!     ci = 3         ; CI failed on the tip (passing counts against, for
+//!                    ; unmerged branches)
+//! [delete-branches]
+//!     staleThreshold = 10
+//! ```
+//!
+//! Branches scoring at least the threshold are badged as stale, and the
+//! explain action shows what each factor contributed.
+
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::forge::{Ci, PrState};
+use crate::{Error, Result};
+
+/// Past a year, a branch isn't getting any staler.
+const MAX_AGE_MONTHS: f64 = 12.0;
+
+pub struct Weights {
+    age: f64,
+    merged: f64,
+    gone: f64,
+    ahead: f64,
+    pr: f64,
+    ci: f64,
+    threshold: f64,
+}
+
+/// One factor's part in a score.
+pub struct Contribution {
+    pub factor: &'static str,
+    /// What was measured, e.g. "14 months".
+    pub detail: String,
+    pub points: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            age: 1.0,
+            merged: 10.0,
+            gone: 5.0,
+            ahead: -0.5,
+            pr: 5.0,
+            ci: 3.0,
+            threshold: 10.0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn load(config: &Config) -> Result<Weights> {
+        let mut weights = Weights::default();
+        let fields = [
+            ("delete-branches.weights.age", &mut weights.age),
+            ("delete-branches.weights.merged", &mut weights.merged),
+            ("delete-branches.weights.gone", &mut weights.gone),
+            ("delete-branches.weights.ahead", &mut weights.ahead),
+            ("delete-branches.weights.pr", &mut weights.pr),
+            ("delete-branches.weights.ci", &mut weights.ci),
+            ("delete-branches.staleThreshold", &mut weights.threshold),
+        ];
+
+        for (key, weight) in fields {
+            if let Some(value) = config.string(key) {
+                *weight = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::InvalidValue(key.to_string(), value))?;
+            }
+        }
+
+        Ok(weights)
+    }
+
+    pub fn contributions(&self, branch: &Branch) -> Vec<Contribution> {
+        let mut contributions = Vec::new();
+
+        let days = crate::branch::days_since(branch.time).max(0);
+        contributions.push(Contribution {
+            factor: "age",
+            detail: crate::branch::age(branch.time),
+            points: self.age * (days as f64 / 30.0).min(MAX_AGE_MONTHS),
+        });
+
+        if branch.merged == Some(true) || branch.squash_merged {
+            contributions.push(Contribution {
+                factor: "merged",
+                detail: if branch.squash_merged {
+                    "squash-merged".to_string()
+                } else {
+                    "merged".to_string()
+                },
+                points: self.merged,
+            });
+        }
+
+        if branch
+            .upstream
+            .as_ref()
+            .is_some_and(|upstream| upstream.gone)
+        {
+            contributions.push(Contribution {
+                factor: "gone",
+                detail: "upstream deleted".to_string(),
+                points: self.gone,
+            });
+        }
+
+        if let Some(ahead) = branch.ahead.filter(|&ahead| ahead > 0) {
+            contributions.push(Contribution {
+                factor: "ahead",
+                detail: format!("{} unmerged commit(s)", ahead),
+                points: self.ahead * ahead as f64,
+            });
+        }
+
+        if let Some(pr) = &branch.pull_request {
+            contributions.push(Contribution {
+                factor: "pr",
+                detail: pr.to_string(),
+                points: match pr.state {
+                    PrState::Open => -4.0 * self.pr,
+                    PrState::Merged | PrState::Closed => self.pr,
+                },
+            });
+        }
+
+        let merged = branch.merged == Some(true) || branch.squash_merged;
+        match branch.checks {
+            Some(Ci::Red) => contributions.push(Contribution {
+                factor: "ci",
+                detail: "CI red".to_string(),
+                points: self.ci,
+            }),
+            Some(Ci::Green) if !merged => contributions.push(Contribution {
+                factor: "ci",
+                detail: "CI green, unmerged".to_string(),
+                points: -self.ci,
+            }),
+            _ => {}
+        }
+
+        contributions
+    }
+
+    pub fn score(&self, branch: &Branch) -> f64 {
+        self.contributions(branch)
+            .iter()
+            .map(|contribution| contribution.points)
+            .sum()
+    }
+
+    pub fn is_stale(&self, branch: &Branch) -> bool {
+        self.score(branch) >= self.threshold
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+}
@@ -0,0 +1,150 @@
+//! `--script <file>` replays decisions without asking, and `--record <file>`
+//! writes the ones made in a session out in the same format, so a cleanup
+//! can be reviewed on one clone and then applied to another:
+//!
+//! ```text
+//! # pattern      action
+//! feature/*      delete
+//! release/2021*  deleteWithUpstream
+//! experiment     snooze
+//! ```
+//!
+//! Patterns are globs, the first line matching a branch decides, and
+//! branches no line matches are kept. Anything that would need asking while
+//! replaying, such as an open pull request, stashed work or a dangerous
+//! action, is answered no, which leaves the branch as it is.
+
+use crate::glob::Case;
+use crate::review::Decision;
+use crate::session::Session;
+use crate::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DECISIONS: [Decision; 4] = [
+    Decision::Keep,
+    Decision::Snooze,
+    Decision::Delete,
+    Decision::DeleteWithUpstream,
+];
+
+pub fn name(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Keep => "keep",
+        Decision::Snooze => "snooze",
+        Decision::Delete => "delete",
+        Decision::DeleteWithUpstream => "deleteWithUpstream",
+    }
+}
+
+pub struct Script {
+    rules: Vec<(String, Decision)>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script> {
+        let invalid = |detail: String| Error::InvalidValue(path.display().to_string(), detail);
+        let text = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let decision = match words[..] {
+                [_, action] => DECISIONS
+                    .into_iter()
+                    .find(|decision| name(*decision).eq_ignore_ascii_case(action)),
+                _ => None,
+            };
+            match decision {
+                Some(decision) => rules.push((words[0].to_string(), decision)),
+                None => return Err(invalid(format!("line {}: {}", i + 1, line))),
+            }
+        }
+
+        Ok(Script { rules })
+    }
+
+    /// Each line's pattern and decision, in order.
+    pub fn rules(&self) -> &[(String, Decision)] {
+        &self.rules
+    }
+
+    /// What to do with `branch`, and why if no line said so.
+    pub fn decide(&self, branch: &str, case: Case) -> (Decision, Option<&'static str>) {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| case.matches(pattern, branch))
+            .map_or(
+                (Decision::Keep, Some("no line in the script")),
+                |(_, decision)| (*decision, None),
+            )
+    }
+}
+
+/// The decisions of a session, to be written out with `--record`.
+pub struct Recording {
+    path: PathBuf,
+    decisions: Vec<(String, Decision)>,
+}
+
+impl Recording {
+    pub fn new(path: PathBuf) -> Recording {
+        Recording {
+            path,
+            decisions: Vec::new(),
+        }
+    }
+
+    /// Branch names can't contain `*` or `?`, so each one is written as a
+    /// pattern matching only itself.
+    pub fn save(&self) -> Result<()> {
+        let width = self
+            .decisions
+            .iter()
+            .map(|(branch, _)| branch.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut text = format!(
+            "# Recorded by {} on {}\n",
+            env!("CARGO_PKG_NAME"),
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        );
+        for (branch, decision) in &self.decisions {
+            text.push_str(&format!(
+                "{:<width$}  {}\n",
+                branch,
+                name(*decision),
+                width = width
+            ));
+        }
+
+        fs::write(&self.path, text).map_err(|e| {
+            Error::InvalidValue(format!("--record {}", self.path.display()), e.to_string())
+        })
+    }
+}
+
+impl Session<'_> {
+    /// Notes what became of `branch` for `--record`. A later decision, such
+    /// as keeping a branch by undoing its deletion, replaces an earlier one.
+    pub fn record(&mut self, branch: &str, decision: Decision) {
+        let recording = match &mut self.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+        match recording
+            .decisions
+            .iter_mut()
+            .find(|(recorded, _)| recorded == branch)
+        {
+            Some((_, recorded)) => *recorded = decision,
+            None => recording.decisions.push((branch.to_string(), decision)),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Seatbelt mode, training wheels for whoever is new to the tool. It is on
+//! until taken off in config:
+//!
+//! ```ini
+//! [delete-branches]
+//!     seatbelt = false
+//!     # At most this many deletions per session while it is on.
+//!     seatbeltLimit = 10
+//! ```
+//!
+//! With the seatbelt on, deletions are only marked and carried out after a
+//! final review, as with `--mark` (scripts, with nobody there to review,
+//! delete straight away); a session deletes at most `seatbeltLimit`
+//! branches; branches that aren't merged can't be deleted; and the trash
+//! keeps deleted branches at least 30 days, whatever `trashDays` says.
+//!
+//! `--seatbelt` puts it on for one session. Set to true in the system
+//! config it is organization policy, and stays on whatever users set, the
+//! way a team can mandate it for new hires.
+
+use crate::branch::Branch;
+use crate::config::Config;
+use crate::review::Decision;
+use crate::session::Session;
+use crate::theme::Role;
+use crate::{Error, Result};
+use git2::ConfigLevel;
+use std::io::Write;
+
+pub const KEY: &str = "delete-branches.seatbelt";
+
+pub const LIMIT_KEY: &str = "delete-branches.seatbeltLimit";
+
+const DEFAULT_LIMIT: usize = 10;
+
+pub struct Seatbelt {
+    /// Most deletions in one session.
+    pub limit: usize,
+}
+
+impl Seatbelt {
+    /// The seatbelt, unless config takes it off and `forced` (`--seatbelt`)
+    /// doesn't put it back on.
+    pub fn load(config: &Config, forced: bool) -> Result<Option<Seatbelt>> {
+        let policy = match config.git().open_level(ConfigLevel::System) {
+            Ok(system) => system.get_bool(KEY).unwrap_or(false),
+            Err(_) => false,
+        };
+        let configured = match config.string(KEY) {
+            Some(value) => git2::Config::parse_bool(value.as_str())
+                .map_err(|_| Error::InvalidValue(KEY.to_string(), value))?,
+            None => true,
+        };
+        if !forced && !policy && !configured {
+            return Ok(None);
+        }
+
+        let limit = match config.string(LIMIT_KEY) {
+            Some(value) => value
+                .parse::<usize>()
+                .map_err(|_| Error::InvalidValue(LIMIT_KEY.to_string(), value))?,
+            None => DEFAULT_LIMIT,
+        };
+        Ok(Some(Seatbelt { limit }))
+    }
+}
+
+impl Session<'_> {
+    /// Says the seatbelt is on, and how to take it off.
+    pub fn announce_seatbelt(&mut self) -> Result<()> {
+        let limit = match &self.seatbelt {
+            Some(seatbelt) => seatbelt.limit,
+            None => return Ok(()),
+        };
+        let on = format!(
+            "Seatbelt on: deletions are reviewed before they happen, merged branches only, at most {} this session.",
+            limit
+        );
+        write!(self.stdout, "{}\r\n", self.theme.paint(Role::Badge, on))?;
+        write!(
+            self.stdout,
+            "`git config --global {} false` takes it off.\r\n",
+            KEY
+        )?;
+        Ok(())
+    }
+
+    /// Whether the seatbelt lets `branch` be deleted, saying why not if it
+    /// doesn't. Marked deletions count towards the limit.
+    pub fn seatbelt_allows(&mut self, branch: &Branch) -> Result<bool> {
+        let limit = match &self.seatbelt {
+            Some(seatbelt) => seatbelt.limit,
+            None => return Ok(true),
+        };
+
+        let marked = self
+            .marks
+            .iter()
+            .flatten()
+            .filter(|mark| {
+                matches!(
+                    mark.decision,
+                    Decision::Delete | Decision::DeleteWithUpstream
+                )
+            })
+            .count();
+        let refused = if branch.merged == Some(false) && !branch.squash_merged {
+            format!("Seatbelt: '{}' isn't merged, so it is kept", branch.name)
+        } else if self.summary.deleted.len() + marked >= limit {
+            format!(
+                "Seatbelt: {} deletion(s) is the most for one session, so '{}' is kept",
+                limit, branch.name
+            )
+        } else {
+            return Ok(true);
+        };
+        write!(
+            self.stdout,
+            "{}\r\n",
+            self.theme.paint(Role::Warning, refused)
+        )?;
+        Ok(false)
+    }
+}
@@ -0,0 +1,1630 @@
+use crate::archive;
+use crate::audit;
+use crate::backend::{self, Backend};
+use crate::branch::Branch;
+use crate::branch_config::BranchConfig;
+use crate::bulk::Bulk;
+use crate::cli::{Cli, TombstoneMode};
+use crate::companion::{self, Companion};
+use crate::config::{Config, Transport};
+use crate::danger::Dangers;
+use crate::encoding::Decoder;
+use crate::events::Events;
+use crate::forge::{Ci, PrState};
+use crate::glob::Case;
+use crate::head::Head;
+use crate::input::{Input, Key};
+use crate::keys::{BranchAction, Keymap};
+use crate::output::{self, Output};
+use crate::prefetch::Prefetch;
+use crate::remote;
+use crate::retention::{self, Limit, Retention};
+use crate::review::{Decision, Mark};
+use crate::score::Weights;
+use crate::script::{Recording, Script};
+use crate::seatbelt::Seatbelt;
+use crate::theme::{Role, Theme};
+use crate::tombstone::Tombstone;
+use crate::worktree::Worktree;
+use crate::{run, Error, Result};
+use crossterm::cursor::MoveUp;
+use crossterm::terminal::{Clear, ClearType};
+use git2::Oid;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// A branch deleted during the session, with what is needed to undo it.
+pub struct Deletion {
+    pub branch: Branch,
+    /// Archive tag created for the branch tip, see `--archive-tags`.
+    pub archive_tag: Option<String>,
+    /// Note written with `--tombstones`.
+    pub tombstone: Option<Tombstone>,
+    /// `branch.<name>.*` as it was before git dropped it.
+    pub branch_config: BranchConfig,
+    /// Refs deleted along with the branch, see [`crate::companion`].
+    pub companions: Vec<Companion>,
+}
+
+/// State carried across the prompts of one interactive run.
+pub struct Session<'a> {
+    pub stdout: Output,
+    pub input: Input,
+    pub backend: &'a mut dyn Backend,
+    pub config: Config,
+    pub cli: Cli,
+    pub keymap: Keymap,
+    pub weights: Weights,
+    pub retention: Retention,
+    pub theme: Theme,
+    pub dangers: Dangers,
+    pub events: Events,
+    /// Decisions to replay instead of asking, with `--script`.
+    pub script: Option<Script>,
+    /// Decisions to write out at the end, with `--record`.
+    pub recording: Option<Recording>,
+    /// What every branch still to come gets, after `a` or `K`.
+    pub rest: Option<Decision>,
+    /// What `/` is looking for, every branch up to it is kept.
+    jump: Option<String>,
+    /// The branch last decided without asking. Offered again straight away
+    /// means its action didn't go through, and it is kept rather than tried
+    /// forever.
+    decided: Option<String>,
+    /// Deletions that can still be undone, most recent last.
+    pub deletions: Vec<Deletion>,
+    /// Decisions waiting for review, until the review takes them.
+    pub marks: Option<Vec<Mark>>,
+    /// Whether deletions wait for the review at the end, see [`crate::review`].
+    pub deferred: bool,
+    pub seatbelt: Option<Seatbelt>,
+    pub head: Head,
+    pub case: Case,
+    /// The branch checked out with `c` during the session, which replaces
+    /// whatever was current when the branches were listed.
+    pub checked_out: Option<String>,
+    /// The slow extras being worked out ahead, see [`crate::prefetch`].
+    pub prefetch: Option<Prefetch>,
+    pub summary: Summary,
+}
+
+impl<'a> Session<'a> {
+    /// A session over `backend`, with keys, weights and colors as `config`
+    /// and `cli` have them.
+    pub fn new(
+        backend: &'a mut dyn Backend,
+        config: Config,
+        cli: Cli,
+        mut input: Input,
+    ) -> Result<Session<'a>> {
+        let script = cli.script.as_deref().map(Script::load).transpose()?;
+        if script.is_some() {
+            input.set_unattended();
+        }
+        let seatbelt = Seatbelt::load(&config, cli.seatbelt)?;
+        let case = Case::load(&config, cli.ignore_case)?;
+        // Nobody is there to review a script's deletions, and the seatbelt
+        // insists on a review.
+        let deferred = script.is_none() && (!cli.immediate || seatbelt.is_some());
+        let marks = deferred.then(Vec::new);
+
+        Ok(Session {
+            stdout: Output::new(cli.quiet),
+            input,
+            keymap: Keymap::load(&config, cli.keys.as_deref())?,
+            weights: Weights::load(&config)?,
+            retention: Retention::load(&config, case)?,
+            theme: Theme::load(&config, cli.color, cli.date)?,
+            dangers: Dangers::load(&config)?,
+            events: Events::connect(
+                cli.events_socket.as_deref(),
+                backend
+                    .repo()
+                    .map(|repo| repo.path().display().to_string())
+                    .unwrap_or_default(),
+            )?,
+            script,
+            recording: cli.record.clone().map(Recording::new),
+            rest: None,
+            jump: None,
+            decided: None,
+            marks,
+            deferred,
+            seatbelt,
+            head: backend.repo().map_or(Head::Branch, Head::of),
+            case,
+            backend,
+            config,
+            cli,
+            deletions: Vec::new(),
+            checked_out: None,
+            prefetch: None,
+            summary: Summary::default(),
+        })
+    }
+
+    /// Offers each branch in turn until they run out or the user quits.
+    pub fn run(&mut self, branches: Vec<Branch>) -> Result<Flow> {
+        let total = branches.len();
+        let mut bulk = None;
+        for (i, branch) in branches.into_iter().enumerate() {
+            if self.rest == Some(Decision::Delete) {
+                // Counting the branch `a` was pressed on, if it is one of these.
+                let pressed = usize::from(i > 0);
+                let current = bulk.get_or_insert_with(|| Bulk::new(pressed, total - i + pressed));
+                if !current.next(&mut self.stdout, &mut self.input, &self.theme)? {
+                    self.rest = Some(Decision::Keep);
+                    bulk = None;
+                }
+            }
+            let branch = self.prefetched(branch)?;
+            let flow = self.act_on_branch(branch)?;
+            if let Some(bulk) = &mut bulk {
+                bulk.advance(&mut self.stdout, &self.theme)?;
+            }
+            if let Flow::Quit | Flow::Review = flow {
+                if let Some(bulk) = &mut bulk {
+                    bulk.clear(&mut self.stdout)?;
+                }
+                return Ok(flow);
+            }
+        }
+        if let Some(pattern) = self.jump.take() {
+            write!(self.stdout, "No branch after matched '{}'\r\n", pattern)?;
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Whether `branch` is checked out in the main worktree right now.
+    pub fn is_current(&self, branch: &Branch) -> bool {
+        match &self.checked_out {
+            Some(name) => name == &branch.name,
+            None => branch.is_head,
+        }
+    }
+
+    /// Why a branch can't be deleted at all, if it can't.
+    pub fn ignore_reason(&self, branch: &Branch) -> Option<String> {
+        if let Some(reason) = self.head.holds(&branch.name) {
+            Some(reason)
+        } else if self.is_current(branch) && !self.cli.include_head {
+            Some("it is the current branch".to_string())
+        } else if let Some(protection) = &branch.protection {
+            Some(format!("it is protected by {}", protection))
+        } else if branch.keep {
+            Some(format!("branch.{}.keep is set", branch.name))
+        } else {
+            branch
+                .worktree
+                .as_ref()
+                .map(|path| format!("it is checked out in worktree {}", path.display()))
+        }
+    }
+
+    /// Whether `branch` is past its retention or, without one, scores as
+    /// stale.
+    pub fn is_stale(&self, branch: &Branch) -> bool {
+        self.retention
+            .expired(branch)
+            .unwrap_or_else(|| self.weights.is_stale(branch))
+    }
+
+    pub fn act_on_branch(&mut self, branch: Branch) -> Result<Flow> {
+        if let Some(reason) = self.ignore_reason(&branch) {
+            write!(
+                self.stdout,
+                "Ignoring '{}' because {}\r\n",
+                branch.name, reason
+            )?;
+        } else {
+            self.events.branch_presented(&branch);
+            let action = match self.decided_action(&branch)? {
+                Some(action) => action,
+                None => {
+                    let stale = self.is_stale(&branch);
+                    let unavailable = self.unavailable_actions(&branch);
+                    get_branch_action_from_user(
+                        &mut self.stdout,
+                        &mut self.input,
+                        &self.keymap,
+                        &self.theme,
+                        &branch,
+                        stale,
+                        &unavailable,
+                    )?
+                }
+            };
+            match action {
+                BranchAction::Delete
+                | BranchAction::DeleteWithUpstream
+                | BranchAction::FormatPatch
+                    if !self.force_confirmed(&branch)? =>
+                {
+                    return self.act_on_branch(branch)
+                }
+                _ if !self.action_confirmed(action, &branch)? => return self.act_on_branch(branch),
+                BranchAction::Quit => {
+                    write!(self.stdout, "Quitting...\r\n")?;
+                    return Ok(Flow::Quit);
+                }
+                BranchAction::Keep => self.keep(branch)?,
+                BranchAction::KeepForever => return self.keep_forever(branch),
+                BranchAction::Publish => return self.publish(branch),
+                BranchAction::Rename => return self.rename(branch),
+                BranchAction::Checkout => return self.checkout(branch),
+                BranchAction::Rebase => return self.rebase(branch),
+                BranchAction::FormatPatch => return self.format_patch(branch),
+                BranchAction::Delete => return self.delete(branch),
+                BranchAction::DeleteWithUpstream => return self.delete_with_upstream(branch),
+                BranchAction::DeleteRest => {
+                    self.input.drain()?;
+                    let question = format!("Delete '{}' and every branch after it?", branch.name);
+                    if self.confirm(&question)? {
+                        self.rest = Some(Decision::Delete);
+                    }
+                    return self.act_on_branch(branch);
+                }
+                BranchAction::KeepRest => {
+                    self.rest = Some(Decision::Keep);
+                    return self.act_on_branch(branch);
+                }
+                BranchAction::Review if self.marks.is_some() => {
+                    write!(self.stdout, "Skipping to the review...\r\n")?;
+                    return Ok(Flow::Review);
+                }
+                BranchAction::Review => {
+                    write!(
+                        self.stdout,
+                        "Nothing waits for a review, deletions happen right away with --immediate\r\n"
+                    )?;
+                    return self.act_on_branch(branch);
+                }
+                BranchAction::Search => return self.search(branch),
+                BranchAction::Snooze => self.snooze(branch)?,
+                BranchAction::Explain => {
+                    self.explain(&branch)?;
+                    return self.act_on_branch(branch);
+                }
+                BranchAction::Undo if self.marks.is_some() => {
+                    self.unmark_last_deletion()?;
+                    return self.act_on_branch(branch);
+                }
+                BranchAction::Undo => {
+                    match self.deletions.pop() {
+                        Some(deletion) => self.undo(deletion)?,
+                        None => write!(self.stdout, "No branch to undo deletion of\r\n")?,
+                    }
+
+                    return self.act_on_branch(branch);
+                }
+            }
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /// The actions that can't do anything for `branch` right now, with why,
+    /// for the help.
+    fn unavailable_actions(&self, branch: &Branch) -> Vec<(BranchAction, String)> {
+        let fixture = self.backend.repo().is_none();
+        BranchAction::ALL
+            .iter()
+            .filter_map(|&action| {
+                let why = match action {
+                    BranchAction::Publish => match &branch.upstream {
+                        Some(upstream) => Some(format!(
+                            "it already tracks {}/{}",
+                            upstream.remote,
+                            upstream.branch_name()
+                        )),
+                        None if fixture => Some("not with --fixture".to_string()),
+                        None => None,
+                    },
+                    BranchAction::KeepForever | BranchAction::Rebase if branch.has_lossy_name() => {
+                        Some("its name isn't valid UTF-8".to_string())
+                    }
+                    BranchAction::KeepForever
+                    | BranchAction::Rebase
+                    | BranchAction::FormatPatch
+                        if fixture =>
+                    {
+                        Some("not with --fixture".to_string())
+                    }
+                    BranchAction::Rebase if branch.behind == Some(0) => {
+                        Some(format!("it is up to date with {}", backend::BASE_BRANCH))
+                    }
+                    BranchAction::FormatPatch if branch.ahead == Some(0) => {
+                        Some("it has no commits of its own".to_string())
+                    }
+                    BranchAction::Checkout => match &self.head {
+                        Head::Bare { .. } => Some("the repository is bare".to_string()),
+                        Head::Detached {
+                            interrupted: Some((name, what)),
+                            ..
+                        } => Some(format!("'{}' is {}", name, what)),
+                        _ => None,
+                    },
+                    BranchAction::DeleteWithUpstream if branch.remote_target().is_none() => {
+                        Some("it has no remote branch".to_string())
+                    }
+                    BranchAction::Review if self.marks.is_none() => {
+                        Some("deletions happen right away with --immediate".to_string())
+                    }
+                    BranchAction::Undo => match &self.marks {
+                        Some(marks) if !marks.iter().any(Mark::deletes) => {
+                            Some("no deletion is marked yet".to_string())
+                        }
+                        None if self.deletions.is_empty() => {
+                            Some("nothing was deleted yet".to_string())
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                why.map(|why| (action, why))
+            })
+            .collect()
+    }
+
+    /// Asks what to look for, then keeps `branch` and every one after it
+    /// up to the next that matches.
+    fn search(&mut self, branch: Branch) -> Result<Flow> {
+        write!(self.stdout, "Jump to (substring or glob) > ")?;
+        self.stdout.flush()?;
+        match self.input.read_line(&mut self.stdout)? {
+            Some(pattern) if !pattern.trim().is_empty() => {
+                self.jump = Some(pattern.trim().to_string());
+                self.keep(branch)?;
+                Ok(Flow::Continue)
+            }
+            _ => self.act_on_branch(branch),
+        }
+    }
+
+    /// What to do with `branch` without asking, if a script or `a`/`K` has
+    /// already decided it, or `/` is looking for another branch.
+    fn decided_action(&mut self, branch: &Branch) -> Result<Option<BranchAction>> {
+        let (decision, why) = match (self.rest, &self.script) {
+            (None, None) => match self.jump.take() {
+                Some(pattern) => {
+                    let found = if pattern.contains(['*', '?']) {
+                        self.case.matches(&pattern, &branch.name)
+                    } else {
+                        self.case.contains(&branch.name, &pattern)
+                    };
+                    if found {
+                        return Ok(None);
+                    }
+                    self.jump = Some(pattern);
+                    (Decision::Keep, Some("looking for another branch"))
+                }
+                _ => return Ok(None),
+            },
+            _ if self.decided.as_deref() == Some(&branch.name) => {
+                (Decision::Keep, Some("that didn't go through"))
+            }
+            (Some(decision), _) => (decision, None),
+            (None, Some(script)) => script.decide(&branch.name, self.case),
+        };
+        self.decided = Some(branch.name.clone());
+
+        write!(
+            self.stdout,
+            "'{}' > {}{}\r\n",
+            self.theme.paint(Role::Branch, &branch.name),
+            crate::script::name(decision),
+            why.map(|why| format!(" ({})", why)).unwrap_or_default()
+        )?;
+        Ok(Some(match decision {
+            Decision::Delete => BranchAction::Delete,
+            Decision::DeleteWithUpstream => BranchAction::DeleteWithUpstream,
+            Decision::Keep => BranchAction::Keep,
+            Decision::Snooze => BranchAction::Snooze,
+        }))
+    }
+
+    /// Restores a deleted branch and takes back what was done along with
+    /// deleting it. A branch that can't be restored yet stays next in line;
+    /// one left deleted goes to the back of the line, and on to the trash.
+    pub fn undo(&mut self, deletion: Deletion) -> Result<()> {
+        let Deletion {
+            branch: deleted,
+            archive_tag,
+            tombstone,
+            branch_config,
+            companions,
+        } = deletion;
+        write!(
+            self.stdout,
+            "Undoing deletion of branch '{}'\r\n",
+            deleted.name
+        )?;
+
+        // The name may have been taken again since, by a new branch.
+        let (restored, force) = match self.backend.tip(&deleted) {
+            None => (deleted.clone(), false),
+            Some(tip) if tip == deleted.id => (deleted.clone(), true),
+            Some(tip) => match self.retaken(&deleted, tip)? {
+                Some(restored) => {
+                    let force = restored.name == deleted.name;
+                    (restored, force)
+                }
+                None => {
+                    write!(
+                        self.stdout,
+                        "Leaving '{}' deleted, it goes to the trash\r\n",
+                        deleted.name
+                    )?;
+                    self.deletions.insert(
+                        0,
+                        Deletion {
+                            branch: deleted,
+                            archive_tag,
+                            tombstone,
+                            branch_config,
+                            companions,
+                        },
+                    );
+                    return Ok(());
+                }
+            },
+        };
+
+        if let Err(e) = self.backend.restore(&restored, force) {
+            write!(
+                self.stdout,
+                "Could not restore '{}': {}\r\n",
+                deleted.name, e
+            )?;
+            self.deletions.push(Deletion {
+                branch: deleted,
+                archive_tag,
+                tombstone,
+                branch_config,
+                companions,
+            });
+            return Ok(());
+        }
+        let sequence = run::next();
+        self.events.undo(&restored, sequence);
+        if let Some(repo) = self.backend.repo() {
+            branch_config.write(repo, &restored.name)?;
+            audit::append(repo, sequence, "restored", &restored.name, restored.id)?;
+        }
+        if let (Some(tombstone), Some(repo)) = (tombstone, self.backend.repo()) {
+            tombstone.undo(repo)?;
+        }
+        if let Some(repo) = self.backend.repo() {
+            companion::restore(repo, &companions)?;
+            self.summary
+                .companions
+                .retain(|name| !companions.iter().any(|companion| &companion.name == name));
+        }
+        if let (Some(tag), Some(repo)) = (archive_tag, self.backend.repo()) {
+            archive::remove(repo, &tag)?;
+            self.summary.archive_tags.retain(|name| name != &tag);
+        }
+        self.summary.deleted.retain(|name| name != &deleted.name);
+        self.record(&deleted.name, Decision::Keep);
+        self.summary.kept.push(restored.name);
+        Ok(())
+    }
+
+    /// Asks what to do about a new branch at `tip` having taken the name of
+    /// `deleted`: the branch to restore in its place or under another name,
+    /// `None` to leave it deleted.
+    fn retaken(&mut self, deleted: &Branch, tip: Oid) -> Result<Option<Branch>> {
+        let suffix = format!("-restored-{:.7}", deleted.id);
+        let mut renamed = deleted.clone();
+        renamed.name.push_str(&suffix);
+        renamed.name_bytes.extend_from_slice(suffix.as_bytes());
+
+        loop {
+            write!(
+                self.stdout,
+                "'{}' is a new branch now, at {:.7} (o/r/s/?) > ",
+                deleted.name, tip
+            )?;
+            self.stdout.flush()?;
+            let c = match self.input.next_key()? {
+                Key::Char(c) => c,
+                Key::Interrupt => 's',
+            };
+            write!(self.stdout, "{}\r\n", c)?;
+
+            match c {
+                'o' => return Ok(Some(deleted.clone())),
+                'r' => return Ok(Some(renamed)),
+                's' => return Ok(None),
+                _ => {
+                    write!(
+                        self.stdout,
+                        "o - Overwrite the new '{}' with the deleted one\r\n",
+                        deleted.name
+                    )?;
+                    write!(
+                        self.stdout,
+                        "r - Restore the deleted one as '{}'\r\n",
+                        renamed.name
+                    )?;
+                    write!(self.stdout, "s - Skip, leaving it deleted\r\n")?;
+                }
+            }
+        }
+    }
+
+    /// Prunes remote-tracking refs once the branches have been dealt with.
+    /// Without `always` this asks first, and only if a deleted branch had an
+    /// upstream, since that is when stale refs are most likely.
+    pub fn prune_remotes(&mut self, always: bool) -> Result<()> {
+        if self.backend.repo().is_none() {
+            return Ok(());
+        }
+
+        if !always
+            && (self.summary.deleted_with_upstream == 0
+                || !self.confirm("Prune stale remote-tracking refs?")?)
+        {
+            return Ok(());
+        }
+
+        let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+
+        for remote in repo.remotes()?.iter().flatten() {
+            match remote::prune(repo, &self.config, remote) {
+                Ok(pruned) => {
+                    for refname in &pruned {
+                        write!(self.stdout, "Pruned {}\r\n", refname)?;
+                    }
+                    self.summary.pruned.extend(pruned);
+                }
+                Err(e) => write!(self.stdout, "Could not prune '{}': {}\r\n", remote, e)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `--clean-archive`: offers to delete every tag under the archive prefix.
+    pub fn clean_archive(&mut self) -> Result<()> {
+        let prefix = self
+            .cli
+            .archive_tags
+            .as_deref()
+            .unwrap_or(crate::cli::DEFAULT_ARCHIVE_PREFIX);
+        let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+
+        let tags = archive::list(repo, prefix)?;
+        if tags.is_empty() {
+            write!(self.stdout, "No tags under '{}'.\r\n", prefix)?;
+            return Ok(());
+        }
+
+        for tag in &tags {
+            write!(self.stdout, "  {}\r\n", tag)?;
+        }
+
+        if self.confirm(&format!("Delete these {} tag(s)?", tags.len()))? {
+            let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+            for tag in &tags {
+                archive::remove(repo, tag)?;
+            }
+            write!(self.stdout, "Deleted {} tag(s).\r\n", tags.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Flags branches whose names only differ in case, which collide in ref
+    /// storage on case-insensitive filesystems, and offers to delete one of
+    /// each group up front. Returns the branches left for the session.
+    pub fn resolve_case_clashes(&mut self, mut branches: Vec<Branch>) -> Result<Vec<Branch>> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for branch in &branches {
+            groups
+                .entry(branch.name.to_lowercase())
+                .or_default()
+                .push(branch.name.clone());
+        }
+
+        for branch in &mut branches {
+            branch.case_clashes = groups[&branch.name.to_lowercase()]
+                .iter()
+                .filter(|name| **name != branch.name)
+                .cloned()
+                .collect();
+        }
+
+        let mut clashes: Vec<_> = groups
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .collect();
+        clashes.sort();
+
+        for names in clashes {
+            write!(
+                self.stdout,
+                "Warning: these branches differ only in case and collide on case-insensitive filesystems:\r\n"
+            )?;
+            for (i, name) in names.iter().enumerate() {
+                write!(self.stdout, "  {} - {}\r\n", i + 1, name)?;
+            }
+            write!(
+                self.stdout,
+                "Delete which one? (1-{}, or s to skip) > ",
+                names.len()
+            )?;
+            self.stdout.flush()?;
+
+            let answer = match self.input.next_key()? {
+                Key::Char(c) => c,
+                Key::Interrupt => return Ok(branches),
+            };
+            write!(self.stdout, "{}\r\n", answer)?;
+
+            let chosen = answer
+                .to_digit(10)
+                .and_then(|n| names.get((n as usize).checked_sub(1)?));
+            if let Some(name) = chosen {
+                if let Some(i) = branches.iter().position(|branch| &branch.name == name) {
+                    let branch = branches.remove(i);
+                    if let Some(reason) = self.ignore_reason(&branch) {
+                        write!(self.stdout, "Can't delete '{}', {}\r\n", name, reason)?;
+                        branches.insert(i, branch);
+                        continue;
+                    }
+                    if !self.force_confirmed(&branch)? {
+                        branches.insert(i, branch);
+                        continue;
+                    }
+                    self.delete(branch)?;
+                }
+                for branch in &mut branches {
+                    branch.case_clashes.retain(|other| other != name);
+                }
+            }
+        }
+
+        Ok(branches)
+    }
+
+    /// Keeps a branch that was never pushed, pushing it and setting its
+    /// upstream on the way.
+    fn publish(&mut self, branch: Branch) -> Result<Flow> {
+        if let Some(upstream) = &branch.upstream {
+            write!(
+                self.stdout,
+                "'{}' already tracks '{}/{}'\r\n",
+                branch.name,
+                upstream.remote,
+                upstream.branch_name()
+            )?;
+            return self.act_on_branch(branch);
+        }
+
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => {
+                write!(self.stdout, "Branches can't be pushed in fixture mode\r\n")?;
+                return self.act_on_branch(branch);
+            }
+        };
+
+        let remote = match remote::default_push_remote(repo, &self.config) {
+            Some(remote) => remote,
+            None => {
+                write!(
+                    self.stdout,
+                    "No remote to push to, set remote.pushDefault\r\n"
+                )?;
+                return self.act_on_branch(branch);
+            }
+        };
+
+        match remote::publish(repo, &self.config, &branch.name, &remote) {
+            Ok(_) => {
+                write!(
+                    self.stdout,
+                    "Pushed '{}' to '{}' and set it as upstream\r\n",
+                    branch.name, remote
+                )?;
+                self.record(&branch.name, Decision::Keep);
+                self.summary.kept.push(branch.name.clone());
+                self.summary.published.push(branch.name);
+                Ok(Flow::Continue)
+            }
+            Err(e) => {
+                write!(self.stdout, "Could not push '{}': {}\r\n", branch.name, e)?;
+                self.act_on_branch(branch)
+            }
+        }
+    }
+
+    /// Keeps the branch and sets `branch.<name>.keep` in the repository's
+    /// config so later sessions leave it alone.
+    fn keep_forever(&mut self, branch: Branch) -> Result<Flow> {
+        let repo = match self.backend.repo() {
+            Some(repo) if !branch.has_lossy_name() => repo,
+            Some(_) => {
+                write!(
+                    self.stdout,
+                    "Branches named in invalid UTF-8 can't have config\r\n"
+                )?;
+                return self.act_on_branch(branch);
+            }
+            None => {
+                write!(self.stdout, "Config can't be set in fixture mode\r\n")?;
+                return self.act_on_branch(branch);
+            }
+        };
+
+        let key = format!("branch.{}.keep", branch.name);
+        repo.config()?
+            .open_level(git2::ConfigLevel::Local)?
+            .set_bool(&key, true)?;
+        write!(
+            self.stdout,
+            "Keeping '{}' from now on, unset {} to be offered it again\r\n",
+            branch.name, key
+        )?;
+        self.keep(branch)?;
+        Ok(Flow::Continue)
+    }
+
+    /// Asks for a new name, renames the branch and offers it again under
+    /// that name.
+    fn rename(&mut self, mut branch: Branch) -> Result<Flow> {
+        write!(
+            self.stdout,
+            "New name for '{}' (Esc to cancel) > ",
+            branch.name
+        )?;
+        self.stdout.flush()?;
+
+        let new_name = match self.input.read_line(&mut self.stdout)? {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => return self.act_on_branch(branch),
+        };
+
+        match self.backend.rename(&branch, &new_name) {
+            Ok(()) => {
+                write!(
+                    self.stdout,
+                    "Renamed '{}' to '{}'\r\n",
+                    branch.name, new_name
+                )?;
+                self.summary
+                    .renamed
+                    .push(format!("{} -> {}", branch.name, new_name));
+                branch.name_bytes = new_name.as_bytes().to_vec();
+                branch.name = new_name;
+            }
+            Err(e) => write!(self.stdout, "Could not rename '{}': {}\r\n", branch.name, e)?,
+        }
+
+        self.act_on_branch(branch)
+    }
+
+    /// Switches to the branch, refusing to clobber local changes unless the
+    /// user agrees to stash them. The branch is kept either way.
+    fn checkout(&mut self, branch: Branch) -> Result<Flow> {
+        if self.head.is_bare() {
+            write!(
+                self.stdout,
+                "A bare repository has nothing to check out in\r\n"
+            )?;
+            return self.act_on_branch(branch);
+        }
+        if let Head::Detached {
+            interrupted: Some((name, what)),
+            ..
+        } = &self.head
+        {
+            write!(
+                self.stdout,
+                "Not checking out '{}' while '{}' is {}\r\n",
+                branch.name, name, what
+            )?;
+            return self.act_on_branch(branch);
+        }
+        let stash = self.backend.has_local_changes()?;
+        if stash
+            && !self
+                .confirm("The working tree has uncommitted changes, stash them and check out?")?
+        {
+            write!(self.stdout, "Not checking out '{}'\r\n", branch.name)?;
+            return self.act_on_branch(branch);
+        }
+
+        match self.backend.checkout(&branch, stash) {
+            Ok(()) => {
+                write!(
+                    self.stdout,
+                    "Switched to branch '{}'{}\r\n",
+                    branch.name,
+                    if stash {
+                        ", local changes were stashed"
+                    } else {
+                        ""
+                    }
+                )?;
+                self.checked_out = Some(branch.name.clone());
+                self.record(&branch.name, Decision::Keep);
+                self.summary.kept.push(branch.name);
+                Ok(Flow::Continue)
+            }
+            Err(e) => {
+                write!(
+                    self.stdout,
+                    "Could not check out '{}': {}\r\n",
+                    branch.name, e
+                )?;
+                self.act_on_branch(branch)
+            }
+        }
+    }
+
+    /// Switches to the base branch if `branch` is the current one, so it can
+    /// be deleted. Returns whether it is out of the way.
+    fn leave_branch(&mut self, branch: &Branch) -> Result<bool> {
+        if !self.is_current(branch) {
+            return Ok(true);
+        }
+
+        let stash = self.backend.has_local_changes()?;
+        if stash
+            && !self.confirm(&format!(
+                "The working tree has uncommitted changes, stash them and switch to '{}'?",
+                backend::BASE_BRANCH
+            ))?
+        {
+            write!(self.stdout, "Not deleting '{}'\r\n", branch.name)?;
+            return Ok(false);
+        }
+
+        match self.backend.checkout_base(stash) {
+            Ok(()) => {
+                write!(
+                    self.stdout,
+                    "Switched to branch '{}'{}\r\n",
+                    backend::BASE_BRANCH,
+                    if stash {
+                        ", local changes were stashed"
+                    } else {
+                        ""
+                    }
+                )?;
+                self.checked_out = Some(backend::BASE_BRANCH.to_string());
+                Ok(true)
+            }
+            Err(e) => {
+                write!(
+                    self.stdout,
+                    "Could not switch to '{}': {}\r\n",
+                    backend::BASE_BRANCH,
+                    e
+                )?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Deletes the branch's remote counterpart, then the branch itself.
+    pub fn delete_with_upstream(&mut self, branch: Branch) -> Result<Flow> {
+        if !self.seatbelt_allows(&branch)? {
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+        if self.marks.is_some() && branch.remote_target().is_some() {
+            return self.mark(branch, Decision::DeleteWithUpstream);
+        }
+
+        // Before anything moves HEAD out of the way, which `delete_as` does
+        // once it is sure the branch goes.
+        match (branch.remote_target(), self.backend.repo()) {
+            (None, _) => {
+                write!(
+                    self.stdout,
+                    "'{}' has no remote branch to delete\r\n",
+                    branch.name
+                )?;
+                return self.not_deleted(branch);
+            }
+            (Some(upstream), _) if upstream.gone => {
+                write!(
+                    self.stdout,
+                    "'{}' is already gone on '{}'\r\n",
+                    upstream.branch_name(),
+                    upstream.remote
+                )?;
+            }
+            (Some(_), None) => {
+                write!(
+                    self.stdout,
+                    "Remote branches can't be deleted in fixture mode\r\n"
+                )?;
+                return self.not_deleted(branch);
+            }
+            (Some(_), Some(_)) => {}
+        }
+
+        self.delete_as(branch, Decision::DeleteWithUpstream)
+    }
+
+    /// Deletes the remote branch `D` deletes along with `branch`, unless it
+    /// is gone already. `false` if that failed.
+    fn delete_upstream(&mut self, branch: &Branch) -> Result<bool> {
+        let upstream = match branch.remote_target() {
+            Some(upstream) => upstream,
+            None => return Ok(true),
+        };
+        if let (false, Some(repo)) = (upstream.gone, self.backend.repo()) {
+            match remote::delete_upstream(repo, &self.config, upstream) {
+                Ok(transport) => {
+                    write!(
+                        self.stdout,
+                        "{} {}\r\n",
+                        self.theme.paint(
+                            Role::Deleted,
+                            format!(
+                                "Deleted '{}' on '{}'",
+                                upstream.branch_name(),
+                                upstream.remote
+                            )
+                        ),
+                        match transport {
+                            Transport::Push => "by pushing",
+                            Transport::Api => "through the forge API",
+                        }
+                    )?;
+                }
+                Err(e) => {
+                    write!(
+                        self.stdout,
+                        "Could not delete '{}' on '{}': {}\r\n",
+                        upstream.branch_name(),
+                        upstream.remote,
+                        e
+                    )?;
+                    self.summary.failed.push(branch.name.clone());
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.summary.deleted_upstreams.push(format!(
+            "{}/{}",
+            upstream.remote,
+            upstream.branch_name()
+        ));
+        Ok(true)
+    }
+
+    /// Prints what each factor contributes to the branch's staleness score.
+    fn explain(&mut self, branch: &Branch) -> Result<()> {
+        if let Some(description) = &branch.description {
+            for line in description.lines() {
+                write!(self.stdout, "  | {}\r\n", line)?;
+            }
+        }
+        if let Some(repo) = self.backend.repo() {
+            let commit = repo.find_commit(branch.id)?;
+            let decoder = Decoder::new(repo);
+            write!(
+                self.stdout,
+                "  Last commit \"{}\" by {}\r\n",
+                decoder.subject(&commit),
+                decoder.author_name(&commit)
+            )?;
+        }
+        if let Some((pattern, limit, age)) = self.retention.policy(branch) {
+            let (kept, this) = match limit {
+                Limit::Days(_) => (
+                    format!("for {}", age),
+                    format!("is {} day(s) old", retention::age_days(branch)),
+                ),
+                Limit::Never => (
+                    "forever".to_string(),
+                    format!("is {} day(s) old", retention::age_days(branch)),
+                ),
+                Limit::Newest(count) => (
+                    format!("while among the newest {}", count),
+                    format!(
+                        "has {} newer one(s)",
+                        self.retention.newer(branch).unwrap_or_default()
+                    ),
+                ),
+            };
+            write!(
+                self.stdout,
+                "  '{}' branches are kept {}, this one {}{}\r\n",
+                pattern,
+                kept,
+                this,
+                if self.retention.expired(branch) == Some(true) {
+                    ", past that, so it is stale whatever its score"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        if let Some(size) = &branch.size {
+            write!(
+                self.stdout,
+                "  Adds {} over {}\r\n",
+                size,
+                backend::BASE_BRANCH
+            )?;
+        }
+        let contributions = self.weights.contributions(branch);
+        for contribution in &contributions {
+            write!(
+                self.stdout,
+                "  {:<7} {:>+7.1}  {}\r\n",
+                contribution.factor, contribution.points, contribution.detail
+            )?;
+        }
+        let score = self.weights.score(branch);
+        write!(
+            self.stdout,
+            "  {:<7} {:>7.1}  {} the stale threshold of {}\r\n",
+            "total",
+            score,
+            if score >= self.weights.threshold() {
+                "at or above"
+            } else {
+                "below"
+            },
+            self.weights.threshold()
+        )?;
+        Ok(())
+    }
+
+    /// Asks a yes/no question, treating anything but `y` as no.
+    pub fn confirm(&mut self, question: &str) -> Result<bool> {
+        write!(self.stdout, "{} (y/n) > ", question)?;
+        self.stdout.flush()?;
+
+        let answer = match self.input.next_key()? {
+            Key::Char(c) => c,
+            Key::Interrupt => 'n',
+        };
+        write!(self.stdout, "{}\r\n", answer)?;
+
+        Ok(answer == 'y')
+    }
+
+    pub fn delete(&mut self, branch: Branch) -> Result<Flow> {
+        self.delete_as(branch, Decision::Delete)
+    }
+
+    /// Deletes the branch, which `--record` writes out as `decision`.
+    fn delete_as(&mut self, branch: Branch, decision: Decision) -> Result<Flow> {
+        if !self.seatbelt_allows(&branch)? {
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+        if self.marks.is_some() {
+            return self.mark(branch, decision);
+        }
+
+        if let Some(pr) = &branch.pull_request {
+            if pr.state == PrState::Open
+                && !self.confirm(&format!("'{}' has {}, delete anyway?", branch.name, pr))?
+            {
+                return self.not_deleted(branch);
+            }
+        }
+
+        if !self.references_confirmed(&branch)? || !self.leave_branch(&branch)? {
+            return self.not_deleted(branch);
+        }
+        let companions = self.companions_confirmed(&branch)?;
+        if !self.pre_delete_hook(&branch)? || !self.back_up(&branch)? {
+            self.summary.failed.push(branch.name.clone());
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+
+        let archive_tag = match (&self.cli.archive_tags, self.backend.repo()) {
+            (Some(prefix), Some(repo)) => {
+                let tag = archive::tag(repo, prefix, &branch, self.cli.archive_annotate)?;
+                write!(
+                    self.stdout,
+                    "Tagged tip of '{}' as '{}'\r\n",
+                    branch.name, tag
+                )?;
+                self.summary.archive_tags.push(tag.clone());
+                Some(tag)
+            }
+            _ => None,
+        };
+
+        let tombstone = match (&self.cli.tombstones, self.backend.repo()) {
+            (Some(mode), Some(_)) => {
+                let reason = if *mode == TombstoneMode::AskReason {
+                    write!(
+                        self.stdout,
+                        "Reason for deleting '{}' (optional) > ",
+                        branch.name
+                    )?;
+                    self.stdout.flush()?;
+                    self.input
+                        .read_line(&mut self.stdout)?
+                        .filter(|reason| !reason.trim().is_empty())
+                } else {
+                    None
+                };
+                let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+                Some(Tombstone::write(repo, &branch, reason.as_deref())?)
+            }
+            _ => None,
+        };
+
+        let branch_config = match self.backend.repo() {
+            Some(repo) if !branch.has_lossy_name() => BranchConfig::read(repo, &branch.name)?,
+            _ => BranchConfig::default(),
+        };
+        // Only once everything above agreed, the remote branch can't be
+        // restored from here, and deleting it closes its pull request.
+        if decision == Decision::DeleteWithUpstream && !self.delete_upstream(&branch)? {
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+        if let Err(e) = self.backend.delete(&branch) {
+            let failed = format!("Could not delete '{}': {}", branch.name, e);
+            write!(
+                self.stdout,
+                "{}\r\n",
+                self.theme.paint(Role::Warning, failed)
+            )?;
+            self.summary.failed.push(branch.name.clone());
+            self.keep(branch)?;
+            return Ok(Flow::Continue);
+        }
+        let sequence = run::next();
+        self.events.branch_deleted(&branch, sequence);
+        if let Some(repo) = self.backend.repo() {
+            audit::append(repo, sequence, "deleted", &branch.name, branch.id)?;
+        }
+        self.post_delete_hook(&branch)?;
+        if branch.remote_target().is_some() {
+            self.summary.deleted_with_upstream += 1;
+        }
+        if !companions.is_empty() {
+            let repo = self.backend.repo().ok_or(Error::NeedsRepository)?;
+            companion::delete(repo, &companions)?;
+            let names: Vec<String> = companions
+                .iter()
+                .map(|companion| companion.name.clone())
+                .collect();
+            write!(
+                self.stdout,
+                "{}\r\n",
+                self.theme.paint(
+                    Role::Deleted,
+                    format!("Deleted companion ref(s) {}", names.join(", "))
+                )
+            )?;
+            self.summary.companions.extend(names);
+        }
+
+        // With `--mark` or the seatbelt deletions happen after the last
+        // prompt, and there is none once a script or `a` decides.
+        if self.deferred || self.script.is_some() || self.rest.is_some() {
+            let deleted = format!("Deleted branch '{}'", branch.name);
+            write!(
+                self.stdout,
+                "{}\r\n",
+                self.theme.paint(Role::Deleted, deleted)
+            )?;
+        } else {
+            write!(
+                self.stdout,
+                "{}, to undo select '{}'\r\n",
+                self.theme
+                    .paint(Role::Deleted, format!("Deleted branch '{}'", branch.name)),
+                self.keymap.key(BranchAction::Undo).unwrap_or('?')
+            )?;
+        }
+        self.record(&branch.name, decision);
+        self.stdout.deleted(&branch.name)?;
+        self.summary.deleted.push(branch.name.clone());
+        self.deletions.push(Deletion {
+            branch,
+            archive_tag,
+            tombstone,
+            branch_config,
+            companions,
+        });
+
+        Ok(Flow::Continue)
+    }
+}
+
+fn get_branch_action_from_user(
+    stdout: &mut Output,
+    input: &mut Input,
+    keymap: &Keymap,
+    theme: &Theme,
+    branch: &Branch,
+    stale: bool,
+    unavailable: &[(BranchAction, String)],
+) -> Result<BranchAction> {
+    let badge = |role, text: &str| format!(" {}", theme.paint(role, text));
+
+    let info = format!(
+        "'{}'{}{}{} ({}) last commit {}{}{}{}{}{}{}{}{}{}{}{}{}",
+        theme.paint(Role::Branch, &branch.name),
+        match &branch.description {
+            Some(description) => format!(
+                " \"{}\"",
+                description.lines().next().unwrap_or_default().trim()
+            ),
+            None => String::new(),
+        },
+        if branch.has_lossy_name() {
+            badge(Role::Warning, "[invalid UTF-8]")
+        } else {
+            String::new()
+        },
+        if branch.case_clashes.is_empty() {
+            String::new()
+        } else {
+            badge(
+                Role::Warning,
+                &format!("[CASE CLASH with '{}']", branch.case_clashes.join("', '")),
+            )
+        },
+        branch.short_id(),
+        theme.time(branch.time),
+        if branch.merged == Some(true) {
+            badge(Role::Merged, "[merged]")
+        } else if branch.squash_merged {
+            badge(Role::Merged, "[squash-merged]")
+        } else {
+            String::new()
+        },
+        match (branch.ahead, branch.behind) {
+            (Some(ahead), Some(behind)) if ahead > 0 || behind > 0 => badge(
+                Role::Badge,
+                &format!("[↑{} ↓{} vs {}]", ahead, behind, backend::BASE_BRANCH),
+            ),
+            _ => String::new(),
+        },
+        match (&branch.upstream, branch.upstream_ahead_behind) {
+            (Some(upstream), Some((ahead, behind))) if ahead > 0 || behind > 0 => badge(
+                Role::Badge,
+                &format!(
+                    "[↑{} ↓{} vs {}/{}]",
+                    ahead,
+                    behind,
+                    upstream.remote,
+                    upstream.branch_name()
+                ),
+            ),
+            (None, _) if branch.orphaned => badge(Role::Badge, "[never pushed]"),
+            _ => String::new(),
+        },
+        match &branch.size {
+            Some(size) if size.commits > 0 => badge(Role::Badge, &format!("[{}]", size)),
+            _ => String::new(),
+        },
+        if branch.owners.is_empty() {
+            String::new()
+        } else {
+            badge(
+                Role::Badge,
+                &format!("[owners: {}]", branch.owners.join(", ")),
+            )
+        },
+        match &branch.push {
+            Some(push) => badge(
+                Role::Badge,
+                &format!("[push: {}/{}]", push.remote, push.branch_name()),
+            ),
+            None => String::new(),
+        },
+        match &branch.pull_request {
+            Some(pr) => badge(Role::Badge, &format!("[{}]", pr)),
+            None => String::new(),
+        },
+        match branch.checks {
+            // Passing work the base branch doesn't have is worth a look.
+            Some(Ci::Green) if branch.merged != Some(true) && !branch.squash_merged => {
+                badge(Role::Warning, "[CI green, unmerged]")
+            }
+            Some(ci) => badge(Role::Badge, &format!("[CI {}]", ci.name())),
+            None => String::new(),
+        },
+        if branch.protected {
+            badge(Role::Warning, "[protected]")
+        } else {
+            String::new()
+        },
+        if branch.stashes.is_empty() {
+            String::new()
+        } else {
+            badge(
+                Role::Warning,
+                &format!("[stashed: {}]", branch.stashes.join(", ")),
+            )
+        },
+        if branch.wip_commit {
+            badge(Role::Warning, "[WIP]")
+        } else {
+            String::new()
+        },
+        if stale {
+            badge(Role::Warning, "[stale]")
+        } else {
+            String::new()
+        },
+    );
+    // Cut to fit on one line, keys and all, with a column left for the key
+    // pressed: a prompt wrapping in raw mode leaves a mess behind.
+    let keys = format!(" ({}) > ", keymap.prompt());
+    let draw = |stdout: &mut Output, width: Option<usize>| -> Result<()> {
+        let info = match width {
+            Some(width) => output::truncate(&info, width.saturating_sub(keys.chars().count() + 1)),
+            None => info.clone(),
+        };
+        write!(stdout, "{}{}", info, keys)?;
+        stdout.flush()?;
+        Ok(())
+    };
+    let width = stdout.width();
+    draw(stdout, width)?;
+
+    let c = match input.next_key_resizing(|width| {
+        write!(stdout, "\r")?;
+        crossterm::execute!(stdout, Clear(ClearType::CurrentLine))?;
+        draw(stdout, Some(width))
+    })? {
+        Key::Char(c) => c,
+        Key::Interrupt => {
+            write!(stdout, "^C\r\n")?;
+            return Ok(BranchAction::Quit);
+        }
+    };
+
+    write!(stdout, "{}\r\n", c)?;
+
+    if c == '?' {
+        if let Key::Interrupt = show_help(stdout, input, &keymap.help(unavailable))? {
+            write!(stdout, "^C\r\n")?;
+            return Ok(BranchAction::Quit);
+        }
+        get_branch_action_from_user(stdout, input, keymap, theme, branch, stale, unavailable)
+    } else if let Some(action) = keymap.action(c) {
+        Ok(action)
+    } else {
+        write!(stdout, "'{}' isn't bound, ? for help\r\n", c)?;
+        get_branch_action_from_user(stdout, input, keymap, theme, branch, stale, unavailable)
+    }
+}
+
+/// Shows the help in a box under the prompt until a key is pressed, then
+/// clears both for the prompt to be drawn again in their place. Piped
+/// output, or a terminal too short for the box, gets the help printed
+/// instead and no key is waited for.
+fn show_help(stdout: &mut Output, input: &mut Input, lines: &[String]) -> Result<Key> {
+    // The line with the `?` and the box's top and bottom.
+    let height = lines.len() + 3;
+    let width = match (stdout.width(), stdout.height()) {
+        (Some(width), Some(rows)) if height <= rows && width > 20 => width,
+        _ => {
+            write!(stdout, "Here are what the commands mean\r\n")?;
+            for line in lines {
+                write!(stdout, "{}\r\n", line)?;
+            }
+            stdout.flush()?;
+            return Ok(Key::Char('?'));
+        }
+    };
+
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| output::truncate(line, width - 4))
+        .collect();
+    let inner = lines
+        .iter()
+        .map(|line| output::columns(line))
+        .max()
+        .unwrap_or(0);
+    let border = |left: char, title: &str, right: char| {
+        let rule = "─".repeat((inner + 2).saturating_sub(title.chars().count() + 1));
+        format!("{}─{}{}{}\r\n", left, title, rule, right)
+    };
+    write!(stdout, "{}", border('┌', " Keys ", '┐'))?;
+    for line in &lines {
+        let padding = " ".repeat(inner - output::columns(line));
+        write!(stdout, "│ {}{} │\r\n", line, padding)?;
+    }
+    write!(stdout, "{}", border('└', " Any key closes this ", '┘'))?;
+    stdout.flush()?;
+
+    let key = input.next_key()?;
+    // The last line of the box left the cursor at the start of a line.
+    crossterm::execute!(
+        stdout,
+        MoveUp(height as u16),
+        Clear(ClearType::FromCursorDown)
+    )?;
+    Ok(key)
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub deleted: Vec<String>,
+    pub kept: Vec<String>,
+    /// Branches left for another time.
+    pub snoozed: Vec<String>,
+    /// `remote/branch` of every upstream deleted along with its branch.
+    pub deleted_upstreams: Vec<String>,
+    /// `old -> new` for every rename.
+    pub renamed: Vec<String>,
+    /// Kept branches that were pushed and given an upstream.
+    pub published: Vec<String>,
+    /// Deleted branches that had an upstream configured.
+    pub deleted_with_upstream: usize,
+    /// Tags created by `--archive-tags`.
+    pub archive_tags: Vec<String>,
+    /// Companion refs deleted along with their branches.
+    pub companions: Vec<String>,
+    /// Remote-tracking refs removed by pruning.
+    pub pruned: Vec<String>,
+    /// Branches whose deletion, or their upstream's, didn't go through.
+    pub failed: Vec<String>,
+    pub worktrees: Vec<Worktree>,
+}
+
+impl Summary {
+    /// Adds a repository's summary to a combined one, naming its branches
+    /// `repository:branch`.
+    pub fn absorb(&mut self, other: Summary, repository: &str) {
+        let named = |names: Vec<String>| {
+            names
+                .into_iter()
+                .map(move |name| format!("{}:{}", repository, name))
+        };
+        self.deleted.extend(named(other.deleted));
+        self.kept.extend(named(other.kept));
+        self.snoozed.extend(named(other.snoozed));
+        self.deleted_upstreams
+            .extend(named(other.deleted_upstreams));
+        self.renamed.extend(named(other.renamed));
+        self.published.extend(named(other.published));
+        self.deleted_with_upstream += other.deleted_with_upstream;
+        self.archive_tags.extend(named(other.archive_tags));
+        self.companions.extend(named(other.companions));
+        self.pruned.extend(named(other.pruned));
+        self.failed.extend(named(other.failed));
+        self.worktrees.extend(other.worktrees);
+    }
+
+    /// Branches whose deletion didn't go through and weren't deleted after
+    /// all.
+    pub fn failures(&self) -> Vec<&str> {
+        let mut failed: Vec<&str> = Vec::new();
+        for name in &self.failed {
+            if !self.deleted.contains(name) && !failed.contains(&name.as_str()) {
+                failed.push(name);
+            }
+        }
+        failed
+    }
+
+    pub fn print(&self, stdout: &mut impl Write) -> Result<()> {
+        write!(stdout, "\r\nSummary\r\n")?;
+        write!(
+            stdout,
+            "Deleted {} branch(es): {}\r\n",
+            self.deleted.len(),
+            self.deleted.join(", ")
+        )?;
+        write!(
+            stdout,
+            "Kept {} branch(es): {}\r\n",
+            self.kept.len(),
+            self.kept.join(", ")
+        )?;
+
+        if !self.snoozed.is_empty() {
+            write!(
+                stdout,
+                "Snoozed {} branch(es): {}\r\n",
+                self.snoozed.len(),
+                self.snoozed.join(", ")
+            )?;
+        }
+
+        if !self.renamed.is_empty() {
+            write!(
+                stdout,
+                "Renamed {} branch(es): {}\r\n",
+                self.renamed.len(),
+                self.renamed.join(", ")
+            )?;
+        }
+
+        if !self.published.is_empty() {
+            write!(
+                stdout,
+                "Pushed {} branch(es): {}\r\n",
+                self.published.len(),
+                self.published.join(", ")
+            )?;
+        }
+
+        if !self.deleted_upstreams.is_empty() {
+            write!(
+                stdout,
+                "Deleted {} remote branch(es): {}\r\n",
+                self.deleted_upstreams.len(),
+                self.deleted_upstreams.join(", ")
+            )?;
+        }
+
+        if !self.archive_tags.is_empty() {
+            write!(
+                stdout,
+                "Archived {} branch tip(s) as: {}\r\n",
+                self.archive_tags.len(),
+                self.archive_tags.join(", ")
+            )?;
+        }
+
+        if !self.companions.is_empty() {
+            write!(
+                stdout,
+                "Deleted {} companion ref(s): {}\r\n",
+                self.companions.len(),
+                self.companions.join(", ")
+            )?;
+        }
+
+        if !self.pruned.is_empty() {
+            write!(
+                stdout,
+                "Pruned {} remote-tracking ref(s): {}\r\n",
+                self.pruned.len(),
+                self.pruned.join(", ")
+            )?;
+        }
+
+        let failed = self.failures();
+        if !failed.is_empty() {
+            write!(
+                stdout,
+                "Could not delete {} branch(es): {}\r\n",
+                failed.len(),
+                failed.join(", ")
+            )?;
+        }
+
+        if !self.worktrees.is_empty() {
+            write!(stdout, "Worktrees:\r\n")?;
+            for worktree in &self.worktrees {
+                write!(stdout, "  {} ({})", worktree.path.display(), worktree.name)?;
+                match &worktree.branch {
+                    _ if worktree.prunable => write!(stdout, " missing and prunable")?,
+                    Some(branch) => write!(stdout, " on '{}'", branch)?,
+                    None => write!(stdout, " detached")?,
+                }
+                write!(stdout, "\r\n")?;
+            }
+        }
+
+        write!(stdout, "Run {}\r\n", run::id())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+pub enum Flow {
+    Continue,
+    Quit,
+    /// Leave the branches not yet decided on and go on to the review.
+    Review,
+}
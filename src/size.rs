@@ -0,0 +1,252 @@
+//! `--sizes`: roughly how much each branch adds to the repository, so the
+//! branches worth deleting for disk space stand out.
+//!
+//! A branch's size is its commits the base branch doesn't have, plus the
+//! trees and blobs those commits reach that neither the base branch's tip
+//! nor the branch's fork point has, counted uncompressed. Objects the base
+//! branch only had at some point in between are counted too, which is why
+//! it is an estimate. Walking trees is slow on big repositories, so results
+//! are kept in `.git/delete-branches-cache/size.json`, keyed by the branch
+//! tip and the base tip.
+
+use crate::backend::BASE_BRANCH;
+use crate::branch::Branch;
+use crate::json::Value;
+use crate::{state, Result};
+use git2::{BranchType, ObjectType, Odb, Oid, Repository, Tree};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+const FILE: &str = "size.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub commits: usize,
+    /// Commits, trees and blobs.
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} commit(s), ~{} object(s), ~{}",
+            self.commits,
+            self.objects,
+            human_bytes(self.bytes)
+        )
+    }
+}
+
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Measures branches against the base branch a batch at a time, remembering
+/// what it measured for the cache.
+pub struct Sizes<'r> {
+    repo: &'r Repository,
+    odb: Odb<'r>,
+    base: Oid,
+    path: PathBuf,
+    cached: HashMap<(Oid, Oid), Size>,
+    used: HashMap<(Oid, Oid), Size>,
+    /// Everything in the base branch's tip, collected the first time a
+    /// branch needs measuring.
+    known: Option<HashSet<Oid>>,
+}
+
+impl<'r> Sizes<'r> {
+    /// `None` when there is no base branch to measure against.
+    pub fn new(repo: &'r Repository) -> Result<Option<Sizes<'r>>> {
+        let base = match repo
+            .find_branch(BASE_BRANCH, BranchType::Local)
+            .ok()
+            .and_then(|branch| branch.get().target())
+        {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+
+        let path = state::cache_dir(repo).join(FILE);
+        Ok(Some(Sizes {
+            repo,
+            odb: repo.odb()?,
+            base,
+            cached: load(&path)?,
+            path,
+            used: HashMap::new(),
+            known: None,
+        }))
+    }
+
+    /// Fills in `size` for every branch.
+    pub fn annotate(&mut self, branches: &mut [Branch]) -> Result<()> {
+        let (repo, odb, base) = (self.repo, &self.odb, self.base);
+        for branch in branches.iter_mut() {
+            let key = (branch.id, base);
+            let size = match self.cached.remove(&key) {
+                Some(size) => size,
+                None => {
+                    let known = match &mut self.known {
+                        Some(known) => known,
+                        None => {
+                            let mut objects = HashSet::new();
+                            let tree = repo.find_commit(base)?.tree()?;
+                            collect(repo, odb, &tree, &HashSet::new(), &mut objects, &mut 0)?;
+                            self.known.insert(objects)
+                        }
+                    };
+                    measure(repo, odb, branch, base, known)?
+                }
+            };
+            branch.size = Some(size);
+            self.used.insert(key, size);
+        }
+        Ok(())
+    }
+
+    /// Writes out the sizes of this run's branches, including what is
+    /// cached for `pending`, those not measured before the run ended. The
+    /// rest are dropped.
+    pub fn save(mut self, pending: &[Branch]) -> Result<()> {
+        for branch in pending {
+            let key = (branch.id, self.base);
+            if let Some(size) = self.cached.remove(&key) {
+                self.used.insert(key, size);
+            }
+        }
+        save(&self.path, &self.used)
+    }
+}
+
+/// Counts what `branch` adds on top of `known`.
+fn measure(
+    repo: &Repository,
+    odb: &Odb,
+    branch: &Branch,
+    base: Oid,
+    known: &HashSet<Oid>,
+) -> Result<Size> {
+    // Objects of the fork point's tree the base tip no longer has aren't
+    // the branch's doing, so they are seen up front and not counted.
+    let mut seen = HashSet::new();
+    if let Some(fork_point) = branch.fork_point {
+        let tree = repo.find_commit(fork_point)?.tree()?;
+        collect(repo, odb, &tree, known, &mut seen, &mut 0)?;
+    }
+    let inherited = seen.len();
+
+    let mut size = Size {
+        commits: 0,
+        objects: 0,
+        bytes: 0,
+    };
+    let mut walk = repo.revwalk()?;
+    walk.push(branch.id)?;
+    walk.hide(base)?;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        size.commits += 1;
+        size.bytes += odb.read_header(commit.id())?.0 as u64;
+        collect(
+            repo,
+            odb,
+            &commit.tree()?,
+            known,
+            &mut seen,
+            &mut size.bytes,
+        )?;
+    }
+
+    size.objects = size.commits + seen.len() - inherited;
+    Ok(size)
+}
+
+/// Adds `tree` and every tree and blob under it to `objects`, and their
+/// sizes to `bytes`. Whatever is in `known` or already in `objects` is
+/// skipped, along with everything under such a tree.
+fn collect(
+    repo: &Repository,
+    odb: &Odb,
+    tree: &Tree,
+    known: &HashSet<Oid>,
+    objects: &mut HashSet<Oid>,
+    bytes: &mut u64,
+) -> Result<()> {
+    if known.contains(&tree.id()) || !objects.insert(tree.id()) {
+        return Ok(());
+    }
+    *bytes += odb.read_header(tree.id())?.0 as u64;
+
+    for entry in tree.iter() {
+        let id = entry.id();
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                let subtree = repo.find_tree(id)?;
+                collect(repo, odb, &subtree, known, objects, bytes)?;
+            }
+            Some(ObjectType::Blob) if !known.contains(&id) && objects.insert(id) => {
+                *bytes += odb.read_header(id)?.0 as u64;
+            }
+            // Submodule commits and blobs already counted.
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn load(path: &std::path::Path) -> Result<HashMap<(Oid, Oid), Size>> {
+    let json = match state::read(path)?.map(|contents| Value::parse(&contents)) {
+        Some(Ok(json)) => json,
+        _ => return Ok(HashMap::new()),
+    };
+
+    Ok(match json.get("branches") {
+        Some(Value::Object(branches)) => branches
+            .iter()
+            .filter_map(|(key, size)| {
+                let (tip, base) = key.split_once(':')?;
+                let key = (Oid::from_str(tip).ok()?, Oid::from_str(base).ok()?);
+                let size = Size {
+                    commits: size.get("commits")?.as_f64()? as usize,
+                    objects: size.get("objects")?.as_f64()? as usize,
+                    bytes: size.get("bytes")?.as_f64()? as u64,
+                };
+                Some((key, size))
+            })
+            .collect(),
+        _ => HashMap::new(),
+    })
+}
+
+/// Writes out the sizes of this run's branches, dropping the rest.
+fn save(path: &std::path::Path, sizes: &HashMap<(Oid, Oid), Size>) -> Result<()> {
+    let mut branches: Vec<_> = sizes
+        .iter()
+        .map(|((tip, base), size)| {
+            let size = Value::Object(vec![
+                ("commits".to_string(), size.commits.into()),
+                ("objects".to_string(), size.objects.into()),
+                ("bytes".to_string(), (size.bytes as i64).into()),
+            ]);
+            (format!("{}:{}", tip, base), size)
+        })
+        .collect();
+    branches.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    let json = Value::Object(vec![("branches".to_string(), Value::Object(branches))]);
+    state::write(path, &json.to_string())
+}
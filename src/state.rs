@@ -0,0 +1,82 @@
+//! Files kept between runs. Caches live under `.git/delete-branches-cache/`
+//! and are safe to delete at any time; whatever can't be recomputed, like
+//! the trash, lives under `.git/delete-branches/`. What belongs to whoever
+//! ran the session, like their trash, goes in `users/<user>/` there, so
+//! people taking turns on a shared clone don't write over each other's.
+
+use crate::Result;
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where caches go, shared by all worktrees of the repository.
+pub fn cache_dir(repo: &Repository) -> PathBuf {
+    common_dir(repo).join("delete-branches-cache")
+}
+
+/// Where state that matters goes, shared by all worktrees of the repository.
+pub fn dir(repo: &Repository) -> PathBuf {
+    common_dir(repo).join("delete-branches")
+}
+
+/// Where `user`'s own state goes.
+pub fn user_dir(repo: &Repository, user: &str) -> PathBuf {
+    dir(repo).join("users").join(user)
+}
+
+/// The OS user running the session, kept to what is safe in a file or ref
+/// name.
+pub fn user() -> String {
+    let name = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "unknown".to_string()
+    } else {
+        name
+    }
+}
+
+/// The repository's own `.git`, shared by all its worktrees.
+pub fn common_dir(repo: &Repository) -> &Path {
+    // Linked worktrees have their own `.git/worktrees/<name>` directory.
+    let git_dir = repo.path();
+    match git_dir.parent() {
+        Some(parent) if parent.file_name().is_some_and(|name| name == "worktrees") => {
+            parent.parent().unwrap_or(git_dir)
+        }
+        _ => git_dir,
+    }
+}
+
+/// Reads a state file, `None` if it doesn't exist yet.
+pub fn read(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replaces a state file, going through a temporary file so a concurrent
+/// reader never sees it half written.
+pub fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! `git delete-branches sync`: fetches forge data for every branch ahead of
+//! time, so that interactive sessions start with a warm cache. Meant to be
+//! run in the background (from cron or a post-fetch hook), it stays within
+//! the API rate limit by pausing until the quota resets.
+
+use crate::backend::Backend;
+use crate::config::Config;
+use crate::forge::{self, Forge, QuotaLimits};
+use crate::forge_cache::ForgeCache;
+use crate::{Error, Result};
+use std::time::Duration;
+
+/// Leave some of the quota for interactive sessions and other tools, and
+/// wait out a reset rather than fail.
+const LIMITS: QuotaLimits = QuotaLimits {
+    reserve: 100,
+    max_wait: Duration::from_secs(60 * 60),
+    degrade: false,
+};
+
+/// Fetches pull requests, and with `check_ci` how CI went, for every branch.
+pub fn run(backend: &dyn Backend, config: &Config, check_ci: bool) -> Result<()> {
+    let repo = backend.repo().ok_or(Error::NeedsRepository)?;
+    let mut forge = Forge::origin(repo, config)?;
+    forge.check_ci = check_ci;
+    let mut cache = ForgeCache::load(repo, &forge.project)?;
+
+    match cache.refresh_protected(&forge) {
+        Ok(patterns) => eprintln!("{} protected branch pattern(s)", patterns.len()),
+        Err(e) => eprintln!("Could not fetch protected branches: {}", e),
+    }
+
+    let mut branches = backend.branches()?;
+    let total = branches.len();
+    let fetched = forge::fetch_statuses(&forge, &mut cache, &mut branches, LIMITS, &|done| {
+        eprint!("\rSyncing {}/{} branches", done, total)
+    });
+    eprintln!();
+
+    // Whatever was fetched before a failure is still worth keeping.
+    cache.save()?;
+    fetched?;
+
+    match forge.rate_limit() {
+        Some(rate_limit) => eprintln!(
+            "Done, {} API request(s) left{}",
+            rate_limit.remaining,
+            rate_limit
+                .limit
+                .map_or(String::new(), |limit| format!(" of {}", limit))
+        ),
+        None => eprintln!("Done"),
+    }
+
+    Ok(())
+}
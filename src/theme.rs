@@ -0,0 +1,243 @@
+//! Colors for the session output.
+//!
+//! Color is on when stdout is a terminal and `NO_COLOR` isn't set, unless
+//! `--color always` or `--color never` says otherwise. Consoles older than
+//! Windows 10 don't take color escape sequences, so they go without. Each
+//! part of the output can be restyled with git's color syntax, a foreground and an
+//! optional background color followed by attributes:
+//!
+//! ```ini
+//! [delete-branches "theme"]
+//!     branch = cyan bold
+//!     old = brightred
+//!     merged = "#00aa00"
+//! ```
+
+use crate::branch;
+use crate::cli::{ColorChoice, DateFormat};
+use crate::config::Config;
+use crate::{Error, Result};
+use chrono::{DateTime, FixedOffset, Local};
+use crossterm::style::{Attribute, Color, ContentStyle};
+use crossterm::tty::IsTty;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    /// Branch names.
+    Branch,
+    /// Last commit dates more than a year ago.
+    Old,
+    /// Last commit dates more than three months ago.
+    Aging,
+    /// `[merged]` and `[squash-merged]`.
+    Merged,
+    /// Badges worth a second look before deleting: protection, stashes,
+    /// WIP tips, case clashes, staleness.
+    Warning,
+    /// Every other badge.
+    Badge,
+    /// Deletion confirmations.
+    Deleted,
+}
+
+impl Role {
+    const ALL: [Role; 7] = [
+        Role::Branch,
+        Role::Old,
+        Role::Aging,
+        Role::Merged,
+        Role::Warning,
+        Role::Badge,
+        Role::Deleted,
+    ];
+
+    fn config_name(self) -> &'static str {
+        match self {
+            Role::Branch => "branch",
+            Role::Old => "old",
+            Role::Aging => "aging",
+            Role::Merged => "merged",
+            Role::Warning => "warning",
+            Role::Badge => "badge",
+            Role::Deleted => "deleted",
+        }
+    }
+
+    fn default_style(self) -> &'static str {
+        match self {
+            Role::Branch => "bold",
+            Role::Old => "red",
+            Role::Aging => "yellow",
+            Role::Merged => "green",
+            Role::Warning => "magenta bold",
+            Role::Badge => "cyan",
+            Role::Deleted => "red",
+        }
+    }
+}
+
+pub struct Theme {
+    enabled: bool,
+    styles: Vec<(Role, ContentStyle)>,
+    date: DateFormat,
+}
+
+impl Theme {
+    /// The default styles with `[delete-branches "theme"]` applied.
+    pub fn load(config: &Config, choice: ColorChoice, date: DateFormat) -> Result<Theme> {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_tty()
+                    && supports_ansi()
+            }
+        };
+
+        let mut styles = Vec::new();
+        for role in Role::ALL {
+            let key = format!("delete-branches.theme.{}", role.config_name());
+            let style = match config.string(&key) {
+                Some(value) => parse_style(&value).ok_or(Error::InvalidValue(key, value))?,
+                None => parse_style(role.default_style()).unwrap_or_default(),
+            };
+            styles.push((role, style));
+        }
+
+        Ok(Theme {
+            enabled,
+            styles,
+            date,
+        })
+    }
+
+    /// `text` in the style of `role`, or as it is with color off.
+    pub fn paint(&self, role: Role, text: impl Display) -> String {
+        let style = self
+            .styles
+            .iter()
+            .find(|(bound, _)| *bound == role)
+            .map(|(_, style)| *style)
+            .unwrap_or_default();
+
+        if self.enabled && style != ContentStyle::default() {
+            style.apply(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// When the last commit was, to follow "last commit", in the `--date`
+    /// format and colored by how long ago it was.
+    pub fn time(&self, time: DateTime<FixedOffset>) -> String {
+        let text = match self.date {
+            DateFormat::Relative if branch::days_since(time) < 1 => "today".to_string(),
+            DateFormat::Relative => format!("{} ago", branch::age(time)),
+            DateFormat::Local => format!(
+                "at {}",
+                time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+            ),
+            DateFormat::Iso => format!("at {}", time.format("%Y-%m-%d %H:%M:%S %z")),
+            DateFormat::Raw => format!("at {} {}", time.timestamp(), time.format("%z")),
+        };
+        match branch::days_since(time) {
+            days if days > 365 => self.paint(Role::Old, text),
+            days if days > 90 => self.paint(Role::Aging, text),
+            _ => text,
+        }
+    }
+}
+
+/// Whether the console understands color escape sequences. Windows
+/// Terminal and the Windows 10 console do once asked to, which this does.
+#[cfg(windows)]
+fn supports_ansi() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+fn supports_ansi() -> bool {
+    true
+}
+
+/// Parses git's color syntax: up to two colors, foreground then background,
+/// and any number of attributes. `normal` skips a color slot.
+fn parse_style(value: &str) -> Option<ContentStyle> {
+    let mut style = ContentStyle::new();
+    let mut colors = 0;
+
+    for word in value.split_whitespace() {
+        let word = word.to_ascii_lowercase();
+        if let Some(attribute) = attribute(&word) {
+            style.attributes.set(attribute);
+            continue;
+        }
+
+        let color = match word.as_str() {
+            "normal" => None,
+            _ => Some(color(&word)?),
+        };
+        match colors {
+            0 => style.foreground_color = color,
+            1 => style.background_color = color,
+            _ => return None,
+        }
+        colors += 1;
+    }
+
+    Some(style)
+}
+
+fn attribute(word: &str) -> Option<Attribute> {
+    Some(match word {
+        "bold" => Attribute::Bold,
+        "dim" => Attribute::Dim,
+        "italic" => Attribute::Italic,
+        "ul" | "underline" => Attribute::Underlined,
+        "blink" => Attribute::SlowBlink,
+        "reverse" => Attribute::Reverse,
+        "strike" => Attribute::CrossedOut,
+        _ => return None,
+    })
+}
+
+/// git's color names map to the normal ANSI colors and their `bright`
+/// variants, which crossterm calls `Dark*` and plain respectively.
+fn color(word: &str) -> Option<Color> {
+    if let Some(hex) = word.strip_prefix('#') {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            6 => Some(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            }),
+            _ => None,
+        };
+    }
+    if let Ok(value) = word.parse() {
+        return Some(Color::AnsiValue(value));
+    }
+
+    Some(match word {
+        "black" => Color::Black,
+        "red" => Color::DarkRed,
+        "green" => Color::DarkGreen,
+        "yellow" => Color::DarkYellow,
+        "blue" => Color::DarkBlue,
+        "magenta" => Color::DarkMagenta,
+        "cyan" => Color::DarkCyan,
+        "white" => Color::Grey,
+        "brightblack" => Color::DarkGrey,
+        "brightred" => Color::Red,
+        "brightgreen" => Color::Green,
+        "brightyellow" => Color::Yellow,
+        "brightblue" => Color::Blue,
+        "brightmagenta" => Color::Magenta,
+        "brightcyan" => Color::Cyan,
+        "brightwhite" => Color::White,
+        _ => return None,
+    })
+}
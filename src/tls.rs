@@ -0,0 +1,187 @@
+//! git's TLS settings, applied to everything that talks HTTPS: the forge API
+//! requests made with curl, and libgit2's pushes and fetches. Self-hosted
+//! forges behind a private CA then work with whatever already makes `git
+//! fetch` work against them:
+//!
+//! ```ini
+//! [http "https://git.corp.example/"]
+//!     sslCAInfo = ~/certs/corp-root.pem
+//! ```
+//!
+//! `http.sslVerify`, `http.sslCAInfo`, `http.sslCAPath`, `http.sslCert`,
+//! `http.sslKey`, `http.sslBackend` and `http.schannelCheckRevoke` are read,
+//! the most specific `http.<url>.*` matching the URL winning over plain
+//! `http.*`, and `GIT_SSL_NO_VERIFY`, `GIT_SSL_CAINFO` and `GIT_SSL_CAPATH`
+//! override them like they do for git. libgit2 is built with one TLS
+//! library, so `http.sslBackend` and client certificates only reach curl.
+//! Nor can libgit2 unload CA certificates, so a remote whose CA settings
+//! differ from those of a remote used earlier in the process is refused
+//! rather than trusting the earlier remote's certificates as well.
+
+use crate::config::Config;
+use crate::Result;
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+pub struct Tls {
+    pub verify: bool,
+    ca_info: Option<PathBuf>,
+    ca_path: Option<PathBuf>,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    backend: Option<String>,
+    check_revoke: bool,
+}
+
+impl Tls {
+    /// The settings git would use for `url`.
+    pub fn for_url(config: &Config, url: &str) -> Tls {
+        let git = config.git();
+        let path = |key: &str| git.get_path(&setting(config, url, key)?).ok();
+        let bool = |key: &str| setting(config, url, key).and_then(|name| git.get_bool(&name).ok());
+        let env = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+        Tls {
+            verify: match env("GIT_SSL_NO_VERIFY") {
+                Some(_) => false,
+                None => bool("sslVerify").unwrap_or(true),
+            },
+            ca_info: env("GIT_SSL_CAINFO")
+                .map(PathBuf::from)
+                .or_else(|| path("sslCAInfo")),
+            ca_path: env("GIT_SSL_CAPATH")
+                .map(PathBuf::from)
+                .or_else(|| path("sslCAPath")),
+            cert: path("sslCert"),
+            key: path("sslKey"),
+            backend: setting(config, url, "sslBackend").and_then(|name| git.get_string(&name).ok()),
+            check_revoke: bool("schannelCheckRevoke").unwrap_or(true),
+        }
+    }
+
+    /// Adds the settings to a curl command line.
+    pub fn curl(&self, curl: &mut Command) {
+        if !self.verify {
+            curl.arg("--insecure");
+        }
+        if let Some(ca_info) = &self.ca_info {
+            curl.arg("--cacert").arg(ca_info);
+        }
+        if let Some(ca_path) = &self.ca_path {
+            curl.arg("--capath").arg(ca_path);
+        }
+        if let Some(cert) = &self.cert {
+            curl.arg("--cert").arg(cert);
+        }
+        if let Some(key) = &self.key {
+            curl.arg("--key").arg(key);
+        }
+        if !self.check_revoke {
+            curl.arg("--ssl-no-revoke");
+        }
+        // Only curl builds with several TLS libraries can switch, and they
+        // pick with this variable, the same way git does.
+        if let Some(backend) = &self.backend {
+            curl.env("CURL_SSL_BACKEND", backend);
+        }
+    }
+
+    /// Points libgit2 at the CA certificates, if any are set. libgit2 keeps
+    /// these for the whole process and can only add to them, so this is
+    /// done before each remote operation, and refuses a remote whose CA
+    /// settings differ from those of one set up earlier: it would trust the
+    /// earlier remote's certificates too.
+    pub fn configure_libgit2(&self) -> Result<()> {
+        let wanted = (self.ca_info.clone(), self.ca_path.clone());
+        let mut loaded = LOADED.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*loaded {
+            Some(loaded) if *loaded == wanted => return Ok(()),
+            Some((ca_info, ca_path)) => {
+                let location = ca_info.as_ref().or(ca_path.as_ref());
+                return Err(git2::Error::from_str(&format!(
+                    "this remote's CA settings differ from an earlier one's, and the \
+                     certificates at {} can't be unloaded; run again for this repository alone",
+                    location.map_or_else(String::new, |location| location.display().to_string())
+                ))
+                .into());
+            }
+            None if wanted == (None, None) => return Ok(()),
+            None => {}
+        }
+        let c_path = |path: &Option<PathBuf>| {
+            path.as_deref()
+                .map(|path| CString::new(path.to_string_lossy().into_owned()))
+                .transpose()
+        };
+        let file = c_path(&self.ca_info).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        let dir = c_path(&self.ca_path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+        // An untouched global option gets libgit2 initialised first.
+        git2::opts::enable_caching(true);
+        let result = unsafe {
+            libgit2_sys::git_libgit2_opts(
+                libgit2_sys::GIT_OPT_SET_SSL_CERT_LOCATIONS as c_int,
+                file.as_ref().map_or(std::ptr::null(), |file| file.as_ptr()),
+                dir.as_ref().map_or(std::ptr::null(), |dir| dir.as_ptr()),
+            )
+        };
+        if result < 0 {
+            let location = self.ca_info.as_ref().or(self.ca_path.as_ref());
+            return Err(git2::Error::from_str(&format!(
+                "can't use the CA certificates at {}",
+                location.map_or_else(String::new, |location| location.display().to_string())
+            ))
+            .into());
+        }
+        *loaded = Some(wanted);
+        Ok(())
+    }
+}
+
+/// The CA certificates libgit2 was pointed at, once a remote set any.
+static LOADED: Mutex<Option<(Option<PathBuf>, Option<PathBuf>)>> = Mutex::new(None);
+
+/// The name of the config variable that sets `key` for `url`: the
+/// `http.<url>.<key>` with the longest URL that `url` starts with, or plain
+/// `http.<key>`. A URL only matches up to a `/`, so `https://git.corp` doesn't
+/// cover `https://git.corporate.example`.
+fn setting(config: &Config, url: &str, key: &str) -> Option<String> {
+    let suffix = format!(".{}", key.to_lowercase());
+    let mut best: Option<(usize, String)> = None;
+
+    let entries = config.git().entries(Some("^http\\.")).ok()?;
+    while let Some(Ok(entry)) = (&entries).next() {
+        let name = match entry.name() {
+            Some(name) => name,
+            None => continue,
+        };
+        let prefix = match name
+            .strip_prefix("http.")
+            .and_then(|rest| rest.strip_suffix(&suffix))
+        {
+            Some(prefix) => prefix,
+            // `http.sslverify` has no URL in between.
+            None if name.eq_ignore_ascii_case(&format!("http{}", suffix)) => "",
+            None => continue,
+        };
+        if !prefix.is_empty() && !covers(prefix, url) {
+            continue;
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(length, _)| prefix.len() >= *length)
+        {
+            best = Some((prefix.len(), name.to_string()));
+        }
+    }
+    best.map(|(_, name)| name)
+}
+
+fn covers(prefix: &str, url: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    url.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
@@ -0,0 +1,78 @@
+//! Notes under `refs/notes/delete-branches` recording that a branch pointing
+//! at a commit was deleted, by whom, when and why. `git log
+//! --notes=delete-branches` shows them next to the commits.
+
+use crate::branch::Branch;
+use crate::Result;
+use chrono::Local;
+use git2::{Oid, Repository};
+
+pub const NOTES_REF: &str = "refs/notes/delete-branches";
+
+/// A note written for a deleted branch, remembering what it replaced so the
+/// deletion can be undone.
+pub struct Tombstone {
+    commit: Oid,
+    previous: Option<String>,
+}
+
+impl Tombstone {
+    /// Appends an entry for `branch` to the note on its tip commit.
+    pub fn write(repo: &Repository, branch: &Branch, reason: Option<&str>) -> Result<Tombstone> {
+        let signature = repo.signature()?;
+        let previous = read(repo, branch.id);
+
+        let mut entry = format!(
+            "Deleted branch: {}\nDeleted by: {} <{}>\nDeleted at: {}\n",
+            branch.name,
+            signature.name().unwrap_or_default(),
+            signature.email().unwrap_or_default(),
+            Local::now().format("%Y-%m-%d %H:%M:%S %z"),
+        );
+        if let Some(reason) = reason {
+            entry.push_str(&format!("Reason: {}\n", reason));
+        }
+
+        let note = match &previous {
+            Some(previous) => format!("{}\n{}", previous, entry),
+            None => entry,
+        };
+        repo.note(
+            &signature,
+            &signature,
+            Some(NOTES_REF),
+            branch.id,
+            &note,
+            true,
+        )?;
+
+        Ok(Tombstone {
+            commit: branch.id,
+            previous,
+        })
+    }
+
+    /// Puts the note back the way it was before [`Tombstone::write`].
+    pub fn undo(self, repo: &Repository) -> Result<()> {
+        let signature = repo.signature()?;
+        match self.previous {
+            Some(previous) => {
+                repo.note(
+                    &signature,
+                    &signature,
+                    Some(NOTES_REF),
+                    self.commit,
+                    &previous,
+                    true,
+                )?;
+            }
+            None => repo.note_delete(self.commit, Some(NOTES_REF), &signature, &signature)?,
+        }
+        Ok(())
+    }
+}
+
+fn read(repo: &Repository, commit: Oid) -> Option<String> {
+    let note = repo.find_note(Some(NOTES_REF), commit).ok()?;
+    note.message().map(|message| message.trim_end().to_string())
+}
@@ -0,0 +1,262 @@
+//! Branches deleted in earlier sessions, kept so they can still be brought
+//! back after the session's own undo is gone.
+//!
+//! Each OS user has a trash of their own, so people sharing a clone don't
+//! lose each other's deletions: `.git/delete-branches/users/<user>/trash.json`
+//! lists each branch they deleted with its tip, its `branch.<name>.*`
+//! config and when it was deleted, and a ref under
+//! `refs/delete-branches/trash/<user>/` holds on to every listed tip so
+//! `git gc` doesn't collect it. Entries older than
+//! `delete-branches.trashDays` (30 by default) are emptied out at the end of
+//! each of the user's sessions. The trash everyone shared before, in
+//! `.git/delete-branches/trash.json` with its refs straight under
+//! `refs/delete-branches/trash/`, is still listed and restored from.
+//!
+//! Before a session's deletions go to the trash there is a short grace
+//! window, `delete-branches.undoGrace` seconds (10 by default, 0 to skip
+//! it), in which `U` restores all of them at once.
+
+use crate::branch::Branch;
+use crate::branch_config::BranchConfig;
+use crate::config::Config;
+use crate::input::Key;
+use crate::json::Value;
+use crate::seatbelt::Seatbelt;
+use crate::session::Session;
+use crate::{state, Error, Result};
+use git2::{Oid, Repository};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const FILE: &str = "trash.json";
+
+pub const REF_PREFIX: &str = "refs/delete-branches/trash/";
+
+const DEFAULT_DAYS: i64 = 30;
+
+const DEFAULT_GRACE: u64 = 10;
+
+pub struct Trash {
+    path: PathBuf,
+    /// Whose trash it is, `None` for the shared one.
+    pub user: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+pub struct Entry {
+    pub name: String,
+    pub tip: Oid,
+    pub config: BranchConfig,
+    pub deleted_at: i64,
+}
+
+impl Trash {
+    /// Loads the user's trash, empty if nothing was ever put in it.
+    pub fn load(repo: &Repository) -> Result<Trash> {
+        Trash::of(repo, Some(state::user()))
+    }
+
+    /// Every trash there is, the user's own first and the shared one last.
+    pub fn all(repo: &Repository) -> Result<Vec<Trash>> {
+        let own = state::user();
+        let mut others = Vec::new();
+        if let Ok(users) = std::fs::read_dir(state::dir(repo).join("users")) {
+            for user in users {
+                let user = user?.file_name().to_string_lossy().into_owned();
+                if user != own && state::user_dir(repo, &user).join(FILE).exists() {
+                    others.push(user);
+                }
+            }
+        }
+        others.sort();
+
+        let mut trashes = vec![Trash::of(repo, Some(own))?];
+        for user in others {
+            trashes.push(Trash::of(repo, Some(user))?);
+        }
+        let shared = Trash::of(repo, None)?;
+        if !shared.entries.is_empty() {
+            trashes.push(shared);
+        }
+        Ok(trashes)
+    }
+
+    fn of(repo: &Repository, user: Option<String>) -> Result<Trash> {
+        let mut trash = Trash {
+            path: match &user {
+                Some(user) => state::user_dir(repo, user).join(FILE),
+                None => state::dir(repo).join(FILE),
+            },
+            user,
+            entries: Vec::new(),
+        };
+
+        let json = match state::read(&trash.path)? {
+            Some(contents) => Value::parse(&contents)
+                .map_err(|e| Error::InvalidValue(trash.path.display().to_string(), e))?,
+            None => return Ok(trash),
+        };
+
+        trash.entries = json
+            .get("branches")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| {
+                Some(Entry {
+                    name: entry.get("name")?.as_str()?.to_string(),
+                    tip: Oid::from_str(entry.get("tip")?.as_str()?).ok()?,
+                    // Entries from before the config was kept have none.
+                    config: entry
+                        .get("config")
+                        .map(BranchConfig::from_json)
+                        .unwrap_or_default(),
+                    deleted_at: entry.get("deleted_at")?.as_f64()? as i64,
+                })
+            })
+            .collect();
+
+        Ok(trash)
+    }
+
+    /// Adds a deleted branch, pinning its tip. Branches whose names aren't
+    /// valid UTF-8 can't be written to the trash and are left out.
+    pub fn add(&mut self, repo: &Repository, branch: &Branch, config: &BranchConfig) -> Result<()> {
+        if std::str::from_utf8(&branch.name_bytes).is_err() {
+            return Ok(());
+        }
+
+        repo.reference(
+            &format!("{}{}", self.ref_prefix(), branch.id),
+            branch.id,
+            true,
+            &format!("delete-branches: trash '{}'", branch.name),
+        )?;
+        self.entries.push(Entry {
+            name: branch.name.clone(),
+            tip: branch.id,
+            config: config.clone(),
+            deleted_at: chrono::Utc::now().timestamp(),
+        });
+        Ok(())
+    }
+
+    /// Drops entries older than `delete-branches.trashDays`, and the refs no
+    /// remaining entry needs.
+    pub fn purge(&mut self, repo: &Repository, config: &Config) -> Result<()> {
+        let key = "delete-branches.trashDays";
+        let days = match config.string(key) {
+            Some(value) => value
+                .parse::<i64>()
+                .map_err(|_| Error::InvalidValue(key.to_string(), value))?,
+            None => DEFAULT_DAYS,
+        };
+        let days = match Seatbelt::load(config, false)? {
+            Some(_) => days.max(DEFAULT_DAYS),
+            None => days,
+        };
+        let cutoff = chrono::Utc::now().timestamp() - days * 24 * 60 * 60;
+
+        self.entries.retain(|entry| entry.deleted_at >= cutoff);
+
+        let prefix = self.ref_prefix();
+        for reference in repo.references_glob(&format!("{}*", prefix))? {
+            let mut reference = reference?;
+            // The shared trash's prefix covers every user's refs too.
+            let theirs = reference
+                .name()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .is_none_or(|rest| rest.contains('/'));
+            if theirs {
+                continue;
+            }
+            let pinned = reference.target();
+            if !self.entries.iter().any(|entry| Some(entry.tip) == pinned) {
+                reference.delete()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ref_prefix(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}{}/", REF_PREFIX, user),
+            None => REF_PREFIX.to_string(),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let branches: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                Value::Object(vec![
+                    ("name".to_string(), entry.name.as_str().into()),
+                    ("tip".to_string(), entry.tip.to_string().into()),
+                    ("config".to_string(), entry.config.to_json()),
+                    ("deleted_at".to_string(), entry.deleted_at.into()),
+                ])
+            })
+            .collect();
+
+        let json = Value::Object(vec![("branches".to_string(), branches.into())]);
+        state::write(&self.path, &json.to_string())
+    }
+}
+
+impl Session<'_> {
+    /// Ends the session's undo: offers to restore every deletion at once,
+    /// then moves whatever is still deleted to the trash.
+    pub fn finish_deletions(&mut self) -> Result<()> {
+        if self.deletions.is_empty() {
+            return Ok(());
+        }
+
+        let key = "delete-branches.undoGrace";
+        let grace = match self.config.string(key) {
+            Some(value) => value
+                .parse::<u64>()
+                .map_err(|_| Error::InvalidValue(key.to_string(), value))?,
+            None => DEFAULT_GRACE,
+        };
+
+        if grace > 0 && self.input.reads_keys() {
+            write!(
+                self.stdout,
+                "Press U within {} seconds to restore all {} deleted branch(es) > ",
+                grace,
+                self.deletions.len()
+            )?;
+            self.stdout.flush()?;
+
+            match self.input.key_within(Duration::from_secs(grace))? {
+                Some(Key::Char('U')) => {
+                    write!(self.stdout, "U\r\n")?;
+                    for deletion in std::mem::take(&mut self.deletions).into_iter().rev() {
+                        self.undo(deletion)?;
+                    }
+                    // Whatever couldn't be restored still goes to the trash.
+                    if self.deletions.is_empty() {
+                        return Ok(());
+                    }
+                }
+                _ => write!(self.stdout, "\r\n")?,
+            }
+        }
+
+        let deletions = std::mem::take(&mut self.deletions);
+        let repo = match self.backend.repo() {
+            Some(repo) => repo,
+            None => return Ok(()),
+        };
+
+        let mut trash = Trash::load(repo)?;
+        for deletion in &deletions {
+            trash.add(repo, &deletion.branch, &deletion.branch_config)?;
+        }
+        trash.purge(repo, &self.config)?;
+        trash.save()
+    }
+}
@@ -0,0 +1,129 @@
+//! `--upload-report <url>`: POSTs a JSON report of the run to an endpoint
+//! once it is over, for organizations collecting branch-hygiene activity
+//! from many repositories:
+//!
+//! ```json
+//! {"run":"5f0c…","time":1700000000,"user":"ci","repositories":["/src/app/.git/"],"status":"done","exit_status":0,"deleted":["topic"],"kept":[],…}
+//! ```
+//!
+//! The token is sent as `Authorization: Bearer`, taken from
+//! `DELETE_BRANCHES_REPORT_TOKEN` or else from git's credential helper for
+//! the URL. The request goes through curl with git's TLS settings, like the
+//! forge API requests, and the token and report go through stdin rather than
+//! argv so neither shows up in the process list.
+//!
+//! A report that can't be uploaded is a warning: the branches are dealt with
+//! by then, and the exit status still says how that went.
+
+use crate::config::Config;
+use crate::json::Value;
+use crate::session::Summary;
+use crate::tls::Tls;
+use crate::{run, state, Error, Result, Status};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const TOKEN_VAR: &str = "DELETE_BRANCHES_REPORT_TOKEN";
+
+/// How long the endpoint gets before the upload is given up on, in seconds.
+const TIMEOUT: u32 = 30;
+
+/// The report of a run over `repositories` that ended with `status`.
+pub fn report(summary: &Summary, repositories: Vec<String>, status: Status) -> Value {
+    let status_name = match status {
+        Status::Done => "done",
+        Status::NothingMatched => "nothing_matched",
+        Status::DeletionsFailed => "deletions_failed",
+    };
+    Value::Object(vec![
+        ("run".to_string(), run::id().into()),
+        ("time".to_string(), chrono::Utc::now().timestamp().into()),
+        ("user".to_string(), state::user().into()),
+        ("repositories".to_string(), repositories.into()),
+        ("status".to_string(), status_name.into()),
+        ("exit_status".to_string(), (status as i64).into()),
+        ("deleted".to_string(), summary.deleted.clone().into()),
+        ("kept".to_string(), summary.kept.clone().into()),
+        ("snoozed".to_string(), summary.snoozed.clone().into()),
+        ("renamed".to_string(), summary.renamed.clone().into()),
+        ("published".to_string(), summary.published.clone().into()),
+        (
+            "deleted_upstreams".to_string(),
+            summary.deleted_upstreams.clone().into(),
+        ),
+        (
+            "archive_tags".to_string(),
+            summary.archive_tags.clone().into(),
+        ),
+        ("companions".to_string(), summary.companions.clone().into()),
+        ("pruned".to_string(), summary.pruned.clone().into()),
+        ("failed".to_string(), summary.failures().into()),
+    ])
+}
+
+/// POSTs `report` to `url`.
+pub fn upload(url: &str, config: &Config, report: &Value) -> Result<()> {
+    let mut curl = Command::new("curl");
+    curl.args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .args(["--max-time", &TIMEOUT.to_string()]);
+    Tls::for_url(config, url).curl(&mut curl);
+    let mut curl = curl
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Upload(format!("running curl: {}", e)))?;
+
+    if let Some(mut stdin) = curl.stdin.take() {
+        writeln!(stdin, "url = {}", quote(url))?;
+        writeln!(stdin, "request = \"POST\"")?;
+        writeln!(stdin, "header = \"Content-Type: application/json\"")?;
+        if let Some(token) = token(config, url) {
+            writeln!(
+                stdin,
+                "header = {}",
+                quote(&format!("Authorization: Bearer {}", token))
+            )?;
+        }
+        writeln!(stdin, "data-binary = {}", quote(&report.to_string()))?;
+    }
+
+    let output = curl.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Upload(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn token(config: &Config, url: &str) -> Option<String> {
+    std::env::var(TOKEN_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .or_else(|| {
+            git2::CredentialHelper::new(url)
+                .config(config.git())
+                .execute()
+                .map(|(_, password)| password)
+        })
+}
+
+/// `value` as a string in a curl config file.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,96 @@
+//! `--interactive-rebase-safe`: spots branches with work that deleting them
+//! would strand, namely stash entries made on them and tips that are still
+//! `WIP`, `fixup!` or `squash!` commits waiting for an interactive rebase.
+
+use crate::branch::Branch;
+use crate::encoding::Decoder;
+use crate::session::Session;
+use crate::Result;
+use git2::Repository;
+use std::io::Write;
+
+/// Subjects of commits that aren't meant to stay as they are.
+const WIP_PREFIXES: &[&str] = &["fixup!", "squash!", "amend!"];
+
+/// Fills in `stashes` and `wip_commit` for every branch.
+pub fn annotate(repo: &Repository, branches: &mut [Branch]) -> Result<()> {
+    // Listing stashes needs a mutable handle, which the backend doesn't hand
+    // out, so use one of our own.
+    let mut stash_repo = Repository::open(repo.path())?;
+    let decoder = Decoder::new(repo);
+    let mut stashes = Vec::new();
+    stash_repo.stash_foreach(|index, message, id| {
+        stashes.push((index, message.to_string(), *id));
+        true
+    })?;
+
+    for branch in branches.iter_mut() {
+        for (index, message, id) in &stashes {
+            // `WIP on <branch>: ...` or `On <branch>: ...`. Stashes made on a
+            // detached HEAD are matched by the commit they were made on.
+            let stashed_on = message
+                .strip_prefix("WIP on ")
+                .or_else(|| message.strip_prefix("On "))
+                .and_then(|rest| rest.split_once(':'))
+                .map(|(name, _)| name);
+            let matches = match stashed_on {
+                Some("(no branch)") | None => {
+                    repo.find_commit(*id)
+                        .ok()
+                        .and_then(|stash| stash.parent_id(0).ok())
+                        == Some(branch.id)
+                }
+                Some(name) => name == branch.name,
+            };
+
+            if matches {
+                branch.stashes.push(format!("stash@{{{}}}", index));
+            }
+        }
+
+        let commit = repo.find_commit(branch.id)?;
+        let summary = decoder.subject(&commit);
+        branch.wip_commit = summary
+            .get(..3)
+            .is_some_and(|start| start.eq_ignore_ascii_case("wip"))
+            || WIP_PREFIXES
+                .iter()
+                .any(|prefix| summary.starts_with(prefix));
+    }
+
+    Ok(())
+}
+
+impl Session<'_> {
+    /// Whether `branch` may be deleted. Branches holding stashed or
+    /// unfinished work need their name typed out first.
+    pub fn force_confirmed(&mut self, branch: &Branch) -> Result<bool> {
+        let mut reasons = Vec::new();
+        if !branch.stashes.is_empty() {
+            reasons.push(format!(
+                "has stashed changes ({})",
+                branch.stashes.join(", ")
+            ));
+        }
+        if branch.wip_commit {
+            reasons.push("ends in a WIP/fixup commit".to_string());
+        }
+        if reasons.is_empty() {
+            return Ok(true);
+        }
+
+        write!(
+            self.stdout,
+            "'{}' {}. Type its name to delete it anyway > ",
+            branch.name,
+            reasons.join(" and ")
+        )?;
+        self.stdout.flush()?;
+
+        let confirmed = self.input.read_line(&mut self.stdout)?.as_deref() == Some(&branch.name);
+        if !confirmed {
+            write!(self.stdout, "Not deleting '{}'\r\n", branch.name)?;
+        }
+        Ok(confirmed)
+    }
+}
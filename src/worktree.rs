@@ -0,0 +1,44 @@
+use crate::Result;
+use git2::Repository;
+use std::path::PathBuf;
+
+/// A linked worktree and the branch it has checked out, if any.
+pub struct Worktree {
+    pub name: String,
+    pub path: PathBuf,
+    pub branch: Option<String>,
+    /// The worktree directory is gone and `git worktree prune` would drop it.
+    pub prunable: bool,
+}
+
+pub fn get_worktrees(repo: &Repository) -> Result<Vec<Worktree>> {
+    let mut worktrees = Vec::new();
+
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        let prunable = worktree.validate().is_err();
+
+        let branch = if prunable {
+            None
+        } else {
+            Repository::open_from_worktree(&worktree)
+                .and_then(|repo| {
+                    let head = repo.head()?;
+                    Ok(head
+                        .is_branch()
+                        .then(|| head.shorthand().map(String::from))
+                        .flatten())
+                })
+                .unwrap_or(None)
+        };
+
+        worktrees.push(Worktree {
+            name: name.to_string(),
+            path: worktree.path().to_path_buf(),
+            branch,
+            prunable,
+        });
+    }
+
+    Ok(worktrees)
+}